@@ -0,0 +1,27 @@
+use anyhow::Result;
+use helm_tests::branch_coverage::{load_branch_coverage, BRANCH_COVERAGE_ENV};
+use helm_tests::chart::list_charts;
+use std::path::PathBuf;
+
+/// Print the branch coverage recorded by a test run and write it to `lcov.info`
+fn main() -> Result<()> {
+    let dir = std::env::var_os(BRANCH_COVERAGE_ENV)
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow::anyhow!("{} is not set", BRANCH_COVERAGE_ENV))?;
+
+    let mut lcov = String::new();
+    for chart in list_charts("../charts/")? {
+        let chart_path = chart.path.to_string_lossy();
+        let Ok(coverage) = load_branch_coverage(&dir, &chart_path) else {
+            println!("{}: no branch coverage recorded", chart.metadata.name);
+            continue;
+        };
+        println!("== {} ==\n{}", chart.metadata.name, coverage.to_text());
+        lcov.push_str(&coverage.to_lcov());
+    }
+
+    let lcov_path = dir.join("lcov.info");
+    std::fs::write(&lcov_path, lcov)?;
+    println!("lcov report written to {}", lcov_path.display());
+    Ok(())
+}
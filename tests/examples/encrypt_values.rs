@@ -0,0 +1,25 @@
+use anyhow::Result;
+use helm_tests::sops::encrypt_sops_values;
+
+/// Encrypt a plaintext values fixture for age recipients in the SOPS format, so the encrypted
+/// production values layout can be render-tested with `run_helm_template_with_sops_values`
+///
+/// Usage: cargo run --example encrypt_values -- <values.yaml> <age1…> [age1…] > values.enc.yaml
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some((values_path, recipients)) = args.split_first().filter(|(_, r)| !r.is_empty()) else {
+        anyhow::bail!("usage: encrypt_values <values.yaml> <age-recipient>...");
+    };
+    let recipients = recipients
+        .iter()
+        .map(|r| {
+            r.parse()
+                .map_err(|e| anyhow::anyhow!("invalid age recipient {}: {}", r, e))
+        })
+        .collect::<Result<Vec<age::x25519::Recipient>>>()?;
+    print!(
+        "{}",
+        encrypt_sops_values(&std::fs::read_to_string(values_path)?, &recipients)?
+    );
+    Ok(())
+}
@@ -0,0 +1,16 @@
+use anyhow::Result;
+use helm_tests::values_schema::generate_chart_values_schema;
+use std::path::Path;
+
+/// Print the JSON Schema inferred from a chart's values.yaml, optionally refined by a merge patch file
+///
+/// Usage: cargo run --example generate_values_schema -- <chart-dir> [refinement.json] > values.schema.json
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(chart_path) = args.first() else {
+        anyhow::bail!("usage: generate_values_schema <chart-dir> [refinement.json]");
+    };
+    let schema = generate_chart_values_schema(chart_path, args.get(1).map(Path::new))?;
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    Ok(())
+}
@@ -0,0 +1,22 @@
+use anyhow::Result;
+use helm_tests::required_values::detect_required_values;
+use std::collections::HashMap;
+
+/// Print the values whose absence renders empty Secret data, env values or image tags, as JSON
+///
+/// Usage: cargo run --example required_values -- <chart-dir> [key=value ...] > required-values.json
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(chart_path) = args.first() else {
+        anyhow::bail!("usage: required_values <chart-dir> [key=value ...]");
+    };
+    let mut fixture = HashMap::new();
+    for arg in &args[1..] {
+        let Some((key, value)) = arg.split_once('=') else {
+            anyhow::bail!("expected key=value, got {}", arg);
+        };
+        fixture.insert(key.to_string(), value.to_string());
+    }
+    println!("{}", detect_required_values(chart_path, &fixture)?.to_json()?);
+    Ok(())
+}
@@ -0,0 +1,381 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+use crate::chart::ChartMetadata;
+use crate::{render_chart_uninstrumented, RenderArgs};
+use crate::template::{chart_template_files, scan_template, RawAction, Segment};
+
+/// Environment variable naming the directory branch coverage is recorded in
+pub const BRANCH_COVERAGE_ENV: &str = "HELM_TESTS_BRANCH_COVERAGE";
+
+/// Prefix of the keys the instrumentation sets on the root context
+const MARKER_PREFIX: &str = "__cov_";
+
+/// Template added to instrumented charts; it sorts first and is therefore rendered last
+const EMITTER_FILE: &str = "templates/00-branch-coverage.yaml";
+
+/// Comment line the emitter template writes the hit branch ids to
+const EMITTER_LINE: &str = "# helm-tests-branch-coverage:";
+
+const EMITTER_TEMPLATE: &str = r#"{{- $hits := list }}
+{{- range $key, $_ := $ }}
+{{- if hasPrefix "__cov_" $key }}
+{{- $hits = append $hits (trimPrefix "__cov_" $key) }}
+{{- end }}
+{{- end }}
+# helm-tests-branch-coverage: {{ join "," $hits }}
+"#;
+
+/// One branch of an `if`, `with` or `range` block in a chart template
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Branch {
+    pub id: usize,
+    /// Template path relative to the chart root
+    pub file: String,
+    pub line: usize,
+    /// Id of the block the branch belongs to, unique within the chart
+    pub block: usize,
+    /// Position of the branch within its block, the block head being 0
+    pub index: usize,
+    pub label: String,
+}
+
+/// A copy of a chart whose templates record which branches they take
+pub struct InstrumentedChart {
+    _dir: TempDir,
+    pub path: PathBuf,
+    pub chart_name: String,
+    pub branches: Vec<Branch>,
+}
+
+enum Frame {
+    Instrumented { block: usize, label: String, branches: usize, has_else: bool },
+    Plain,
+}
+
+fn marker(id: usize, trim_right: bool) -> String {
+    format!(
+        "{{{{ if kindIs \"map\" $ }}}}{{{{ $_ := set $ \"{}{}\" true }}}}{{{{ end {}}}}}",
+        MARKER_PREFIX,
+        id,
+        if trim_right { "-" } else { "" }
+    )
+}
+
+fn keyword(action: &RawAction) -> &str {
+    action
+        .text
+        .split(|c: char| c.is_whitespace() || c == '(')
+        .next()
+        .unwrap_or("")
+}
+
+/// Insert branch markers into a template source, appending the branches found to `branches`
+pub fn instrument_template(source: &str, file: &str, branches: &mut Vec<Branch>) -> Result<String> {
+    let mut inserts: Vec<(usize, String)> = Vec::new();
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut next_block = branches.iter().map(|b| b.block + 1).max().unwrap_or(0);
+
+    let add_branch = |branches: &mut Vec<Branch>, block: usize, index: usize, line: usize, label: String| {
+        let id = branches.len();
+        branches.push(Branch {
+            id,
+            file: file.to_string(),
+            line,
+            block,
+            index,
+            label,
+        });
+        id
+    };
+
+    for segment in scan_template(source)? {
+        let Segment::Action(action) = segment else {
+            continue;
+        };
+        let in_define = stack.iter().any(|frame| matches!(frame, Frame::Plain));
+        match keyword(&action) {
+            "if" | "with" | "range" if !in_define => {
+                let block = next_block;
+                next_block += 1;
+                let id = add_branch(branches, block, 0, action.line, action.text.clone());
+                inserts.push((action.end, marker(id, action.trim_right)));
+                stack.push(Frame::Instrumented {
+                    block,
+                    label: action.text.clone(),
+                    branches: 1,
+                    has_else: false,
+                });
+            }
+            "if" | "with" | "range" | "define" | "block" => stack.push(Frame::Plain),
+            "else" => {
+                if let Some(Frame::Instrumented {
+                    block,
+                    branches: count,
+                    has_else,
+                    ..
+                }) = stack.last_mut()
+                {
+                    *has_else = action.text == "else";
+                    let id = add_branch(branches, *block, *count, action.line, action.text.clone());
+                    *count += 1;
+                    inserts.push((action.end, marker(id, action.trim_right)));
+                }
+            }
+            "end" => match stack.pop() {
+                Some(Frame::Instrumented {
+                    block,
+                    label,
+                    branches: count,
+                    has_else: false,
+                }) => {
+                    let id = add_branch(branches, block, count, action.line, format!("else (implicit) of {}", label));
+                    let open = if action.trim_left { "{{- else }}" } else { "{{ else }}" };
+                    inserts.push((action.start, format!("{}{}", open, marker(id, false))));
+                }
+                Some(_) => {}
+                None => anyhow::bail!("{}:{}: unexpected {{{{end}}}}", file, action.line),
+            },
+            _ => {}
+        }
+    }
+    if !stack.is_empty() {
+        anyhow::bail!("{}: unclosed block", file);
+    }
+
+    let mut instrumented = String::with_capacity(source.len());
+    let mut copied = 0;
+    inserts.sort_by_key(|(offset, _)| *offset);
+    for (offset, text) in inserts {
+        instrumented.push_str(&source[copied..offset]);
+        instrumented.push_str(&text);
+        copied = offset;
+    }
+    instrumented.push_str(&source[copied..]);
+    Ok(instrumented)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy a chart directory into a temp dir and instrument its templates
+pub fn instrument_chart(chart_path: &str) -> Result<InstrumentedChart> {
+    let chart_dir = Path::new(chart_path);
+    let chart_name = ChartMetadata::load(chart_dir)?.name;
+    let dir_name = chart_dir
+        .canonicalize()?
+        .file_name()
+        .map(|n| n.to_os_string())
+        .ok_or_else(|| anyhow::anyhow!("Cannot determine directory name of {}", chart_path))?;
+
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join(dir_name);
+    copy_dir(chart_dir, &path)?;
+
+    let mut branches = Vec::new();
+    for file in chart_template_files(chart_dir)? {
+        let instrumentable = [".yaml", ".yml", ".tpl", ".txt"].iter().any(|ext| file.ends_with(ext));
+        if !instrumentable {
+            continue;
+        }
+        let source = std::fs::read_to_string(chart_dir.join(&file))?;
+        let instrumented = instrument_template(&source, &file, &mut branches)?;
+        std::fs::write(path.join(&file), instrumented)?;
+    }
+    std::fs::write(path.join(EMITTER_FILE), EMITTER_TEMPLATE)?;
+
+    Ok(InstrumentedChart {
+        _dir: dir,
+        path,
+        chart_name,
+        branches,
+    })
+}
+
+impl InstrumentedChart {
+    /// Render the instrumented chart, returning the output without the coverage document
+    /// together with the ids of the branches taken
+    pub fn render(&self, values: Option<&HashMap<String, String>>) -> Result<(String, BTreeSet<usize>)> {
+        self.render_with(&RenderArgs { values, ..Default::default() })
+    }
+
+    fn render_with(&self, args: &RenderArgs) -> Result<(String, BTreeSet<usize>)> {
+        let output = render_chart_uninstrumented(&self.path.to_string_lossy(), args)?;
+        let source = format!("# Source: {}/{}", self.chart_name, EMITTER_FILE);
+        let position = output
+            .find(&source)
+            .ok_or_else(|| anyhow::anyhow!("Rendered output has no branch coverage document"))?;
+        let start = output[..position].rfind("---").unwrap_or(position);
+        let end = output[position..]
+            .find("\n---")
+            .map(|i| position + i + 1)
+            .unwrap_or(output.len());
+
+        let hits = output[position..end]
+            .lines()
+            .find_map(|line| line.strip_prefix(EMITTER_LINE))
+            .map(|ids| {
+                ids.split(',')
+                    .filter_map(|id| id.trim().parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok((format!("{}{}", &output[..start], &output[end..]), hits))
+    }
+}
+
+/// Hit counts of the branches of one chart, accumulated over any number of renders
+#[derive(Debug, Clone)]
+pub struct BranchCoverage {
+    pub chart_path: PathBuf,
+    pub branches: Vec<Branch>,
+    pub hits: BTreeMap<usize, u64>,
+}
+
+impl BranchCoverage {
+    pub fn new(chart_path: &Path, branches: Vec<Branch>) -> Self {
+        BranchCoverage {
+            chart_path: chart_path.to_path_buf(),
+            branches,
+            hits: BTreeMap::new(),
+        }
+    }
+
+    /// Record the branches taken by one render
+    pub fn record<'a>(&mut self, ids: impl IntoIterator<Item = &'a usize>) {
+        for id in ids {
+            *self.hits.entry(*id).or_default() += 1;
+        }
+    }
+
+    pub fn count(&self, id: usize) -> u64 {
+        self.hits.get(&id).copied().unwrap_or(0)
+    }
+
+    /// Branches never taken by any recorded render
+    pub fn missed(&self) -> Vec<&Branch> {
+        self.branches.iter().filter(|b| self.count(b.id) == 0).collect()
+    }
+
+    fn by_file(&self) -> BTreeMap<&str, Vec<&Branch>> {
+        let mut files: BTreeMap<&str, Vec<&Branch>> = BTreeMap::new();
+        for branch in &self.branches {
+            files.entry(branch.file.as_str()).or_default().push(branch);
+        }
+        files
+    }
+
+    /// Plain text report listing every branch with its hit count
+    pub fn to_text(&self) -> String {
+        let mut report = String::new();
+        for (file, branches) in self.by_file() {
+            let covered = branches.iter().filter(|b| self.count(b.id) > 0).count();
+            report.push_str(&format!("{}: {}/{} branches covered\n", file, covered, branches.len()));
+            for branch in branches {
+                let count = self.count(branch.id);
+                report.push_str(&format!(
+                    "  {} {:>4}  {:>5}  {}\n",
+                    if count > 0 { "+" } else { "-" },
+                    branch.line,
+                    count,
+                    branch.label
+                ));
+            }
+        }
+        let covered = self.branches.iter().filter(|b| self.count(b.id) > 0).count();
+        report.push_str(&format!("total: {}/{} branches covered\n", covered, self.branches.len()));
+        report
+    }
+
+    /// Report in lcov tracefile format
+    pub fn to_lcov(&self) -> String {
+        let root = self.chart_path.canonicalize().unwrap_or_else(|_| self.chart_path.clone());
+        let mut report = String::from("TN:\n");
+        for (file, branches) in self.by_file() {
+            report.push_str(&format!("SF:{}\n", root.join(file).display()));
+            let blocks_taken: BTreeSet<usize> = branches
+                .iter()
+                .filter(|b| self.count(b.id) > 0)
+                .map(|b| b.block)
+                .collect();
+            for branch in &branches {
+                let count = if blocks_taken.contains(&branch.block) {
+                    self.count(branch.id).to_string()
+                } else {
+                    "-".to_string()
+                };
+                report.push_str(&format!("BRDA:{},{},{},{}\n", branch.line, branch.block, branch.index, count));
+            }
+            let hit = branches.iter().filter(|b| self.count(b.id) > 0).count();
+            report.push_str(&format!("BRF:{}\nBRH:{}\nend_of_record\n", branches.len(), hit));
+        }
+        report
+    }
+}
+
+fn coverage_files(dir: &Path, chart_path: &str) -> Result<(PathBuf, PathBuf)> {
+    let name = Path::new(chart_path)
+        .canonicalize()?
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| anyhow::anyhow!("Cannot determine directory name of {}", chart_path))?;
+    Ok((
+        dir.join(format!("{}.branches.json", name)),
+        dir.join(format!("{}.hits", name)),
+    ))
+}
+
+/// Render a chart through an instrumented copy, recording the branch table and the branches
+/// taken in `dir`
+pub(crate) fn render_with_branch_coverage(dir: &Path, chart_path: &str, args: &RenderArgs) -> Result<String> {
+    let chart = instrument_chart(chart_path)?;
+    let (output, hits) = chart.render_with(args)?;
+
+    std::fs::create_dir_all(dir)?;
+    let (branches_file, hits_file) = coverage_files(dir, chart_path)?;
+    let mut table = tempfile::NamedTempFile::new_in(dir)?;
+    serde_json::to_writer_pretty(&mut table, &chart.branches)?;
+    table.persist(&branches_file)?;
+
+    let line = hits.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",");
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&hits_file)?
+        .write_all(format!("{}\n", line).as_bytes())?;
+
+    Ok(output)
+}
+
+/// Load the branch coverage recorded in `dir` for a chart
+pub fn load_branch_coverage(dir: &Path, chart_path: &str) -> Result<BranchCoverage> {
+    let (branches_file, hits_file) = coverage_files(dir, chart_path)?;
+    let branches: Vec<Branch> = serde_json::from_str(&std::fs::read_to_string(&branches_file).map_err(|e| {
+        anyhow::anyhow!("Cannot read {}: {}", branches_file.display(), e)
+    })?)?;
+
+    let mut coverage = BranchCoverage::new(Path::new(chart_path), branches);
+    if let Ok(content) = std::fs::read_to_string(&hits_file) {
+        for line in content.lines() {
+            let ids: Vec<usize> = line.split(',').filter_map(|id| id.trim().parse().ok()).collect();
+            coverage.record(&ids);
+        }
+    }
+    Ok(coverage)
+}
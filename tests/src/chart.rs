@@ -0,0 +1,320 @@
+use anyhow::Result;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::Severity;
+
+/// Description left in Chart.yaml by `helm create`
+const SCAFFOLD_DESCRIPTION: &str = "A Helm chart for Kubernetes";
+
+/// appVersion left in Chart.yaml by `helm create`
+const SCAFFOLD_APP_VERSION: &str = "1.16.0";
+
+/// Typed view of a chart's Chart.yaml
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChartMetadata {
+    #[serde(default)]
+    pub api_version: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, deserialize_with = "scalar_string")]
+    pub version: String,
+    #[serde(default, deserialize_with = "optional_scalar_string")]
+    pub app_version: Option<String>,
+    pub description: Option<String>,
+    #[serde(rename = "type")]
+    pub chart_type: Option<String>,
+    pub kube_version: Option<String>,
+    pub home: Option<String>,
+    #[serde(default)]
+    pub sources: Vec<String>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub dependencies: Vec<ChartDependency>,
+    #[serde(default)]
+    pub maintainers: Vec<Maintainer>,
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub deprecated: bool,
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// An entry of Chart.yaml `dependencies`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChartDependency {
+    pub name: String,
+    #[serde(default, deserialize_with = "scalar_string")]
+    pub version: String,
+    pub repository: Option<String>,
+    pub condition: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub alias: Option<String>,
+}
+
+/// An entry of Chart.yaml `maintainers`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Maintainer {
+    #[serde(default)]
+    pub name: String,
+    pub email: Option<String>,
+    pub url: Option<String>,
+}
+
+/// A chart directory together with its parsed metadata
+#[derive(Debug, Clone)]
+pub struct ChartInfo {
+    pub path: PathBuf,
+    pub metadata: ChartMetadata,
+}
+
+/// A problem found while validating Chart.yaml
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChartIssue {
+    pub severity: Severity,
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for ChartIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.severity, self.field, self.message)
+    }
+}
+
+fn scalar_to_string(value: serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(s) => Some(s),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn scalar_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(optional_scalar_string(deserializer)?.unwrap_or_default())
+}
+
+fn optional_scalar_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    let value = serde_yaml::Value::deserialize(deserializer)?;
+    Ok(scalar_to_string(value))
+}
+
+impl ChartMetadata {
+    /// Parse the contents of a Chart.yaml file
+    pub fn parse(content: &str) -> Result<Self> {
+        Ok(serde_yaml::from_str(content)?)
+    }
+
+    /// Load Chart.yaml from a chart directory
+    pub fn load(chart_dir: &Path) -> Result<Self> {
+        let path = chart_dir.join("Chart.yaml");
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+        Self::parse(&content)
+    }
+
+    /// The chart version parsed as semver
+    pub fn semver(&self) -> Result<Version> {
+        Version::parse(&self.version)
+            .map_err(|e| anyhow::anyhow!("Chart version '{}' is not valid semver: {}", self.version, e))
+    }
+
+    /// Whether this is an application chart (the default when `type` is absent)
+    pub fn is_application(&self) -> bool {
+        self.chart_type.as_deref().unwrap_or("application") == "application"
+    }
+
+    /// Validate the metadata of a chart living in `chart_dir`
+    pub fn validate(&self, chart_dir: &Path) -> Vec<ChartIssue> {
+        let mut issues = Vec::new();
+        let mut report = |severity: Severity, field: &str, message: String| {
+            issues.push(ChartIssue {
+                severity,
+                field: field.to_string(),
+                message,
+            })
+        };
+
+        if self.api_version != "v2" {
+            report(
+                Severity::Error,
+                "apiVersion",
+                format!("expected 'v2', got '{}'", self.api_version),
+            );
+        }
+
+        let dir_name = chart_dir
+            .canonicalize()
+            .ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()));
+        if self.name.is_empty() {
+            report(Severity::Error, "name", "name is required".to_string());
+        } else if let Some(dir_name) = dir_name.filter(|d| *d != self.name) {
+            report(
+                Severity::Error,
+                "name",
+                format!("name '{}' does not match directory '{}'", self.name, dir_name),
+            );
+        }
+
+        if let Err(e) = self.semver() {
+            report(Severity::Error, "version", e.to_string());
+        }
+
+        match self.chart_type.as_deref() {
+            None | Some("application") | Some("library") => {}
+            Some(other) => report(
+                Severity::Error,
+                "type",
+                format!("type must be 'application' or 'library', got '{}'", other),
+            ),
+        }
+
+        if let Some(kube_version) = &self.kube_version {
+            if let Err(e) = parse_version_constraint(kube_version) {
+                report(Severity::Error, "kubeVersion", e.to_string());
+            }
+        }
+
+        if self.is_application() {
+            match self.description.as_deref().map(str::trim) {
+                None | Some("") => report(
+                    Severity::Warning,
+                    "description",
+                    "application charts should have a description".to_string(),
+                ),
+                Some(SCAFFOLD_DESCRIPTION) => report(
+                    Severity::Warning,
+                    "description",
+                    format!("'{}' is the helm create placeholder", SCAFFOLD_DESCRIPTION),
+                ),
+                Some(_) => {}
+            }
+
+            match self.app_version.as_deref() {
+                None => report(
+                    Severity::Info,
+                    "appVersion",
+                    "application charts should set appVersion".to_string(),
+                ),
+                Some(SCAFFOLD_APP_VERSION) => report(
+                    Severity::Warning,
+                    "appVersion",
+                    format!("'{}' is the helm create placeholder", SCAFFOLD_APP_VERSION),
+                ),
+                Some(_) => {}
+            }
+        }
+
+        for dependency in &self.dependencies {
+            let field = format!("dependencies[{}]", dependency.name);
+            if dependency.name.is_empty() {
+                report(Severity::Error, "dependencies", "dependency without a name".to_string());
+            }
+            if let Err(e) = parse_version_constraint(&dependency.version) {
+                report(Severity::Error, &field, e.to_string());
+            }
+        }
+
+        for (index, maintainer) in self.maintainers.iter().enumerate() {
+            if maintainer.name.is_empty() {
+                report(
+                    Severity::Error,
+                    &format!("maintainers[{}]", index),
+                    "maintainer without a name".to_string(),
+                );
+            }
+        }
+
+        if self.icon.is_none() {
+            report(Severity::Info, "icon", "icon is recommended".to_string());
+        }
+
+        issues
+    }
+}
+
+/// One Masterminds comparator in `semver` syntax: a bare version means `=` there (`1.28` is 1.28.x
+/// only), not caret, and versions may carry a leading `v`
+fn normalize_comparator(comparator: &str) -> String {
+    let comparator = comparator.replace("=>", ">=").replace("=<", "<=");
+    let version_start = comparator.find(|c: char| !"<>=!~^".contains(c)).unwrap_or(comparator.len());
+    let (operator, version) = comparator.split_at(version_start);
+    let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+    let wildcard = version.split('.').any(|part| matches!(part, "*" | "x" | "X"));
+    let operator = if operator.is_empty() && !wildcard { "=" } else { operator };
+    format!("{}{}", operator, version)
+}
+
+/// Parse a Helm (Masterminds) version constraint such as `>=1.19.0-0 <1.31` or `~1.20 || ^1.25`
+pub fn parse_version_constraint(constraint: &str) -> Result<Vec<VersionReq>> {
+    if constraint.trim().is_empty() {
+        anyhow::bail!("version constraint is empty");
+    }
+
+    constraint
+        .split("||")
+        .map(|alternative| {
+            let alternative = alternative.trim();
+            let comparators = match alternative.split_once(" - ") {
+                Some((low, high)) => format!(
+                    ">={}, <={}",
+                    normalize_comparator(low.trim()).trim_start_matches('='),
+                    normalize_comparator(high.trim()).trim_start_matches('=')
+                ),
+                None => {
+                    let mut parts: Vec<String> = Vec::new();
+                    for token in alternative.split([',', ' ']).filter(|t| !t.is_empty()) {
+                        // Operators may be separated from their version by a space: ">= 1.19"
+                        match parts.last_mut() {
+                            Some(last) if last.chars().all(|c| "<>=!~^".contains(c)) => last.push_str(token),
+                            _ => parts.push(token.to_string()),
+                        }
+                    }
+                    parts.iter().map(|part| normalize_comparator(part)).collect::<Vec<_>>().join(", ")
+                }
+            };
+            VersionReq::parse(&comparators)
+                .map_err(|e| anyhow::anyhow!("Invalid version constraint '{}': {}", constraint, e))
+        })
+        .collect()
+}
+
+/// Whether a version satisfies a Helm version constraint
+pub fn constraint_matches(constraint: &str, version: &Version) -> Result<bool> {
+    Ok(parse_version_constraint(constraint)?
+        .iter()
+        .any(|req| req.matches(version)))
+}
+
+/// Enumerate every chart under `charts_dir`, sorted by directory name
+pub fn list_charts(charts_dir: &str) -> Result<Vec<ChartInfo>> {
+    let mut chart_dirs: Vec<PathBuf> = std::fs::read_dir(charts_dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.join("Chart.yaml").is_file())
+        .collect();
+    chart_dirs.sort();
+
+    chart_dirs
+        .into_iter()
+        .map(|path| {
+            let metadata = ChartMetadata::load(&path)?;
+            Ok(ChartInfo { path, metadata })
+        })
+        .collect()
+}
+
+/// Load and validate the Chart.yaml of a chart directory
+pub fn validate_chart_metadata(chart_path: &str) -> Result<Vec<ChartIssue>> {
+    let chart_dir = Path::new(chart_path);
+    Ok(ChartMetadata::load(chart_dir)?.validate(chart_dir))
+}
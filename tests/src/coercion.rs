@@ -0,0 +1,120 @@
+use anyhow::{bail, Result};
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::lineage::{chart_lineage, rendered_fields, Lineage, OutputField};
+use crate::{parse_yaml_documents, run_helm_template, run_helm_template_with_values_files};
+
+/// How intended values reach helm, which decides how their types are inferred
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValuesSource {
+    /// `--set key=value`: digits become int64, `true` and `false` become booleans
+    Set,
+    /// A values file with each value written unquoted, as people write them: numbers become
+    /// float64, so `123456789012` renders as `1.23456789012e+11` and `1.10` as `1.1`
+    ValuesFile,
+}
+
+/// An intended value whose text did not survive rendering
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoercionIssue {
+    pub path: String,
+    pub field: OutputField,
+    pub intended: String,
+    /// The decoded field text in the render, `None` when the field disappeared
+    pub rendered: Option<String>,
+}
+
+impl fmt::Display for CoercionIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.rendered {
+            Some(rendered) => write!(
+                f,
+                "{}={} renders as '{}' in {}",
+                self.path, self.intended, rendered, self.field
+            ),
+            None => write!(f, "{}={} removes {}", self.path, self.intended, self.field),
+        }
+    }
+}
+
+/// Compare each intended value with the fields its lineage says it reaches, after decoding Secret
+/// data: a field that is the whole value must be exactly the intended text, others must contain it
+pub fn find_coercions(
+    documents: &[Value],
+    lineage: &Lineage,
+    intended: &HashMap<String, String>,
+) -> Vec<CoercionIssue> {
+    let rendered = rendered_fields(documents);
+
+    let mut paths: Vec<&String> = intended.keys().collect();
+    paths.sort();
+    let mut issues = Vec::new();
+    for path in paths {
+        let value = &intended[path];
+        for field in lineage.fields_of(path) {
+            let text = rendered.get(field);
+            let survived = if lineage.is_whole_value(path, field) {
+                text.is_some_and(|t| t == value)
+            } else {
+                text.is_some_and(|t| t.contains(value.as_str()))
+            };
+            if survived {
+                continue;
+            }
+            issues.push(CoercionIssue {
+                path: path.clone(),
+                field: field.clone(),
+                intended: value.clone(),
+                rendered: text.cloned(),
+            });
+        }
+    }
+    issues
+}
+
+/// A values file that writes every value unquoted under its dotted path
+pub fn unquoted_values_file(intended: &HashMap<String, String>) -> Result<String> {
+    let mut paths: Vec<&String> = intended.keys().collect();
+    paths.sort();
+    let mut lines: Vec<String> = Vec::new();
+    let mut open: Vec<&str> = Vec::new();
+    for path in paths {
+        if path.contains('[') {
+            bail!("cannot write {} to a values file: list indexes are not supported", path);
+        }
+        let keys: Vec<&str> = path.split('.').collect();
+        let (leaf, parents) = keys.split_last().unwrap_or((&"", &[]));
+        let shared = open.iter().zip(parents).take_while(|(a, b)| a == b).count();
+        open.truncate(shared);
+        for key in &parents[shared..] {
+            lines.push(format!("{}{}:", "  ".repeat(open.len()), key));
+            open.push(key);
+        }
+        lines.push(format!("{}{}: {}", "  ".repeat(open.len()), leaf, intended[path]));
+    }
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Render a chart with the intended values the way `source` passes them, and report every value
+/// whose text changed on the way to the rendered output
+///
+/// Lineage comes from a canary render; `true` and `false` are traced by flipping them, see
+/// [`chart_lineage`].
+pub fn check_coercion(
+    chart_path: &str,
+    intended: &HashMap<String, String>,
+    source: ValuesSource,
+) -> Result<Vec<CoercionIssue>> {
+    let lineage = chart_lineage(chart_path, Some(intended))?;
+    let output = match source {
+        ValuesSource::Set => run_helm_template(chart_path, Some(intended))?,
+        ValuesSource::ValuesFile => {
+            let values_file = tempfile::Builder::new().suffix(".yaml").tempfile()?;
+            std::fs::write(values_file.path(), unquoted_values_file(intended)?)?;
+            run_helm_template_with_values_files(chart_path, &[values_file.path()], None)?
+        }
+    };
+    Ok(find_coercions(&parse_yaml_documents(&output)?, &lineage, intended))
+}
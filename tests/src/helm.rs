@@ -25,6 +25,169 @@ pub fn find_resources_by_kind<'a>(documents: &'a [Value], kind: &str) -> Vec<&'a
         .collect()
 }
 
+/// Get the kind of a rendered resource
+pub fn resource_kind(doc: &Value) -> Option<&str> {
+    doc.get("kind").and_then(|k| k.as_str())
+}
+
+/// Get the metadata.name of a rendered resource
+pub fn resource_name(doc: &Value) -> Option<&str> {
+    doc.get("metadata")
+        .and_then(|m| m.get("name"))
+        .and_then(|n| n.as_str())
+}
+
+/// Get a metadata.annotations entry of a rendered resource
+pub fn resource_annotation<'a>(doc: &'a Value, key: &str) -> Option<&'a str> {
+    doc.get("metadata")
+        .and_then(|m| m.get("annotations"))
+        .and_then(|a| a.get(key))
+        .and_then(|v| v.as_str())
+}
+
+/// Get the pod spec of a workload resource (Pod, Deployment, StatefulSet, DaemonSet, Job, CronJob, ...)
+pub fn pod_spec(doc: &Value) -> Option<&Value> {
+    match resource_kind(doc)? {
+        "Pod" => doc.get("spec"),
+        "CronJob" => doc
+            .get("spec")?
+            .get("jobTemplate")?
+            .get("spec")?
+            .get("template")?
+            .get("spec"),
+        "Deployment" | "StatefulSet" | "DaemonSet" | "ReplicaSet" | "ReplicationController" | "Job" => {
+            doc.get("spec")?.get("template")?.get("spec")
+        }
+        _ => None,
+    }
+}
+
+/// Get all containers of a pod spec, including init and ephemeral containers
+pub fn pod_containers(pod_spec: &Value) -> Vec<&Value> {
+    ["initContainers", "containers", "ephemeralContainers"]
+        .iter()
+        .filter_map(|field| pod_spec.get(*field).and_then(|c| c.as_sequence()))
+        .flatten()
+        .collect()
+}
+
+/// A reference from a pod spec to a Secret or ConfigMap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectReference {
+    pub kind: String,
+    pub name: String,
+    pub via: String,
+}
+
+/// Collect every Secret and ConfigMap a pod spec consumes through env, envFrom, volumes or imagePullSecrets
+pub fn pod_object_references(pod_spec: &Value) -> Vec<ObjectReference> {
+    let mut refs = Vec::new();
+    let mut push = |kind: &str, name: Option<&Value>, via: String| {
+        if let Some(name) = name.and_then(|n| n.as_str()) {
+            refs.push(ObjectReference {
+                kind: kind.to_string(),
+                name: name.to_string(),
+                via,
+            });
+        }
+    };
+
+    for container in pod_containers(pod_spec) {
+        let container_name = container.get("name").and_then(|n| n.as_str()).unwrap_or("?");
+
+        for env in container.get("env").and_then(|e| e.as_sequence()).into_iter().flatten() {
+            let env_name = env.get("name").and_then(|n| n.as_str()).unwrap_or("?");
+            let value_from = env.get("valueFrom");
+            push(
+                "Secret",
+                value_from.and_then(|v| v.get("secretKeyRef")).and_then(|r| r.get("name")),
+                format!("container '{}' env {}", container_name, env_name),
+            );
+            push(
+                "ConfigMap",
+                value_from.and_then(|v| v.get("configMapKeyRef")).and_then(|r| r.get("name")),
+                format!("container '{}' env {}", container_name, env_name),
+            );
+        }
+
+        for env_from in container.get("envFrom").and_then(|e| e.as_sequence()).into_iter().flatten() {
+            push(
+                "Secret",
+                env_from.get("secretRef").and_then(|r| r.get("name")),
+                format!("container '{}' envFrom", container_name),
+            );
+            push(
+                "ConfigMap",
+                env_from.get("configMapRef").and_then(|r| r.get("name")),
+                format!("container '{}' envFrom", container_name),
+            );
+        }
+    }
+
+    for volume in pod_spec.get("volumes").and_then(|v| v.as_sequence()).into_iter().flatten() {
+        let volume_name = volume.get("name").and_then(|n| n.as_str()).unwrap_or("?");
+        push(
+            "Secret",
+            volume.get("secret").and_then(|s| s.get("secretName")),
+            format!("volume '{}'", volume_name),
+        );
+        push(
+            "ConfigMap",
+            volume.get("configMap").and_then(|c| c.get("name")),
+            format!("volume '{}'", volume_name),
+        );
+        let sources = volume
+            .get("projected")
+            .and_then(|p| p.get("sources"))
+            .and_then(|s| s.as_sequence());
+        for source in sources.into_iter().flatten() {
+            push(
+                "Secret",
+                source.get("secret").and_then(|s| s.get("name")),
+                format!("projected volume '{}'", volume_name),
+            );
+            push(
+                "ConfigMap",
+                source.get("configMap").and_then(|c| c.get("name")),
+                format!("projected volume '{}'", volume_name),
+            );
+        }
+    }
+
+    for pull_secret in pod_spec.get("imagePullSecrets").and_then(|s| s.as_sequence()).into_iter().flatten() {
+        push("Secret", pull_secret.get("name"), "imagePullSecrets".to_string());
+    }
+
+    refs
+}
+
+/// Volume mounts of a container whose path its `command` and `args` never mention
+///
+/// Containers without `command` or `args` run their image's entrypoint, which may read any mount,
+/// so none of their mounts are reported.
+pub fn unreferenced_mounts(container: &Value) -> Vec<&Value> {
+    let script: Vec<&str> = ["command", "args"]
+        .iter()
+        .filter_map(|field| container.get(*field).and_then(|v| v.as_sequence()))
+        .flatten()
+        .filter_map(|v| v.as_str())
+        .collect();
+    if script.is_empty() {
+        return Vec::new();
+    }
+    container
+        .get("volumeMounts")
+        .and_then(|m| m.as_sequence())
+        .into_iter()
+        .flatten()
+        .filter(|mount| {
+            let path = mount.get("mountPath").and_then(|p| p.as_str()).unwrap_or_default();
+            let path = path.trim_end_matches('/');
+            !path.is_empty() && !script.iter().any(|arg| arg.contains(path))
+        })
+        .collect()
+}
+
 /// Validate that a deployment has the expected environment variables from secrets
 pub fn validate_deployment_secret_env_vars(
     deployment: &Deployment,
@@ -81,15 +244,18 @@ pub fn validate_deployment_secret_env_vars(
     Ok(())
 }
 
-/// Validate that a secret contains expected keys
+/// Validate that a secret contains expected keys in `data` or `stringData`
 pub fn validate_secret_keys(secret: &Secret, expected_keys: &[&str]) -> Result<()> {
-    let data = secret
-        .data
-        .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("Secret has no data"))?;
+    if secret.data.is_none() && secret.string_data.is_none() {
+        anyhow::bail!("Secret has no data");
+    }
+    let has_key = |key: &str| {
+        secret.data.as_ref().is_some_and(|d| d.contains_key(key))
+            || secret.string_data.as_ref().is_some_and(|d| d.contains_key(key))
+    };
 
     for key in expected_keys {
-        if !data.contains_key(*key) {
+        if !has_key(key) {
             anyhow::bail!("Secret missing expected key: {}", key);
         }
     }
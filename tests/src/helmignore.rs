@@ -0,0 +1,120 @@
+use anyhow::Result;
+use globset::{GlobBuilder, GlobMatcher};
+use std::path::Path;
+
+/// A single `.helmignore` pattern
+#[derive(Debug, Clone)]
+struct Rule {
+    matcher: GlobMatcher,
+    negate: bool,
+    must_dir: bool,
+    basename_only: bool,
+}
+
+impl Rule {
+    fn matches(&self, rel_path: &str) -> bool {
+        if self.basename_only {
+            let base = rel_path.rsplit('/').next().unwrap_or(rel_path);
+            self.matcher.is_match(base)
+        } else {
+            self.matcher.is_match(rel_path)
+        }
+    }
+}
+
+/// Rules from a chart's `.helmignore`, evaluated the way `helm package` does
+#[derive(Debug, Clone, Default)]
+pub struct HelmIgnore {
+    rules: Vec<Rule>,
+}
+
+impl HelmIgnore {
+    /// Load `.helmignore` from a chart directory; a missing file ignores nothing
+    pub fn load(chart_dir: &Path) -> Result<Self> {
+        let path = chart_dir.join(".helmignore");
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parse the contents of a `.helmignore` file
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut rules = Vec::new();
+
+        for line in content.lines() {
+            let mut pattern = line.trim();
+            if pattern.is_empty() || pattern.starts_with('#') {
+                continue;
+            }
+            if pattern.contains("**") {
+                anyhow::bail!("double-star (**) syntax is not supported in .helmignore: {}", pattern);
+            }
+
+            let negate = pattern.starts_with('!');
+            if negate {
+                pattern = &pattern[1..];
+            }
+            let must_dir = pattern.ends_with('/');
+            let pattern = pattern.trim_end_matches('/');
+            let basename_only = !pattern.contains('/');
+            let pattern = pattern.trim_start_matches('/');
+
+            let glob = GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .backslash_escape(true)
+                .build()?;
+
+            rules.push(Rule {
+                matcher: glob.compile_matcher(),
+                negate,
+                must_dir,
+                basename_only,
+            });
+        }
+
+        Ok(Self { rules })
+    }
+
+    /// Whether a chart-relative path is ignored, mirroring Helm's `Rules.Ignore`
+    pub fn ignores(&self, rel_path: &str, is_dir: bool) -> bool {
+        let rel_path = rel_path.trim_start_matches("./").trim_end_matches('/');
+        if rel_path.is_empty() || rel_path == "." {
+            return false;
+        }
+
+        for rule in &self.rules {
+            if rule.negate {
+                if rule.must_dir && !is_dir {
+                    return true;
+                }
+                if !rule.matches(rel_path) {
+                    return true;
+                }
+                continue;
+            }
+            if rule.must_dir && !is_dir {
+                continue;
+            }
+            if rule.matches(rel_path) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Whether a chart-relative file ends up in the packaged chart, taking ignored parent directories into account
+    pub fn is_packaged(&self, rel_path: &str) -> bool {
+        let rel_path = rel_path.trim_start_matches("./");
+        let components: Vec<&str> = rel_path.split('/').collect();
+
+        for depth in 1..components.len() {
+            if self.ignores(&components[..depth].join("/"), true) {
+                return false;
+            }
+        }
+
+        !self.ignores(rel_path, false)
+    }
+}
@@ -0,0 +1,359 @@
+use serde_yaml::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::{pod_object_references, pod_spec, resource_annotation, resource_kind, resource_name, Severity};
+
+/// Kind ordering Helm uses when installing resources and running hooks of the same weight
+const INSTALL_ORDER: &[&str] = &[
+    "PriorityClass",
+    "Namespace",
+    "NetworkPolicy",
+    "ResourceQuota",
+    "LimitRange",
+    "PodSecurityPolicy",
+    "PodDisruptionBudget",
+    "ServiceAccount",
+    "Secret",
+    "SecretList",
+    "ConfigMap",
+    "StorageClass",
+    "PersistentVolume",
+    "PersistentVolumeClaim",
+    "CustomResourceDefinition",
+    "ClusterRole",
+    "ClusterRoleList",
+    "ClusterRoleBinding",
+    "ClusterRoleBindingList",
+    "Role",
+    "RoleList",
+    "RoleBinding",
+    "RoleBindingList",
+    "Service",
+    "DaemonSet",
+    "Pod",
+    "ReplicationController",
+    "ReplicaSet",
+    "Deployment",
+    "HorizontalPodAutoscaler",
+    "StatefulSet",
+    "Job",
+    "CronJob",
+    "IngressClass",
+    "Ingress",
+    "APIService",
+];
+
+/// A release operation that Helm runs hooks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifecycleEvent {
+    Install,
+    Upgrade,
+    Rollback,
+    Uninstall,
+    Test,
+}
+
+impl LifecycleEvent {
+    /// Hook names that run before and after the release resources are applied
+    fn hook_phases(self) -> (&'static str, &'static str) {
+        match self {
+            LifecycleEvent::Install => ("pre-install", "post-install"),
+            LifecycleEvent::Upgrade => ("pre-upgrade", "post-upgrade"),
+            LifecycleEvent::Rollback => ("pre-rollback", "post-rollback"),
+            LifecycleEvent::Uninstall => ("pre-delete", "post-delete"),
+            LifecycleEvent::Test => ("test", ""),
+        }
+    }
+}
+
+impl fmt::Display for LifecycleEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            LifecycleEvent::Install => "install",
+            LifecycleEvent::Upgrade => "upgrade",
+            LifecycleEvent::Rollback => "rollback",
+            LifecycleEvent::Uninstall => "uninstall",
+            LifecycleEvent::Test => "test",
+        };
+        f.write_str(label)
+    }
+}
+
+/// A hook resource parsed from its `helm.sh/hook*` annotations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hook {
+    pub kind: String,
+    pub name: String,
+    pub events: Vec<String>,
+    pub weight: i64,
+    pub delete_policies: Vec<String>,
+}
+
+impl Hook {
+    fn runs_on(&self, phase: &str) -> bool {
+        self.events
+            .iter()
+            .any(|e| e == phase || (phase == "test" && e == "test-success"))
+    }
+
+    fn has_policy(&self, policy: &str) -> bool {
+        self.delete_policies.iter().any(|p| p == policy)
+    }
+}
+
+/// What happened to an object during a simulated lifecycle step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookAction {
+    Created,
+    Applied,
+    Deleted,
+}
+
+/// A single change to the cluster made while simulating an event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LifecycleAction {
+    pub phase: String,
+    pub action: HookAction,
+    pub kind: String,
+    pub name: String,
+}
+
+/// The actions of one simulated event and the objects that exist once it completes
+#[derive(Debug, Clone)]
+pub struct LifecycleStep {
+    pub event: LifecycleEvent,
+    pub actions: Vec<LifecycleAction>,
+    pub existing: BTreeSet<(String, String)>,
+}
+
+impl LifecycleStep {
+    /// Whether an object of the given kind and name exists after this step
+    pub fn exists(&self, kind: &str, name: &str) -> bool {
+        self.existing.contains(&(kind.to_string(), name.to_string()))
+    }
+}
+
+/// A problem with how regular resources depend on hook-managed ones
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookDependencyIssue {
+    pub severity: Severity,
+    pub dependent: String,
+    pub hook: String,
+    pub message: String,
+}
+
+impl fmt::Display for HookDependencyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {} -> {}: {}", self.severity, self.dependent, self.hook, self.message)
+    }
+}
+
+/// Parse the hook annotations of a rendered resource, if it is a hook
+pub fn parse_hook(doc: &Value) -> Option<Hook> {
+    let events = resource_annotation(doc, "helm.sh/hook")?;
+    let weight = resource_annotation(doc, "helm.sh/hook-weight")
+        .and_then(|w| w.trim().parse().ok())
+        .unwrap_or(0);
+    let mut delete_policies = split_list(resource_annotation(doc, "helm.sh/hook-delete-policy").unwrap_or(""));
+    if delete_policies.is_empty() {
+        delete_policies.push("before-hook-creation".to_string());
+    }
+
+    Some(Hook {
+        kind: resource_kind(doc)?.to_string(),
+        name: resource_name(doc)?.to_string(),
+        events: split_list(events),
+        weight,
+        delete_policies,
+    })
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn kind_rank(kind: &str) -> usize {
+    INSTALL_ORDER
+        .iter()
+        .position(|k| *k == kind)
+        .unwrap_or(INSTALL_ORDER.len())
+}
+
+/// Order hooks the way Helm executes them: by weight, then name; Helm sorts by kind first and
+/// its weight sort is stable, so kind only breaks ties between hooks with the same name
+pub fn sort_hooks(hooks: &mut [Hook]) {
+    hooks.sort_by(|a, b| {
+        a.weight
+            .cmp(&b.weight)
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| kind_rank(&a.kind).cmp(&kind_rank(&b.kind)))
+    });
+}
+
+/// Walk a sequence of release events over a render, assuming every hook succeeds
+pub fn simulate_lifecycle(documents: &[Value], events: &[LifecycleEvent]) -> Vec<LifecycleStep> {
+    let mut hooks: Vec<Hook> = documents.iter().filter_map(parse_hook).collect();
+    sort_hooks(&mut hooks);
+
+    let mut release: Vec<(String, String)> = documents
+        .iter()
+        .filter(|doc| parse_hook(doc).is_none())
+        .filter_map(|doc| Some((resource_kind(doc)?.to_string(), resource_name(doc)?.to_string())))
+        .collect();
+    release.sort_by(|a, b| kind_rank(&a.0).cmp(&kind_rank(&b.0)).then_with(|| a.1.cmp(&b.1)));
+
+    let mut existing = BTreeSet::new();
+    let mut steps = Vec::new();
+
+    for event in events {
+        let (pre, post) = event.hook_phases();
+        let mut actions = Vec::new();
+
+        run_hook_phase(pre, &hooks, &mut existing, &mut actions);
+
+        match event {
+            LifecycleEvent::Install | LifecycleEvent::Upgrade | LifecycleEvent::Rollback => {
+                for key in &release {
+                    let action = if existing.insert(key.clone()) {
+                        HookAction::Created
+                    } else {
+                        HookAction::Applied
+                    };
+                    actions.push(LifecycleAction {
+                        phase: event.to_string(),
+                        action,
+                        kind: key.0.clone(),
+                        name: key.1.clone(),
+                    });
+                }
+            }
+            LifecycleEvent::Uninstall => {
+                for key in release.iter().rev() {
+                    if existing.remove(key) {
+                        actions.push(LifecycleAction {
+                            phase: event.to_string(),
+                            action: HookAction::Deleted,
+                            kind: key.0.clone(),
+                            name: key.1.clone(),
+                        });
+                    }
+                }
+            }
+            LifecycleEvent::Test => {}
+        }
+
+        if !post.is_empty() {
+            run_hook_phase(post, &hooks, &mut existing, &mut actions);
+        }
+
+        steps.push(LifecycleStep {
+            event: *event,
+            actions,
+            existing: existing.clone(),
+        });
+    }
+
+    steps
+}
+
+fn run_hook_phase(
+    phase: &str,
+    hooks: &[Hook],
+    existing: &mut BTreeSet<(String, String)>,
+    actions: &mut Vec<LifecycleAction>,
+) {
+    let executing: Vec<&Hook> = hooks.iter().filter(|h| h.runs_on(phase)).collect();
+    let mut record = |action: HookAction, hook: &Hook| {
+        actions.push(LifecycleAction {
+            phase: phase.to_string(),
+            action,
+            kind: hook.kind.clone(),
+            name: hook.name.clone(),
+        })
+    };
+
+    for hook in &executing {
+        let key = (hook.kind.clone(), hook.name.clone());
+        if hook.has_policy("before-hook-creation") && existing.remove(&key) {
+            record(HookAction::Deleted, hook);
+        }
+        if existing.insert(key) {
+            record(HookAction::Created, hook);
+        } else {
+            // Without before-hook-creation Helm fails with "already exists"; keep the old object.
+            record(HookAction::Applied, hook);
+        }
+    }
+
+    // Helm deletes succeeded hooks only after every hook of the phase has completed.
+    for hook in &executing {
+        if hook.has_policy("hook-succeeded") && existing.remove(&(hook.kind.clone(), hook.name.clone())) {
+            record(HookAction::Deleted, hook);
+        }
+    }
+}
+
+/// Report regular resources that consume Secrets or ConfigMaps managed by hooks
+pub fn check_hook_dependencies(documents: &[Value]) -> Vec<HookDependencyIssue> {
+    let hooks: BTreeMap<(String, String), Hook> = documents
+        .iter()
+        .filter_map(parse_hook)
+        .map(|h| ((h.kind.clone(), h.name.clone()), h))
+        .collect();
+    let mut issues = Vec::new();
+
+    for doc in documents {
+        if parse_hook(doc).is_some() {
+            continue;
+        }
+        let (Some(spec), Some(kind), Some(name)) = (pod_spec(doc), resource_kind(doc), resource_name(doc)) else {
+            continue;
+        };
+        let dependent = format!("{}/{}", kind, name);
+
+        for reference in pod_object_references(spec) {
+            let Some(hook) = hooks.get(&(reference.kind.clone(), reference.name.clone())) else {
+                continue;
+            };
+            let hook_id = format!("{}/{}", hook.kind, hook.name);
+            let mut report = |severity: Severity, message: String| {
+                issues.push(HookDependencyIssue {
+                    severity,
+                    dependent: dependent.clone(),
+                    hook: hook_id.clone(),
+                    message,
+                })
+            };
+
+            if hook.has_policy("hook-succeeded") {
+                report(
+                    Severity::Error,
+                    format!(
+                        "{} reads it, but hook-succeeded deletes it as soon as its hooks finish",
+                        reference.via
+                    ),
+                );
+            }
+            if !hook.runs_on("pre-install") && !hook.runs_on("post-install") {
+                report(
+                    Severity::Error,
+                    format!("{} reads it, but no install hook creates it", reference.via),
+                );
+            }
+            report(
+                Severity::Warning,
+                format!(
+                    "{} reads a hook resource; Helm does not track it as release content or delete it on uninstall",
+                    reference.via
+                ),
+            );
+        }
+    }
+
+    issues
+}
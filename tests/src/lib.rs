@@ -1,35 +1,263 @@
 use anyhow::Result;
 use base64::Engine;
 use std::collections::HashMap;
-use std::process::Command;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
 
+pub mod branch_coverage;
+pub mod chart;
+pub mod coercion;
+pub mod consistency;
 pub mod helm;
+pub mod helmignore;
+pub mod hooks;
+pub mod lineage;
+pub mod named_template;
+pub mod names;
+pub mod pairwise;
+pub mod report;
+pub mod required_values;
+pub mod script_lint;
+pub mod sealed_secret;
+pub mod secret_scan;
+pub mod sops;
+pub mod sql_drift;
+pub mod strict_values;
+pub mod template;
+pub mod template_lint;
+pub mod typed_secret;
+pub mod unused;
+pub mod upgrade;
+pub mod value_format;
+pub mod values_coverage;
+pub mod values_fuzz;
+pub mod values_schema;
+pub mod version_bump;
 
 pub use helm::*;
+pub use report::Severity;
 
-/// Helper function to run helm template command
-pub fn run_helm_template(chart_path: &str, values: Option<&HashMap<String, String>>) -> Result<String> {
+/// What a `helm template` invocation renders the chart with, besides the chart itself
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RenderArgs<'a> {
+    pub release: &'a str,
+    pub values_files: &'a [&'a Path],
+    /// Values YAML piped to `-f -`, after the values files
+    pub stdin_values: Option<&'a str>,
+    pub values: Option<&'a HashMap<String, String>>,
+}
+
+impl Default for RenderArgs<'_> {
+    fn default() -> Self {
+        RenderArgs {
+            release: "test-release",
+            values_files: &[],
+            stdin_values: None,
+            values: None,
+        }
+    }
+}
+
+/// Run `helm template`; every render helper goes through here
+///
+/// When `HELM_TESTS_BRANCH_COVERAGE` is set, chart directories are rendered through an
+/// instrumented copy and the branches taken are recorded in that directory. Charts extracted
+/// to temporary directories, such as previous revisions, are rendered as is.
+pub(crate) fn render_chart(chart_path: &str, args: &RenderArgs) -> Result<String> {
+    if let Some(dir) = std::env::var_os(branch_coverage::BRANCH_COVERAGE_ENV) {
+        let chart_dir = Path::new(chart_path);
+        let is_temporary = match (chart_dir.canonicalize(), std::env::temp_dir().canonicalize()) {
+            (Ok(chart), Ok(tmp)) => chart.starts_with(tmp),
+            _ => false,
+        };
+        if chart_dir.is_dir() && !is_temporary {
+            return branch_coverage::render_with_branch_coverage(Path::new(&dir), chart_path, args);
+        }
+    }
+    render_chart_uninstrumented(chart_path, args)
+}
+
+/// Run `helm template` without branch coverage instrumentation
+pub(crate) fn render_chart_uninstrumented(chart_path: &str, args: &RenderArgs) -> Result<String> {
     let mut cmd = Command::new("helm");
-    cmd.args(["template", "test-release", chart_path]);
-    
-    if let Some(vals) = values {
+    cmd.args(["template", args.release, chart_path]);
+
+    for file in args.values_files {
+        cmd.arg("-f").arg(file);
+    }
+    if args.stdin_values.is_some() {
+        cmd.args(["-f", "-"]);
+    }
+
+    if let Some(vals) = args.values {
         for (key, value) in vals {
             cmd.args(["--set", &format!("{}={}", key, value)]);
         }
     }
-    
-    let output = cmd.output()?;
-    
+
+    let mut child = cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    if let (Some(mut stdin), Some(values_yaml)) = (child.stdin.take(), args.stdin_values) {
+        stdin.write_all(values_yaml.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+
     if !output.status.success() {
         anyhow::bail!(
             "Helm template failed: {}",
             String::from_utf8_lossy(&output.stderr)
         );
     }
-    
+
     Ok(String::from_utf8(output.stdout)?)
 }
 
+/// Helper function to run helm template command
+pub fn run_helm_template(chart_path: &str, values: Option<&HashMap<String, String>>) -> Result<String> {
+    render_chart(chart_path, &RenderArgs { values, ..Default::default() })
+}
+
+/// Helper function to run helm template command with a specific release name
+pub fn run_helm_template_with_release(
+    chart_path: &str,
+    release_name: &str,
+    values: Option<&HashMap<String, String>>,
+) -> Result<String> {
+    render_chart(
+        chart_path,
+        &RenderArgs {
+            release: release_name,
+            values,
+            ..Default::default()
+        },
+    )
+}
+
+/// Helper function to run helm template command with a values overlay file and `--set` values
+pub fn run_helm_template_with_overlay(
+    chart_path: &str,
+    overlay: &serde_yaml::Value,
+    values: Option<&HashMap<String, String>>,
+) -> Result<String> {
+    let overlay_file = tempfile::Builder::new().suffix(".yaml").tempfile()?;
+    std::fs::write(overlay_file.path(), serde_yaml::to_string(overlay)?)?;
+    run_helm_template_with_values_files(chart_path, &[overlay_file.path()], values)
+}
+
+/// Helper function to run helm template command with values files (`-f`) and `--set` values
+pub fn run_helm_template_with_values_files(
+    chart_path: &str,
+    values_files: &[&Path],
+    values: Option<&HashMap<String, String>>,
+) -> Result<String> {
+    render_chart(
+        chart_path,
+        &RenderArgs {
+            values_files,
+            values,
+            ..Default::default()
+        },
+    )
+}
+
+/// Helper function to run helm template command with values YAML piped to `-f -`, so plaintext
+/// values never touch the disk
+pub fn run_helm_template_with_stdin_values(
+    chart_path: &str,
+    values_yaml: &str,
+    values: Option<&HashMap<String, String>>,
+) -> Result<String> {
+    render_chart(
+        chart_path,
+        &RenderArgs {
+            stdin_values: Some(values_yaml),
+            values,
+            ..Default::default()
+        },
+    )
+}
+
+/// Helper function to run helm template command with a SOPS-encrypted values file, decrypted in
+/// process with age identities, and `--set` values
+pub fn run_helm_template_with_sops_values(
+    chart_path: &str,
+    sops_file: &Path,
+    identities: &[age::x25519::Identity],
+    values: Option<&HashMap<String, String>>,
+) -> Result<String> {
+    let plaintext = sops::decrypt_sops_values(&std::fs::read_to_string(sops_file)?, identities)?;
+    run_helm_template_with_stdin_values(chart_path, &serde_yaml::to_string(&plaintext)?, values)
+}
+
+/// Whether an error comes from helm rejecting the render, rather than from failing to run helm
+pub fn is_render_failure(error: &anyhow::Error) -> bool {
+    error.to_string().starts_with("Helm template failed")
+}
+
+/// Render a chart that is expected to fail and return the error message
+pub fn expect_render_error(chart_path: &str, values: Option<&HashMap<String, String>>) -> Result<String> {
+    match run_helm_template(chart_path, values) {
+        Ok(_) => anyhow::bail!("Expected rendering {} to fail, but it succeeded", chart_path),
+        Err(e) if is_render_failure(&e) => Ok(e.to_string()),
+        Err(e) => Err(e),
+    }
+}
+
+/// One step of a `--set` key: a map key or a list index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetKeySegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Split a `--set` key such as `api.ingress.hosts[0].host` or `annotations.example\.com/name` into
+/// its map keys and list indices, as Helm's strvals parser does
+pub fn set_key_segments(key: &str) -> Result<Vec<SetKeySegment>> {
+    let mut segments = Vec::new();
+    let mut segment = String::new();
+    let mut chars = key.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'.') => segment.push(chars.next().unwrap_or('.')),
+            '.' => {
+                if !segment.is_empty() {
+                    segments.push(SetKeySegment::Key(std::mem::take(&mut segment)));
+                }
+            }
+            '[' => {
+                if !segment.is_empty() {
+                    segments.push(SetKeySegment::Key(std::mem::take(&mut segment)));
+                }
+                let index: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                let Ok(index) = index.trim().parse() else {
+                    anyhow::bail!("--set key {} has an invalid list index [{}]", key, index);
+                };
+                segments.push(SetKeySegment::Index(index));
+            }
+            c => segment.push(c),
+        }
+    }
+    if !segment.is_empty() {
+        segments.push(SetKeySegment::Key(segment));
+    }
+    Ok(segments)
+}
+
+/// A `--set` key as a values path, with list indices as [`values_coverage::LIST_ELEMENT`]; a key
+/// Helm cannot parse is kept whole, so it is reported as undeclared
+pub fn set_key_path(key: &str) -> Vec<String> {
+    match set_key_segments(key) {
+        Ok(segments) => segments
+            .into_iter()
+            .map(|segment| match segment {
+                SetKeySegment::Key(key) => key,
+                SetKeySegment::Index(_) => values_coverage::LIST_ELEMENT.to_string(),
+            })
+            .collect(),
+        Err(_) => vec![key.to_string()],
+    }
+}
+
 /// Helper function to run helm lint
 pub fn run_helm_lint(chart_path: &str) -> Result<String> {
     let output = Command::new("helm")
@@ -64,10 +292,39 @@ pub fn run_helm_lint_with_values(chart_path: &str, extra_args: &[&str]) -> Resul
     Ok(String::from_utf8(output.stdout)?)
 }
 
+/// Helper function to run a git command inside a repository directory
+pub fn run_git(repo_dir: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo_dir)
+        .args(args)
+        .output()?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(String::from_utf8(output.stdout)?)
+}
+
 /// Parse YAML documents from helm template output
 pub fn parse_yaml_documents(yaml_content: &str) -> Result<Vec<serde_yaml::Value>> {
-    let documents: Vec<serde_yaml::Value> = yaml_content
-        .split("---")
+    // Split on separator lines only, so PEM blocks (`-----BEGIN ...`) in stringData stay intact
+    let mut chunks = vec![String::new()];
+    for line in yaml_content.lines() {
+        if line.trim_end() == "---" || line.starts_with("--- ") {
+            chunks.push(String::new());
+        } else if let Some(chunk) = chunks.last_mut() {
+            chunk.push_str(line);
+            chunk.push('\n');
+        }
+    }
+    let documents: Vec<serde_yaml::Value> = chunks
+        .iter()
         .filter(|doc| !doc.trim().is_empty())
         .map(|doc| serde_yaml::from_str(doc.trim()))
         .collect::<Result<Vec<_>, _>>()?;
@@ -93,17 +350,38 @@ pub fn find_resource_by_kind_and_name<'a>(
     })
 }
 
-/// Extract base64 decoded value from secret data
+/// A Secret's `data` and `stringData` merged the way the API server does, `stringData` winning
+pub fn secret_data(secret: &serde_yaml::Value) -> Result<std::collections::BTreeMap<String, Vec<u8>>> {
+    let mut data = std::collections::BTreeMap::new();
+    for (key, value) in secret.get("data").and_then(|d| d.as_mapping()).into_iter().flatten() {
+        let (Some(key), Some(value)) = (key.as_str(), value.as_str()) else {
+            anyhow::bail!("Secret data must map strings to base64 strings");
+        };
+        data.insert(key.to_string(), base64::engine::general_purpose::STANDARD.decode(value)?);
+    }
+    for (key, value) in secret.get("stringData").and_then(|d| d.as_mapping()).into_iter().flatten() {
+        let Some(key) = key.as_str() else {
+            anyhow::bail!("Secret stringData keys must be strings");
+        };
+        let text = match value {
+            serde_yaml::Value::String(s) => s.clone(),
+            serde_yaml::Value::Null => String::new(),
+            other => serde_yaml::to_string(other)?.trim_end().to_string(),
+        };
+        data.insert(key.to_string(), text.into_bytes());
+    }
+    Ok(data)
+}
+
+/// Extract base64 decoded value from secret data, including `stringData`
 pub fn extract_secret_value(secret: &serde_yaml::Value, key: &str) -> Result<String> {
-    let data = secret
-        .get("data")
-        .ok_or_else(|| anyhow::anyhow!("Secret has no data field"))?;
+    if secret.get("data").is_none() && secret.get("stringData").is_none() {
+        anyhow::bail!("Secret has no data field");
+    }
     
-    let encoded_value = data
-        .get(key)
-        .and_then(|v| v.as_str())
+    let decoded = secret_data(secret)?
+        .remove(key)
         .ok_or_else(|| anyhow::anyhow!("Secret key '{}' not found", key))?;
     
-    let decoded = base64::engine::general_purpose::STANDARD.decode(encoded_value)?;
     Ok(String::from_utf8(decoded)?)
 }
\ No newline at end of file
@@ -0,0 +1,289 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::path::Path;
+
+use crate::required_values::{collect_strings, field_text};
+use crate::values_coverage::{declared_values, display_path};
+use crate::values_fuzz::set_value_at;
+use crate::values_schema::sensitive_value_paths;
+use crate::{is_render_failure, parse_yaml_documents, resource_kind, resource_name, run_helm_template_with_overlay};
+
+/// A field of a rendered resource
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct OutputField {
+    pub kind: String,
+    pub name: String,
+    /// Path of the field, e.g. `data.api-key` or `metadata.annotations.example.com/owner`
+    pub field: String,
+}
+
+impl OutputField {
+    /// Whether the field holds Secret data, the only place sensitive values may reach
+    pub fn is_secret_data(&self) -> bool {
+        self.kind == "Secret" && (self.field.starts_with("data.") || self.field.starts_with("stringData."))
+    }
+}
+
+impl fmt::Display for OutputField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}.{}", self.kind, self.name, self.field)
+    }
+}
+
+/// Which output fields each values path reaches
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Lineage {
+    /// The canary string injected for each values path
+    pub canaries: BTreeMap<String, String>,
+    /// The fields each values path reached, keyed by values path
+    pub fields: BTreeMap<String, Vec<OutputField>>,
+    /// Fixture values of `true` or `false`, traced by flipping them instead of with a canary
+    pub booleans: Vec<String>,
+    /// The values paths and fields where the field's whole text is the value
+    pub whole: BTreeSet<(String, OutputField)>,
+}
+
+impl Lineage {
+    pub fn fields_of(&self, path: &str) -> &[OutputField] {
+        self.fields.get(path).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Whether the field's whole text is the value at `path`, rather than text around it
+    pub fn is_whole_value(&self, path: &str, field: &OutputField) -> bool {
+        self.whole.contains(&(path.to_string(), field.clone()))
+    }
+
+    /// Values paths that reach nothing in the output
+    pub fn unreached(&self) -> Vec<&str> {
+        self.canaries
+            .keys()
+            .chain(&self.booleans)
+            .filter(|path| self.fields_of(path).is_empty())
+            .map(String::as_str)
+            .collect()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.fields)?)
+    }
+}
+
+/// A sensitive value that reaches something other than Secret data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SensitiveLeak {
+    pub path: String,
+    pub field: OutputField,
+}
+
+impl fmt::Display for SensitiveLeak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sensitive value {} reaches {}", self.path, self.field)
+    }
+}
+
+/// A unique canary string for the n-th values path, fixed width so no canary contains another
+pub fn canary(index: usize) -> String {
+    format!("cnry{:05}x", index)
+}
+
+/// Find every output field containing a canary, decoding base64 in Secret data
+pub fn trace_canaries(documents: &[Value], canaries: &BTreeMap<String, String>) -> BTreeMap<String, Vec<OutputField>> {
+    let mut lineage: BTreeMap<String, Vec<OutputField>> = BTreeMap::new();
+    for doc in documents {
+        let (Some(kind), Some(name)) = (resource_kind(doc), resource_name(doc)) else {
+            continue;
+        };
+        let mut fields = BTreeMap::new();
+        collect_strings(doc, String::new(), &mut fields);
+        for (field, value) in fields {
+            let text = field_text(kind, &field, &value).to_lowercase();
+            for (path, canary) in canaries {
+                if text.contains(canary.as_str()) {
+                    lineage.entry(path.clone()).or_default().push(OutputField {
+                        kind: kind.to_string(),
+                        name: name.to_string(),
+                        field: field.clone(),
+                    });
+                }
+            }
+        }
+    }
+    lineage
+}
+
+/// Like `collect_strings`, but keeps numbers and booleans as text
+pub(crate) fn collect_scalars(value: &Value, path: String, out: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Mapping(map) => {
+            for (key, child) in map {
+                let key = key.as_str().unwrap_or_default();
+                let child_path = if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+                collect_scalars(child, child_path, out);
+            }
+        }
+        Value::Sequence(items) => {
+            for (index, item) in items.iter().enumerate() {
+                collect_scalars(item, format!("{}[{}]", path, index), out);
+            }
+        }
+        Value::String(s) => {
+            out.insert(path, s.clone());
+        }
+        Value::Number(n) => {
+            out.insert(path, n.to_string());
+        }
+        Value::Bool(b) => {
+            out.insert(path, b.to_string());
+        }
+        Value::Null => {
+            out.insert(path, String::new());
+        }
+        Value::Tagged(tagged) => collect_scalars(&tagged.value, path, out),
+    }
+}
+
+/// The text of every scalar field of a render, decoding base64 in Secret data
+pub(crate) fn rendered_fields(documents: &[Value]) -> BTreeMap<OutputField, String> {
+    let mut rendered = BTreeMap::new();
+    for doc in documents {
+        let (Some(kind), Some(name)) = (resource_kind(doc), resource_name(doc)) else {
+            continue;
+        };
+        let mut fields = BTreeMap::new();
+        collect_scalars(doc, String::new(), &mut fields);
+        for (field, value) in fields {
+            let text = field_text(kind, &field, &value);
+            let field = OutputField {
+                kind: kind.to_string(),
+                name: name.to_string(),
+                field,
+            };
+            rendered.insert(field, text);
+        }
+    }
+    rendered
+}
+
+/// Find the fields that follow a boolean value: those whose text in `flipped`, a render with
+/// the value flipped, is their text in `documents` with `true` and `false` swapped
+pub fn trace_boolean(documents: &[Value], flipped: &[Value], value: bool) -> Vec<OutputField> {
+    let (from, to) = if value { ("true", "false") } else { ("false", "true") };
+    let flipped = rendered_fields(flipped);
+    rendered_fields(documents)
+        .into_iter()
+        .filter(|(field, text)| {
+            let text = text.to_lowercase();
+            text.contains(from)
+                && flipped
+                    .get(field)
+                    .is_some_and(|flipped| flipped.to_lowercase() == text.replace(from, to))
+        })
+        .map(|(field, _)| field)
+        .collect()
+}
+
+/// Render a chart with a canary in every string leaf of values.yaml and every fixture value, and
+/// map each values path to the output fields it reaches
+///
+/// Fixture values of `true` or `false` are kept as they are, since they usually toggle resources,
+/// and traced with [`trace_boolean`] by rendering once more with each flipped; one whose flip helm
+/// rejects is left unreached.
+pub fn chart_lineage(chart_path: &str, fixture: Option<&HashMap<String, String>>) -> Result<Lineage> {
+    let defaults: Value = serde_yaml::from_str(&std::fs::read_to_string(Path::new(chart_path).join("values.yaml"))?)?;
+    let mut values = fixture.cloned().unwrap_or_default();
+    let mut overlay = Value::Mapping(Default::default());
+    let mut canaries = BTreeMap::new();
+
+    let mut keys: Vec<String> = values.keys().cloned().collect();
+    keys.sort();
+    let mut booleans = Vec::new();
+    for key in keys {
+        if matches!(values[&key].as_str(), "true" | "false") {
+            booleans.push(key);
+            continue;
+        }
+        let value = canary(canaries.len());
+        values.insert(key.clone(), value.clone());
+        canaries.insert(key, value);
+    }
+
+    for declared in declared_values(&defaults) {
+        let key = display_path(&declared.path);
+        if !declared.leaf || canaries.contains_key(&key) || values.contains_key(&key) || key.contains('[') {
+            continue;
+        }
+        let default = declared.path.iter().try_fold(&defaults, |v, k| v.get(k.as_str()));
+        if !matches!(default, Some(Value::String(_)) | Some(Value::Null)) {
+            continue;
+        }
+        let value = canary(canaries.len());
+        set_value_at(&mut overlay, &declared.path, Value::String(value.clone()));
+        canaries.insert(key, value);
+    }
+
+    let documents = parse_yaml_documents(&run_helm_template_with_overlay(chart_path, &overlay, Some(&values))?)?;
+    let mut fields = trace_canaries(&documents, &canaries);
+    let rendered = rendered_fields(&documents);
+    let mut whole = BTreeSet::new();
+    for (path, canary) in &canaries {
+        for field in fields.get(path).into_iter().flatten() {
+            if rendered.get(field).is_some_and(|text| text.eq_ignore_ascii_case(canary)) {
+                whole.insert((path.clone(), field.clone()));
+            }
+        }
+    }
+
+    for key in &booleans {
+        let value = values[key] == "true";
+        let mut flipped_values = values.clone();
+        flipped_values.insert(key.clone(), (!value).to_string());
+        let flipped = match run_helm_template_with_overlay(chart_path, &overlay, Some(&flipped_values)) {
+            Ok(output) => parse_yaml_documents(&output)?,
+            Err(e) if is_render_failure(&e) => continue,
+            Err(e) => return Err(e),
+        };
+        let reached = trace_boolean(&documents, &flipped, value);
+        for field in &reached {
+            if rendered.get(field).is_some_and(|text| text.eq_ignore_ascii_case(&values[key])) {
+                whole.insert((key.clone(), field.clone()));
+            }
+        }
+        if !reached.is_empty() {
+            fields.insert(key.clone(), reached);
+        }
+    }
+
+    Ok(Lineage {
+        canaries,
+        fields,
+        booleans,
+        whole,
+    })
+}
+
+/// The values paths marked `# @sensitive` in a chart's values.yaml
+pub fn chart_sensitive_values(chart_path: &str) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(Path::new(chart_path).join("values.yaml"))?;
+    Ok(sensitive_value_paths(&content).iter().map(|p| display_path(p)).collect())
+}
+
+/// Every place a sensitive value reaches outside Secret data, such as a ConfigMap, an annotation,
+/// an env `value` or command args
+pub fn check_sensitive(lineage: &Lineage, sensitive: &[String]) -> Vec<SensitiveLeak> {
+    sensitive
+        .iter()
+        .flat_map(|path| {
+            lineage
+                .fields_of(path)
+                .iter()
+                .filter(|field| !field.is_secret_data())
+                .map(|field| SensitiveLeak {
+                    path: path.clone(),
+                    field: field.clone(),
+                })
+        })
+        .collect()
+}
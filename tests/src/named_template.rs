@@ -0,0 +1,151 @@
+use anyhow::Result;
+use base64::Engine;
+use serde_yaml::Value;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+
+use crate::template::chart_template_files;
+
+/// Template of the throwaway chart that renders the named template under test
+const RENDER_FILE: &str = "templates/named-template-under-test.yaml";
+
+/// The root context a named template is rendered with
+#[derive(Debug, Clone)]
+pub struct TemplateContext {
+    /// Values merged over the chart's values.yaml, or replacing it when `chart_values` is false
+    pub values: Value,
+    pub chart_values: bool,
+    pub release_name: String,
+    pub namespace: String,
+    pub is_upgrade: bool,
+    /// Overrides of `.Chart.Name`, `.Chart.Version` and `.Chart.AppVersion`
+    pub chart_name: Option<String>,
+    pub chart_version: Option<String>,
+    pub app_version: Option<String>,
+}
+
+impl Default for TemplateContext {
+    fn default() -> Self {
+        TemplateContext {
+            values: Value::Mapping(Default::default()),
+            chart_values: true,
+            release_name: "test-release".to_string(),
+            namespace: "default".to_string(),
+            is_upgrade: false,
+            chart_name: None,
+            chart_version: None,
+            app_version: None,
+        }
+    }
+}
+
+impl TemplateContext {
+    /// Context with the given values merged over the chart's defaults
+    pub fn with_values(values: &str) -> Result<Self> {
+        Ok(TemplateContext {
+            values: serde_yaml::from_str(values)?,
+            ..Default::default()
+        })
+    }
+}
+
+/// A throwaway chart containing a chart's helpers and a single template including one of them
+pub struct NamedTemplateChart {
+    _dir: TempDir,
+    pub path: PathBuf,
+    values_file: PathBuf,
+    context: TemplateContext,
+}
+
+/// Generate a throwaway chart that renders the named template `name` of the chart at `chart_path`
+pub fn named_template_chart(chart_path: &str, name: &str, context: &TemplateContext) -> Result<NamedTemplateChart> {
+    let chart_dir = Path::new(chart_path);
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("chart");
+    std::fs::create_dir_all(path.join("templates"))?;
+
+    let mut metadata: Value = serde_yaml::from_str(&std::fs::read_to_string(chart_dir.join("Chart.yaml"))?)?;
+    let Some(fields) = metadata.as_mapping_mut() else {
+        anyhow::bail!("Chart.yaml of {} is not a mapping", chart_path);
+    };
+    // The helpers are all that is needed, subcharts are left out
+    fields.remove("dependencies");
+    for (key, value) in [
+        ("name", &context.chart_name),
+        ("version", &context.chart_version),
+        ("appVersion", &context.app_version),
+    ] {
+        if let Some(value) = value {
+            fields.insert(key.into(), value.as_str().into());
+        }
+    }
+    std::fs::write(path.join("Chart.yaml"), serde_yaml::to_string(&metadata)?)?;
+
+    let chart_values = chart_dir.join("values.yaml");
+    if context.chart_values && chart_values.is_file() {
+        std::fs::copy(&chart_values, path.join("values.yaml"))?;
+    }
+    let values_file = dir.path().join("values.yaml");
+    std::fs::write(&values_file, serde_yaml::to_string(&context.values)?)?;
+
+    for file in chart_template_files(chart_dir)? {
+        let is_helper = file.rsplit('/').next().is_some_and(|n| n.starts_with('_'));
+        if is_helper {
+            let target = path.join(&file);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(chart_dir.join(&file), target)?;
+        }
+    }
+
+    // Encoded so that any output, including multi-line or non-YAML text, survives the render
+    std::fs::write(
+        path.join(RENDER_FILE),
+        format!("rendered: {{{{ include {:?} . | b64enc | quote }}}}\n", name),
+    )?;
+
+    Ok(NamedTemplateChart {
+        _dir: dir,
+        path,
+        values_file,
+        context: context.clone(),
+    })
+}
+
+impl NamedTemplateChart {
+    /// Render the named template and return its exact output
+    pub fn render(&self) -> Result<String> {
+        let mut cmd = Command::new("helm");
+        cmd.args(["template", &self.context.release_name])
+            .arg(&self.path)
+            .args(["--namespace", &self.context.namespace, "--show-only", RENDER_FILE])
+            .arg("-f")
+            .arg(&self.values_file);
+        if self.context.is_upgrade {
+            cmd.arg("--is-upgrade");
+        }
+
+        let output = cmd.output()?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Helm template failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+
+        let doc: Value = serde_yaml::from_slice(&output.stdout)?;
+        let encoded = doc
+            .get("rendered")
+            .and_then(|r| r.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Named template output is missing from the render"))?;
+        let decoded = base64::engine::general_purpose::STANDARD.decode(encoded)?;
+        Ok(String::from_utf8(decoded)?)
+    }
+}
+
+/// Render a single named template of a chart in isolation
+pub fn render_named_template(chart_path: &str, name: &str, context: &TemplateContext) -> Result<String> {
+    named_template_chart(chart_path, name, context)?.render()
+}
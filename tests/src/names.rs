@@ -0,0 +1,244 @@
+use serde_yaml::Value;
+use std::fmt;
+
+use crate::{pod_containers, pod_spec, resource_kind, resource_name};
+
+/// Longest name of a CronJob: the controller appends an 11 character suffix to its Jobs
+const CRONJOB_NAME_MAX: usize = 52;
+
+/// A rendered name or label that Kubernetes would reject
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameViolation {
+    pub kind: String,
+    pub name: String,
+    /// Path of the offending field, e.g. `metadata.name` or `spec.ports[0].name`
+    pub field: String,
+    pub value: String,
+    pub message: String,
+}
+
+impl fmt::Display for NameViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}: {} '{}' {}",
+            self.kind, self.name, self.field, self.value, self.message
+        )
+    }
+}
+
+fn is_alnum(c: char) -> bool {
+    c.is_ascii_lowercase() || c.is_ascii_digit()
+}
+
+/// Check a name against the DNS-1123 label rule (RFC 1123): at most 63 lowercase alphanumerics or '-',
+/// starting and ending with an alphanumeric
+pub fn check_dns1123_label(value: &str) -> Result<(), String> {
+    if value.is_empty() || value.len() > 63 {
+        return Err(format!("must be 1-63 characters long, got {}", value.len()));
+    }
+    if !value.chars().all(|c| is_alnum(c) || c == '-') {
+        return Err("must consist of lowercase alphanumerics or '-'".to_string());
+    }
+    if !value.starts_with(is_alnum) || !value.ends_with(is_alnum) {
+        return Err("must start and end with an alphanumeric".to_string());
+    }
+    Ok(())
+}
+
+/// Check a name against the DNS-1035 label rule used for Services: a DNS-1123 label starting with a letter
+pub fn check_dns1035_label(value: &str) -> Result<(), String> {
+    check_dns1123_label(value)?;
+    if !value.starts_with(|c: char| c.is_ascii_lowercase()) {
+        return Err("must start with a lowercase letter".to_string());
+    }
+    Ok(())
+}
+
+/// Check a name against the DNS-1123 subdomain rule: at most 253 characters of dot-separated DNS-1123 labels
+pub fn check_dns1123_subdomain(value: &str) -> Result<(), String> {
+    if value.is_empty() || value.len() > 253 {
+        return Err(format!("must be 1-253 characters long, got {}", value.len()));
+    }
+    if !value.chars().all(|c| is_alnum(c) || c == '-' || c == '.') {
+        return Err("must consist of lowercase alphanumerics, '-' or '.'".to_string());
+    }
+    if !value.split('.').all(|part| part.starts_with(is_alnum) && part.ends_with(is_alnum)) {
+        return Err("must start and end with an alphanumeric around every '.'".to_string());
+    }
+    Ok(())
+}
+
+fn check_label_name(value: &str) -> Result<(), String> {
+    let valid_char = |c: char| c.is_ascii_alphanumeric() || "-_.".contains(c);
+    if value.len() > 63 {
+        return Err(format!("must be at most 63 characters long, got {}", value.len()));
+    }
+    if !value.chars().all(valid_char) {
+        return Err("must consist of alphanumerics, '-', '_' or '.'".to_string());
+    }
+    if !value.is_empty()
+        && (!value.starts_with(|c: char| c.is_ascii_alphanumeric())
+            || !value.ends_with(|c: char| c.is_ascii_alphanumeric()))
+    {
+        return Err("must start and end with an alphanumeric".to_string());
+    }
+    Ok(())
+}
+
+/// Check a label value: empty, or at most 63 alphanumerics, '-', '_' or '.' starting and ending with an alphanumeric
+pub fn check_label_value(value: &str) -> Result<(), String> {
+    check_label_name(value)
+}
+
+/// Check a label key: a name with an optional DNS-1123 subdomain prefix, such as `app.kubernetes.io/name`
+pub fn check_label_key(value: &str) -> Result<(), String> {
+    let name = match value.split_once('/') {
+        Some((prefix, name)) => {
+            check_dns1123_subdomain(prefix).map_err(|e| format!("prefix {}", e))?;
+            name
+        }
+        None => value,
+    };
+    if name.is_empty() {
+        return Err("name part must not be empty".to_string());
+    }
+    check_label_name(name)
+}
+
+/// Check a container port name against the IANA_SVC_NAME rule: at most 15 lowercase alphanumerics or '-',
+/// containing a letter, without leading, trailing or consecutive '-'
+pub fn check_port_name(value: &str) -> Result<(), String> {
+    if value.is_empty() || value.len() > 15 {
+        return Err(format!("must be 1-15 characters long, got {}", value.len()));
+    }
+    if !value.chars().all(|c| is_alnum(c) || c == '-') {
+        return Err("must consist of lowercase alphanumerics or '-'".to_string());
+    }
+    if !value.chars().any(|c| c.is_ascii_lowercase()) {
+        return Err("must contain at least one letter".to_string());
+    }
+    if value.starts_with('-') || value.ends_with('-') || value.contains("--") {
+        return Err("must not start or end with '-' or contain '--'".to_string());
+    }
+    Ok(())
+}
+
+/// Check a `metadata.name` against the rule Kubernetes applies to the given kind
+pub fn check_resource_name(kind: &str, name: &str) -> Result<(), String> {
+    match kind {
+        "Service" => check_dns1035_label(name),
+        // Used verbatim as a label value (job-name) or in pod hostnames
+        "Namespace" | "Job" | "StatefulSet" => check_dns1123_label(name),
+        "CronJob" => {
+            check_dns1123_subdomain(name)?;
+            if name.len() > CRONJOB_NAME_MAX {
+                return Err(format!(
+                    "must be at most {} characters long, got {}",
+                    CRONJOB_NAME_MAX,
+                    name.len()
+                ));
+            }
+            Ok(())
+        }
+        _ => check_dns1123_subdomain(name),
+    }
+}
+
+fn check_labels(labels: Option<&Value>, field: &str, mut report: impl FnMut(String, String, String)) {
+    let Some(labels) = labels.and_then(|l| l.as_mapping()) else {
+        return;
+    };
+    for (key, value) in labels {
+        let key = key.as_str().unwrap_or_default();
+        if let Err(message) = check_label_key(key) {
+            report(format!("{} key", field), key.to_string(), message);
+        }
+        let value = match value {
+            Value::String(s) => s.clone(),
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => String::new(),
+        };
+        if let Err(message) = check_label_value(&value) {
+            report(format!("{}.{}", field, key), value, message);
+        }
+    }
+}
+
+/// Check every resource name, label, selector and port name of a render against the Kubernetes rules
+pub fn check_rendered_names(documents: &[Value]) -> Vec<NameViolation> {
+    let mut violations = Vec::new();
+
+    for doc in documents {
+        let kind = resource_kind(doc).unwrap_or_default().to_string();
+        let name = resource_name(doc).unwrap_or_default().to_string();
+        let mut report = |field: String, value: String, message: String| {
+            violations.push(NameViolation {
+                kind: kind.clone(),
+                name: name.clone(),
+                field,
+                value,
+                message,
+            })
+        };
+
+        if let Err(message) = check_resource_name(&kind, &name) {
+            report("metadata.name".to_string(), name.clone(), message);
+        }
+
+        check_labels(doc.get("metadata").and_then(|m| m.get("labels")), "metadata.labels", &mut report);
+        let spec = doc.get("spec");
+        if kind == "Service" {
+            check_labels(spec.and_then(|s| s.get("selector")), "spec.selector", &mut report);
+        } else {
+            check_labels(
+                spec.and_then(|s| s.get("selector")).and_then(|s| s.get("matchLabels")),
+                "spec.selector.matchLabels",
+                &mut report,
+            );
+        }
+        let template_metadata = spec
+            .and_then(|s| s.get("jobTemplate"))
+            .and_then(|j| j.get("spec"))
+            .or(spec)
+            .and_then(|s| s.get("template"))
+            .and_then(|t| t.get("metadata"));
+        check_labels(
+            template_metadata.and_then(|m| m.get("labels")),
+            "spec.template.metadata.labels",
+            &mut report,
+        );
+
+        if kind == "Service" {
+            let ports = spec.and_then(|s| s.get("ports")).and_then(|p| p.as_sequence());
+            for (index, port) in ports.into_iter().flatten().enumerate() {
+                if let Some(port_name) = port.get("name").and_then(|n| n.as_str()) {
+                    if let Err(message) = check_dns1123_label(port_name) {
+                        report(format!("spec.ports[{}].name", index), port_name.to_string(), message);
+                    }
+                }
+            }
+        }
+
+        if let Some(pod_spec) = pod_spec(doc) {
+            for container in pod_containers(pod_spec) {
+                let container_name = container.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+                let ports = container.get("ports").and_then(|p| p.as_sequence());
+                for port in ports.into_iter().flatten() {
+                    if let Some(port_name) = port.get("name").and_then(|n| n.as_str()) {
+                        if let Err(message) = check_port_name(port_name) {
+                            report(
+                                format!("container {} port name", container_name),
+                                port_name.to_string(),
+                                message,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    violations
+}
@@ -0,0 +1,186 @@
+use anyhow::Result;
+use serde_yaml::{Mapping, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use crate::values_fuzz::set_value_at;
+use crate::values_schema::enum_value_paths;
+use crate::{parse_yaml_documents, run_helm_template_with_overlay};
+
+/// A values key varied by the matrix together with the levels it takes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Factor {
+    pub path: Vec<String>,
+    pub levels: Vec<Value>,
+}
+
+impl Factor {
+    pub fn new(path: &str, levels: Vec<Value>) -> Self {
+        Factor {
+            path: path.split('.').map(str::to_string).collect(),
+            levels,
+        }
+    }
+
+    pub fn display_path(&self) -> String {
+        self.path.join(".")
+    }
+}
+
+/// Discover the boolean toggles of a values file and the keys whose allowed values a comment
+/// such as `# One of: ClusterIP, NodePort` documents. Lists are not descended into.
+pub fn discover_factors(values_yaml: &str) -> Result<Vec<Factor>> {
+    fn walk(value: &Value, path: &mut Vec<String>, enums: &BTreeMap<Vec<String>, Vec<String>>, out: &mut Vec<Factor>) {
+        match value {
+            Value::Bool(_) => out.push(Factor {
+                path: path.clone(),
+                levels: vec![Value::Bool(true), Value::Bool(false)],
+            }),
+            Value::String(current) => {
+                if let Some(levels) = enums.get(path.as_slice()) {
+                    let mut levels = levels.clone();
+                    // Keep the chart default as the first level
+                    if let Some(index) = levels.iter().position(|l| l == current) {
+                        levels[..=index].rotate_right(1);
+                    }
+                    out.push(Factor {
+                        path: path.clone(),
+                        levels: levels.into_iter().map(Value::from).collect(),
+                    });
+                }
+            }
+            Value::Mapping(map) => {
+                for (key, child) in map {
+                    if let Some(key) = key.as_str() {
+                        path.push(key.to_string());
+                        walk(child, path, enums, out);
+                        path.pop();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let values: Value = serde_yaml::from_str(values_yaml)?;
+    let mut out = Vec::new();
+    walk(&values, &mut Vec::new(), &enum_value_paths(values_yaml), &mut out);
+    Ok(out)
+}
+
+/// Generate rows of level indices such that every pair of levels of every two factors appears in
+/// some row; without factors, the one row is the chart defaults
+pub fn pairwise_combinations(factors: &[Factor]) -> Vec<Vec<usize>> {
+    if factors.is_empty() {
+        return vec![Vec::new()];
+    }
+
+    let mut uncovered: BTreeSet<(usize, usize, usize, usize)> = BTreeSet::new();
+    for i in 0..factors.len() {
+        for j in i + 1..factors.len() {
+            for a in 0..factors[i].levels.len() {
+                for b in 0..factors[j].levels.len() {
+                    uncovered.insert((i, a, j, b));
+                }
+            }
+        }
+    }
+
+    let mut rows = Vec::new();
+    if factors.len() == 1 {
+        rows.extend((0..factors[0].levels.len()).map(|a| vec![a]));
+        return rows;
+    }
+
+    while let Some(&(i, a, j, b)) = uncovered.iter().next() {
+        let mut row: Vec<Option<usize>> = vec![None; factors.len()];
+        row[i] = Some(a);
+        row[j] = Some(b);
+
+        for k in 0..factors.len() {
+            if row[k].is_some() {
+                continue;
+            }
+            // Pick the level covering the most pairs with the factors assigned so far
+            let gain = |level: usize| {
+                row.iter()
+                    .enumerate()
+                    .filter_map(|(other, assigned)| Some((other, (*assigned)?)))
+                    .filter(|&(other, other_level)| {
+                        let pair = if other < k {
+                            (other, other_level, k, level)
+                        } else {
+                            (k, level, other, other_level)
+                        };
+                        uncovered.contains(&pair)
+                    })
+                    .count()
+            };
+            let best = (0..factors[k].levels.len())
+                .max_by(|x, y| gain(*x).cmp(&gain(*y)).then(y.cmp(x)))
+                .unwrap_or(0);
+            row[k] = Some(best);
+        }
+
+        let row: Vec<usize> = row.into_iter().map(|level| level.unwrap_or(0)).collect();
+        for x in 0..row.len() {
+            for y in x + 1..row.len() {
+                uncovered.remove(&(x, row[x], y, row[y]));
+            }
+        }
+        rows.push(row);
+    }
+    rows
+}
+
+/// Build the values overlay selecting one level of every factor
+pub fn combination_overlay(factors: &[Factor], row: &[usize]) -> Value {
+    let mut overlay = Value::Mapping(Mapping::new());
+    for (factor, level) in factors.iter().zip(row) {
+        set_value_at(&mut overlay, &factor.path, factor.levels[*level].clone());
+    }
+    overlay
+}
+
+/// A combination whose render failed or was rejected by the checker
+#[derive(Debug, Clone)]
+pub struct MatrixFailure {
+    pub row: Vec<usize>,
+    /// Values overlay reproducing the failure with `helm template -f`
+    pub overlay: Value,
+    pub error: String,
+}
+
+impl fmt::Display for MatrixFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let overlay = serde_yaml::to_string(&self.overlay).unwrap_or_default();
+        write!(f, "{}\nreproduce with overlay:\n{}", self.error, overlay)
+    }
+}
+
+/// Render every pairwise combination of `factors` and run `check` on each render
+pub fn run_pairwise_matrix<F>(
+    chart_path: &str,
+    factors: &[Factor],
+    values: Option<&HashMap<String, String>>,
+    mut check: F,
+) -> Result<Vec<MatrixFailure>>
+where
+    F: FnMut(&[Value]) -> Result<()>,
+{
+    let mut failures = Vec::new();
+    for row in pairwise_combinations(factors) {
+        let overlay = combination_overlay(factors, &row);
+        let result = run_helm_template_with_overlay(chart_path, &overlay, values)
+            .and_then(|output| parse_yaml_documents(&output))
+            .and_then(|documents| check(&documents));
+        if let Err(e) = result {
+            failures.push(MatrixFailure {
+                row,
+                overlay,
+                error: e.to_string(),
+            });
+        }
+    }
+    Ok(failures)
+}
@@ -0,0 +1,204 @@
+use anyhow::Result;
+use base64::Engine;
+use serde::Serialize;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::values_coverage::{declared_values, display_path};
+use crate::{is_render_failure, parse_yaml_documents, resource_kind, resource_name, run_helm_template};
+
+/// Rendered text that Go templates and Sprig produce for a missing value
+const EMPTY_MARKERS: &[&str] = &["", "<nil>", "<no value>"];
+
+/// A rendered field that comes out empty when a value is absent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EmptyField {
+    pub kind: String,
+    pub name: String,
+    /// Path of the field, e.g. `data.api-key` or `spec.template.spec.containers[0].env[2].value`
+    pub field: String,
+}
+
+/// A value whose absence silently produces empty output instead of a render failure
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RequiredValue {
+    pub key: String,
+    pub empty_fields: Vec<EmptyField>,
+}
+
+/// Machine-readable list of the values a chart needs to render meaningful output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RequiredValuesReport {
+    pub chart: String,
+    /// Values that render empty fields when absent
+    pub required: Vec<RequiredValue>,
+    /// Values whose absence already makes the render fail, e.g. through `required`
+    pub enforced: Vec<String>,
+}
+
+impl RequiredValuesReport {
+    pub fn keys(&self) -> Vec<&str> {
+        self.required.iter().map(|r| r.key.as_str()).collect()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+fn is_empty_text(text: &str) -> bool {
+    EMPTY_MARKERS.contains(&text.trim())
+}
+
+/// The text a field renders to, with Secret `data` decoded
+pub(crate) fn field_text(doc_kind: &str, field: &str, value: &str) -> String {
+    if doc_kind == "Secret" && field.starts_with("data.") {
+        let decoded = base64::engine::general_purpose::STANDARD.decode(value).unwrap_or_default();
+        return String::from_utf8_lossy(&decoded).to_string();
+    }
+    value.to_string()
+}
+
+pub(crate) fn collect_strings(value: &Value, path: String, out: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Mapping(map) => {
+            for (key, child) in map {
+                let key = key.as_str().unwrap_or_default();
+                let child_path = if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+                collect_strings(child, child_path, out);
+            }
+        }
+        Value::Sequence(items) => {
+            for (index, item) in items.iter().enumerate() {
+                collect_strings(item, format!("{}[{}]", path, index), out);
+            }
+        }
+        Value::String(s) => {
+            out.insert(path, s.clone());
+        }
+        Value::Null => {
+            out.insert(path, String::new());
+        }
+        _ => {}
+    }
+}
+
+/// Fields that are filled in `present` but empty in `absent`, matching documents by kind and name
+pub fn find_emptied_fields(present: &[Value], absent: &[Value]) -> Vec<EmptyField> {
+    let mut emptied = Vec::new();
+    for doc in absent {
+        let (Some(kind), Some(name)) = (resource_kind(doc), resource_name(doc)) else {
+            continue;
+        };
+        let Some(reference) = present
+            .iter()
+            .find(|d| resource_kind(d) == Some(kind) && resource_name(d) == Some(name))
+        else {
+            continue;
+        };
+
+        let mut absent_fields = BTreeMap::new();
+        collect_strings(doc, String::new(), &mut absent_fields);
+        let mut present_fields = BTreeMap::new();
+        collect_strings(reference, String::new(), &mut present_fields);
+
+        for (field, value) in absent_fields {
+            let Some(present_value) = present_fields.get(&field) else {
+                continue;
+            };
+            let absent_text = field_text(kind, &field, &value);
+            let present_text = field_text(kind, &field, present_value);
+            let empty_tag = field.ends_with(".image") && absent_text.ends_with(':');
+            if present_text != absent_text && !is_empty_text(&present_text) && (is_empty_text(&absent_text) || empty_tag) {
+                emptied.push(EmptyField {
+                    kind: kind.to_string(),
+                    name: name.to_string(),
+                    field,
+                });
+            }
+        }
+    }
+    emptied
+}
+
+/// Find the values of a chart whose absence produces empty Secret data, env values or image tags
+///
+/// Every fixture value and every string default in values.yaml is removed in turn, and every
+/// scalar whose default is empty is filled in with a probe, comparing renders with and without it.
+/// Only renders helm rejects count as enforced; failing to run helm at all is returned as an error.
+pub fn detect_required_values(chart_path: &str, fixture: &HashMap<String, String>) -> Result<RequiredValuesReport> {
+    let baseline = parse_yaml_documents(&run_helm_template(chart_path, Some(fixture))?)?;
+    let mut required = Vec::new();
+    let mut enforced = Vec::new();
+
+    let mut keys: Vec<&String> = fixture.keys().collect();
+    keys.sort();
+    for key in keys {
+        let mut values = fixture.clone();
+        values.remove(key);
+        let absent = match run_helm_template(chart_path, Some(&values)) {
+            Ok(output) => parse_yaml_documents(&output)?,
+            Err(e) if is_render_failure(&e) => {
+                enforced.push(key.clone());
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let empty_fields = find_emptied_fields(&baseline, &absent);
+        if !empty_fields.is_empty() {
+            required.push(RequiredValue {
+                key: key.clone(),
+                empty_fields,
+            });
+        }
+    }
+
+    let defaults: Value = serde_yaml::from_str(&std::fs::read_to_string(Path::new(chart_path).join("values.yaml"))?)?;
+    for declared in declared_values(&defaults) {
+        let key = display_path(&declared.path);
+        if !declared.leaf || fixture.contains_key(&key) || key.contains('[') {
+            continue;
+        }
+        let default = declared.path.iter().try_fold(&defaults, |v, k| v.get(k.as_str()));
+        let probe = match default {
+            Some(Value::Null) => true,
+            Some(Value::String(s)) => s.is_empty(),
+            _ => continue,
+        };
+
+        // Empty defaults are filled in with a probe; non-empty ones are deleted with `null`
+        let mut values = fixture.clone();
+        if probe {
+            values.insert(key.clone(), format!("probe-{}", key.replace('.', "-")));
+        } else {
+            values.insert(key.clone(), "null".to_string());
+        }
+        let rendered = match run_helm_template(chart_path, Some(&values)) {
+            Ok(output) => parse_yaml_documents(&output)?,
+            Err(e) if is_render_failure(&e) => {
+                if !probe {
+                    enforced.push(key);
+                }
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let empty_fields = if probe {
+            find_emptied_fields(&rendered, &baseline)
+        } else {
+            find_emptied_fields(&baseline, &rendered)
+        };
+        if !empty_fields.is_empty() {
+            required.push(RequiredValue { key, empty_fields });
+        }
+    }
+
+    required.sort_by(|a, b| a.key.cmp(&b.key));
+    enforced.sort();
+    Ok(RequiredValuesReport {
+        chart: chart_path.to_string(),
+        required,
+        enforced,
+    })
+}
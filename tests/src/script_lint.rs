@@ -0,0 +1,540 @@
+use serde_yaml::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::{pod_containers, pod_spec, resource_kind, resource_name, Severity};
+
+/// Shells whose `-c` body is analyzed
+const SHELLS: &[&str] = &["sh", "bash", "ash", "dash", "zsh"];
+
+/// Variables the shell itself sets
+const SHELL_VARIABLES: &[&str] = &[
+    "HOME", "PATH", "PWD", "OLDPWD", "HOSTNAME", "USER", "SHELL", "TERM", "IFS", "RANDOM", "LINENO", "SECONDS",
+    "UID", "EUID", "PPID", "BASH_SOURCE", "BASH_VERSION", "FUNCNAME", "PIPESTATUS", "REPLY", "OPTARG", "OPTIND",
+];
+
+/// Words that mark a string or heredoc as SQL
+const SQL_KEYWORDS: &[&str] = &[
+    "SELECT", "INSERT", "UPDATE", "DELETE", "CREATE", "ALTER", "DROP", "GRANT", "REVOKE", "TRUNCATE",
+];
+
+/// Where a variable is expanded, which decides whether its value can change the meaning of the script
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpansionContext {
+    /// Outside any quotes; `assignment` is set for the right-hand side of `NAME=$VAR`
+    Unquoted { assignment: bool },
+    /// Inside double quotes; `sql` when the string is a SQL statement, `nested_shell` when it is
+    /// the body of `eval` or another `sh -c`
+    DoubleQuoted { sql: bool, nested_shell: bool },
+    /// Inside an unquoted heredoc; `sql` when the heredoc holds SQL statements
+    Heredoc { sql: bool },
+}
+
+/// A `$VAR` or `${VAR}` in a script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expansion {
+    pub name: String,
+    /// Line of the script, starting at 1
+    pub line: usize,
+    pub context: ExpansionContext,
+    /// `${VAR:-default}` and friends handle an unset variable themselves
+    pub has_default: bool,
+}
+
+/// What a shell script expands and defines
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShellScript {
+    pub expansions: Vec<Expansion>,
+    /// Variables the script assigns, reads or loops over
+    pub definitions: BTreeSet<String>,
+    pub heredocs: Vec<HeredocText>,
+}
+
+/// The body of a heredoc in a script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeredocText {
+    pub delimiter: String,
+    /// A quoted delimiter (`<<'EOF'`) turns off expansion in the body
+    pub quoted: bool,
+    /// Line of the script the body starts on
+    pub line: usize,
+    pub body: String,
+}
+
+/// A problem with how a container script uses its environment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptIssue {
+    pub severity: Severity,
+    /// The workload, e.g. `Job/test-release-simbruna-db-init`
+    pub resource: String,
+    pub container: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {} container '{}' line {}: {}",
+            self.severity, self.resource, self.container, self.line, self.message
+        )
+    }
+}
+
+fn is_identifier(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Whether text contains a SQL statement keyword as a whole word
+pub fn looks_like_sql(text: &str) -> bool {
+    text.split(|c: char| !c.is_ascii_alphabetic())
+        .any(|word| SQL_KEYWORDS.iter().any(|keyword| word.eq_ignore_ascii_case(keyword)))
+}
+
+/// Parse the expansion starting at the `$` at `chars[start]`, returning it and the index after it
+fn parse_expansion(chars: &[char], start: usize) -> Option<(String, bool, usize)> {
+    let mut i = start + 1;
+    if chars.get(i) == Some(&'{') {
+        let close = chars[i..].iter().position(|&c| c == '}')? + i;
+        let inner: String = chars[i + 1..close].iter().collect();
+        let inner = inner.trim_start_matches(['#', '!']);
+        let name: String = inner.chars().take_while(|c| c.is_ascii_alphanumeric() || *c == '_').collect();
+        let operator = &inner[name.len()..];
+        let has_default = [":-", ":=", ":?", ":+", "-", "=", "?", "+"]
+            .iter()
+            .any(|op| operator.starts_with(op));
+        return is_identifier(&name).then_some((name, has_default, close + 1));
+    }
+    let name_start = i;
+    while chars.get(i).is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_') {
+        i += 1;
+    }
+    let name: String = chars[name_start..i].iter().collect();
+    is_identifier(&name).then_some((name, false, i))
+}
+
+/// Expansions in an unquoted heredoc body, where quotes are plain text
+fn heredoc_expansions(body: &[char], first_line: usize, sql: bool, out: &mut Vec<Expansion>) {
+    let mut line = first_line;
+    let mut i = 0;
+    while i < body.len() {
+        match body[i] {
+            '\\' => i += 2,
+            '\n' => {
+                line += 1;
+                i += 1;
+            }
+            '$' => match parse_expansion(body, i) {
+                Some((name, has_default, end)) => {
+                    out.push(Expansion {
+                        name,
+                        line,
+                        context: ExpansionContext::Heredoc { sql },
+                        has_default,
+                    });
+                    i = end;
+                }
+                None => i += 1,
+            },
+            _ => i += 1,
+        }
+    }
+}
+
+/// A pending heredoc: its delimiter, whether the delimiter was quoted and whether it is `<<-`
+struct Heredoc {
+    delimiter: String,
+    quoted: bool,
+    strip_tabs: bool,
+}
+
+/// Read the delimiter of a heredoc whose `<<` ends just before `chars[start]`
+fn parse_heredoc(chars: &[char], start: usize) -> (Heredoc, usize) {
+    let mut i = start;
+    let strip_tabs = chars.get(i) == Some(&'-');
+    if strip_tabs {
+        i += 1;
+    }
+    while chars.get(i).is_some_and(|c| *c == ' ' || *c == '\t') {
+        i += 1;
+    }
+    let mut delimiter = String::new();
+    let mut quoted = false;
+    while let Some(&c) = chars.get(i) {
+        match c {
+            '\'' | '"' => {
+                quoted = true;
+                let close = chars[i + 1..].iter().position(|&q| q == c).map(|p| p + i + 1).unwrap_or(chars.len());
+                delimiter.extend(&chars[i + 1..close]);
+                i = close + 1;
+            }
+            '\\' => {
+                quoted = true;
+                i += 1;
+            }
+            c if c.is_whitespace() || matches!(c, ';' | '|' | '&' | '>' | '<' | ')') => break,
+            c => {
+                delimiter.push(c);
+                i += 1;
+            }
+        }
+    }
+    (Heredoc { delimiter, quoted, strip_tabs }, i)
+}
+
+/// Record the variables a finished command line defines: `NAME=...`, `read NAME` and `for NAME in`
+fn record_definitions(words: &[String], definitions: &mut BTreeSet<String>) {
+    let mut previous: Option<&str> = None;
+    let mut reading = false;
+    for word in words {
+        if let Some((name, _)) = word.split_once('=') {
+            if is_identifier(name) {
+                definitions.insert(name.to_string());
+            }
+        }
+        if reading && is_identifier(word) {
+            definitions.insert(word.clone());
+        } else if reading && !word.starts_with('-') {
+            reading = false;
+        }
+        if previous == Some("for") && is_identifier(word) {
+            definitions.insert(word.clone());
+        }
+        if word == "read" {
+            reading = true;
+        }
+        previous = Some(word.as_str());
+    }
+}
+
+/// Find the heredoc body starting at `start`, returning where the body ends and where its
+/// delimiter line ends
+fn heredoc_body(chars: &[char], start: usize, heredoc: &Heredoc) -> (usize, usize) {
+    let mut line_start = start;
+    while line_start < chars.len() {
+        let line_end = chars[line_start..]
+            .iter()
+            .position(|&c| c == '\n')
+            .map_or(chars.len(), |p| p + line_start);
+        let text: String = chars[line_start..line_end].iter().collect();
+        let text = if heredoc.strip_tabs { text.trim_start_matches('\t') } else { &text };
+        if text == heredoc.delimiter {
+            return (line_start, line_end);
+        }
+        line_start = line_end + 1;
+    }
+    (chars.len(), chars.len())
+}
+
+/// Whether the text before a double-quoted string makes it the body of `eval` or another `sh -c`
+fn runs_nested_shell(prefix: &str) -> bool {
+    let prefix = prefix.trim();
+    prefix.starts_with("eval") || (prefix.ends_with("-c") && SHELLS.iter().any(|s| prefix.contains(s)))
+}
+
+/// Parse a shell script well enough to tell how each variable is expanded and which ones it defines
+pub fn parse_shell_script(script: &str) -> ShellScript {
+    let chars: Vec<char> = script.chars().collect();
+    let mut parsed = ShellScript::default();
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut word_start = 0;
+    let mut words: Vec<String> = Vec::new();
+    let mut heredocs: Vec<Heredoc> = Vec::new();
+    let mut double_quoted: Option<usize> = None;
+    let mut quoted_expansions: Vec<Expansion> = Vec::new();
+    let mut single_quoted = false;
+
+    let end_word = |words: &mut Vec<String>, from: usize, to: usize| {
+        let word: String = chars[from.min(to)..to].iter().collect();
+        if !word.trim().is_empty() {
+            words.push(word.trim().to_string());
+        }
+    };
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' && (single_quoted || double_quoted.is_some()) {
+            line += 1;
+        }
+        if single_quoted {
+            single_quoted = c != '\'';
+            i += 1;
+            continue;
+        }
+        if let Some(start) = double_quoted {
+            match c {
+                '\\' => i += 1,
+                '"' => {
+                    let content: String = chars[start..i].iter().collect();
+                    let prefix: String = chars[line_start..start - 1].iter().collect();
+                    let context = ExpansionContext::DoubleQuoted {
+                        sql: looks_like_sql(&content),
+                        nested_shell: runs_nested_shell(&prefix),
+                    };
+                    for mut expansion in quoted_expansions.drain(..) {
+                        expansion.context = context;
+                        parsed.expansions.push(expansion);
+                    }
+                    double_quoted = None;
+                }
+                '$' => {
+                    if let Some((name, has_default, end)) = parse_expansion(&chars, i) {
+                        quoted_expansions.push(Expansion {
+                            name,
+                            line,
+                            context: ExpansionContext::DoubleQuoted { sql: false, nested_shell: false },
+                            has_default,
+                        });
+                        i = end;
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+            continue;
+        }
+
+        match c {
+            '\\' => i += 1,
+            '\'' => single_quoted = true,
+            '"' => double_quoted = Some(i + 1),
+            '#' if i == word_start => {
+                while chars.get(i + 1).is_some_and(|c| *c != '\n') {
+                    i += 1;
+                }
+            }
+            '$' => {
+                if let Some((name, has_default, end)) = parse_expansion(&chars, i) {
+                    let word: String = chars[word_start..i].iter().collect();
+                    let assignment = word.split_once('=').is_some_and(|(name, _)| is_identifier(name));
+                    parsed.expansions.push(Expansion {
+                        name,
+                        line,
+                        context: ExpansionContext::Unquoted { assignment },
+                        has_default,
+                    });
+                    i = end;
+                    continue;
+                }
+            }
+            '<' if chars.get(i + 1) == Some(&'<') && chars.get(i + 2) != Some(&'<') => {
+                end_word(&mut words, word_start, i);
+                let (heredoc, end) = parse_heredoc(&chars, i + 2);
+                heredocs.push(heredoc);
+                i = end;
+                word_start = i;
+                continue;
+            }
+            c if c.is_whitespace() || matches!(c, ';' | '|' | '&' | '(' | ')') => {
+                end_word(&mut words, word_start, i);
+                word_start = i + 1;
+                if c != ' ' && c != '\t' {
+                    record_definitions(&words, &mut parsed.definitions);
+                    words.clear();
+                }
+                if c == '\n' {
+                    line += 1;
+                    for heredoc in heredocs.drain(..) {
+                        let (body_end, delimiter_end) = heredoc_body(&chars, i + 1, &heredoc);
+                        let body = &chars[i + 1..body_end];
+                        let text: String = body.iter().collect();
+                        if !heredoc.quoted {
+                            heredoc_expansions(body, line, looks_like_sql(&text), &mut parsed.expansions);
+                        }
+                        parsed.heredocs.push(HeredocText {
+                            delimiter: heredoc.delimiter,
+                            quoted: heredoc.quoted,
+                            line,
+                            body: text,
+                        });
+                        line += body.iter().filter(|&&c| c == '\n').count() + 1;
+                        i = delimiter_end;
+                    }
+                    line_start = i + 1;
+                    word_start = i + 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    end_word(&mut words, word_start, chars.len());
+    record_definitions(&words, &mut parsed.definitions);
+    parsed
+}
+
+/// Where a container env var gets its value
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum EnvSource {
+    Secret { name: String, key: String },
+    Other,
+}
+
+/// The `-c` body of a container that runs a shell
+pub fn shell_body(container: &Value) -> Option<&str> {
+    let argv: Vec<&str> = ["command", "args"]
+        .iter()
+        .filter_map(|field| container.get(*field).and_then(|v| v.as_sequence()))
+        .flatten()
+        .filter_map(|v| v.as_str())
+        .collect();
+    let shell = argv.iter().position(|arg| {
+        let program = arg.rsplit('/').next().unwrap_or(arg);
+        SHELLS.contains(&program)
+    })?;
+    let flag = argv[shell + 1..]
+        .iter()
+        .position(|arg| arg.starts_with('-') && !arg.starts_with("--") && arg.contains('c'))?;
+    argv.get(shell + flag + 2).copied()
+}
+
+/// The data keys of a rendered Secret or ConfigMap
+fn object_keys<'a>(documents: &'a [Value], kind: &str, name: &str) -> Option<Vec<&'a str>> {
+    let doc = documents
+        .iter()
+        .find(|d| resource_kind(d) == Some(kind) && resource_name(d) == Some(name))?;
+    Some(
+        ["data", "stringData"]
+            .iter()
+            .filter_map(|field| doc.get(*field).and_then(|d| d.as_mapping()))
+            .flat_map(|data| data.keys().filter_map(|k| k.as_str()))
+            .collect(),
+    )
+}
+
+/// The env vars of a container and where each comes from, and whether the list is complete,
+/// which it is not when an `envFrom` source is missing from the rendered output
+fn container_env(container: &Value, documents: &[Value]) -> (BTreeMap<String, EnvSource>, bool) {
+    let mut env = BTreeMap::new();
+    let mut complete = true;
+    for from in container.get("envFrom").and_then(|e| e.as_sequence()).into_iter().flatten() {
+        let prefix = from.get("prefix").and_then(|p| p.as_str()).unwrap_or_default();
+        for (field, kind) in [("secretRef", "Secret"), ("configMapRef", "ConfigMap")] {
+            let Some(name) = from.get(field).and_then(|r| r.get("name")).and_then(|n| n.as_str()) else {
+                continue;
+            };
+            let Some(keys) = object_keys(documents, kind, name) else {
+                complete = false;
+                continue;
+            };
+            for key in keys {
+                let source = if kind == "Secret" {
+                    EnvSource::Secret {
+                        name: name.to_string(),
+                        key: key.to_string(),
+                    }
+                } else {
+                    EnvSource::Other
+                };
+                env.insert(format!("{}{}", prefix, key), source);
+            }
+        }
+    }
+    for var in container.get("env").and_then(|e| e.as_sequence()).into_iter().flatten() {
+        let Some(name) = var.get("name").and_then(|n| n.as_str()) else {
+            continue;
+        };
+        let secret = var.get("valueFrom").and_then(|v| v.get("secretKeyRef"));
+        let source = match secret {
+            Some(secret) => EnvSource::Secret {
+                name: secret.get("name").and_then(|n| n.as_str()).unwrap_or("?").to_string(),
+                key: secret.get("key").and_then(|k| k.as_str()).unwrap_or("?").to_string(),
+            },
+            None => EnvSource::Other,
+        };
+        env.insert(name.to_string(), source);
+    }
+    (env, complete)
+}
+
+/// Report secret-sourced env vars interpolated into SQL, heredocs or commands without safe
+/// quoting, and variables used by a script but defined nowhere, in the shell scripts of Jobs and Pods
+pub fn lint_container_scripts(documents: &[Value]) -> Vec<ScriptIssue> {
+    let mut issues = Vec::new();
+    for doc in documents {
+        let (Some(kind), Some(name)) = (resource_kind(doc), resource_name(doc)) else {
+            continue;
+        };
+        if !matches!(kind, "Job" | "CronJob" | "Pod") {
+            continue;
+        }
+        let Some(spec) = pod_spec(doc) else {
+            continue;
+        };
+
+        for container in pod_containers(spec) {
+            let Some(body) = shell_body(container) else {
+                continue;
+            };
+            let container_name = container.get("name").and_then(|n| n.as_str()).unwrap_or("?");
+            let (env, env_complete) = container_env(container, documents);
+            let script = parse_shell_script(body);
+            let mut report = |severity: Severity, line: usize, message: String| {
+                issues.push(ScriptIssue {
+                    severity,
+                    resource: format!("{}/{}", kind, name),
+                    container: container_name.to_string(),
+                    line,
+                    message,
+                })
+            };
+
+            let mut undefined = BTreeSet::new();
+            for expansion in &script.expansions {
+                let variable = &expansion.name;
+                match env.get(variable) {
+                    Some(EnvSource::Secret { name: secret, key }) => {
+                        let source = format!("${} (from Secret {} key {})", variable, secret, key);
+                        let problem = match expansion.context {
+                            ExpansionContext::Heredoc { sql: true } => Some((
+                                Severity::Error,
+                                "is expanded into SQL in an unquoted heredoc; a value containing ' breaks or injects statements",
+                            )),
+                            ExpansionContext::Heredoc { sql: false } => Some((
+                                Severity::Warning,
+                                "is expanded in an unquoted heredoc",
+                            )),
+                            ExpansionContext::DoubleQuoted { sql: true, .. } => Some((
+                                Severity::Error,
+                                "is expanded into a SQL string; a value containing ' breaks or injects statements",
+                            )),
+                            ExpansionContext::DoubleQuoted { nested_shell: true, .. } => Some((
+                                Severity::Error,
+                                "is expanded into a command string that another shell parses",
+                            )),
+                            ExpansionContext::Unquoted { assignment: false } => Some((
+                                Severity::Warning,
+                                "is expanded without quotes, so word splitting and globbing apply",
+                            )),
+                            _ => None,
+                        };
+                        if let Some((severity, message)) = problem {
+                            report(severity, expansion.line, format!("{} {}", source, message));
+                        }
+                    }
+                    Some(EnvSource::Other) => {}
+                    None => {
+                        let defined = expansion.has_default
+                            || !env_complete
+                            || script.definitions.contains(variable)
+                            || SHELL_VARIABLES.contains(&variable.as_str());
+                        if !defined && undefined.insert(variable.clone()) {
+                            report(
+                                Severity::Error,
+                                expansion.line,
+                                format!("${} is not defined in the container env or by the script", variable),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+    issues
+}
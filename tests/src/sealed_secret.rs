@@ -0,0 +1,249 @@
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{bail, Result};
+use base64::Engine;
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use rsa::rand_core::RngCore;
+use rsa::{Oaep, RsaPrivateKey, RsaPublicKey};
+use serde_yaml::{Mapping, Value};
+use sha2::Sha256;
+use x509_cert::der::{DecodePem, Encode};
+use x509_cert::Certificate;
+
+use crate::{resource_kind, resource_name, secret_data};
+
+const SESSION_KEY_BYTES: usize = 32;
+const NAMESPACE_WIDE_ANNOTATION: &str = "sealedsecrets.bitnami.com/namespace-wide";
+const CLUSTER_WIDE_ANNOTATION: &str = "sealedsecrets.bitnami.com/cluster-wide";
+/// Annotations kubeseal drops from the template, since they can hold the plaintext Secret
+const STRIPPED_ANNOTATIONS: &[&str] = &["kubectl.kubernetes.io/last-applied-configuration"];
+
+/// Where a SealedSecret may be unsealed; the scope is bound into the RSA-OAEP label
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SealingScope {
+    /// Only under the same name and namespace
+    Strict,
+    /// Under any name in the same namespace
+    NamespaceWide,
+    /// Under any name in any namespace
+    ClusterWide,
+}
+
+impl SealingScope {
+    /// The OAEP label kubeseal uses for this scope
+    pub fn label(&self, namespace: &str, name: &str) -> String {
+        match self {
+            SealingScope::Strict => format!("{}/{}", namespace, name),
+            SealingScope::NamespaceWide => namespace.to_string(),
+            SealingScope::ClusterWide => String::new(),
+        }
+    }
+
+    fn annotation(&self) -> Option<&'static str> {
+        match self {
+            SealingScope::Strict => None,
+            SealingScope::NamespaceWide => Some(NAMESPACE_WIDE_ANNOTATION),
+            SealingScope::ClusterWide => Some(CLUSTER_WIDE_ANNOTATION),
+        }
+    }
+
+    /// The scope a SealedSecret's annotations declare
+    pub fn of(sealed: &Value) -> SealingScope {
+        let annotation = |key: &str| crate::resource_annotation(sealed, key) == Some("true");
+        if annotation(CLUSTER_WIDE_ANNOTATION) {
+            SealingScope::ClusterWide
+        } else if annotation(NAMESPACE_WIDE_ANNOTATION) {
+            SealingScope::NamespaceWide
+        } else {
+            SealingScope::Strict
+        }
+    }
+}
+
+/// The sealing key from a PEM certificate, as `kubeseal --fetch-cert` prints it, or a PEM public key
+pub fn load_sealing_key(pem: &str) -> Result<RsaPublicKey> {
+    if pem.contains("BEGIN CERTIFICATE") {
+        let certificate = Certificate::from_pem(pem.trim().as_bytes())?;
+        let spki = certificate.tbs_certificate.subject_public_key_info.to_der()?;
+        return Ok(RsaPublicKey::from_public_key_der(&spki)?);
+    }
+    Ok(RsaPublicKey::from_public_key_pem(pem.trim())?)
+}
+
+/// The controller's private key, in PKCS#8 or PKCS#1 PEM
+pub fn load_unsealing_key(pem: &str) -> Result<RsaPrivateKey> {
+    if pem.contains("BEGIN RSA PRIVATE KEY") {
+        return Ok(RsaPrivateKey::from_pkcs1_pem(pem.trim())?);
+    }
+    Ok(RsaPrivateKey::from_pkcs8_pem(pem.trim())?)
+}
+
+/// kubeseal's hybrid encryption: a fresh AES-256-GCM session key sealed with RSA-OAEP-SHA256 under
+/// `label`, laid out as `u16 RSA ciphertext length || RSA ciphertext || AES-GCM ciphertext`
+pub fn hybrid_encrypt(key: &RsaPublicKey, plaintext: &[u8], label: &str) -> Result<Vec<u8>> {
+    let mut session_key = [0u8; SESSION_KEY_BYTES];
+    OsRng.fill_bytes(&mut session_key);
+    let sealed_key = key.encrypt(&mut OsRng, Oaep::new_with_label::<Sha256, _>(label), &session_key)?;
+
+    let mut ciphertext = (sealed_key.len() as u16).to_be_bytes().to_vec();
+    ciphertext.extend(&sealed_key);
+    // The session key is used once, so a zero nonce is safe
+    let cipher = Aes256Gcm::new_from_slice(&session_key)?;
+    let sealed = cipher
+        .encrypt(&Nonce::default(), plaintext)
+        .map_err(|_| anyhow::anyhow!("AES-GCM encryption failed"))?;
+    ciphertext.extend(sealed);
+    Ok(ciphertext)
+}
+
+/// Reverse [`hybrid_encrypt`]; fails when `label` differs from the one used to seal
+pub fn hybrid_decrypt(key: &RsaPrivateKey, ciphertext: &[u8], label: &str) -> Result<Vec<u8>> {
+    let Some((length, rest)) = ciphertext.split_first_chunk::<2>() else {
+        bail!("ciphertext is too short");
+    };
+    let length = u16::from_be_bytes(*length) as usize;
+    if rest.len() < length {
+        bail!("ciphertext is shorter than its {}-byte RSA block", length);
+    }
+    let (sealed_key, sealed) = rest.split_at(length);
+    let session_key = key
+        .decrypt(Oaep::new_with_label::<Sha256, _>(label), sealed_key)
+        .map_err(|_| anyhow::anyhow!("cannot decrypt the session key with label '{}': wrong key or scope", label))?;
+    Aes256Gcm::new_from_slice(&session_key)?
+        .decrypt(&Nonce::default(), sealed)
+        .map_err(|_| anyhow::anyhow!("AES-GCM decryption failed"))
+}
+
+fn metadata_string<'a>(doc: &'a Value, field: &str) -> Option<&'a str> {
+    doc.get("metadata").and_then(|m| m.get(field)).and_then(|v| v.as_str())
+}
+
+/// Seal a Secret into a Bitnami SealedSecret the way `kubeseal --scope` does; `namespace` is used
+/// when the Secret has none, as `helm template` output usually doesn't
+pub fn seal_secret(secret: &Value, namespace: &str, scope: SealingScope, key: &RsaPublicKey) -> Result<Value> {
+    if resource_kind(secret) != Some("Secret") {
+        bail!("only Secrets can be sealed");
+    }
+    let Some(name) = resource_name(secret) else {
+        bail!("Secret has no name");
+    };
+    let namespace = metadata_string(secret, "namespace").unwrap_or(namespace);
+    let label = scope.label(namespace, name);
+
+    let mut encrypted = Mapping::new();
+    let b64 = &base64::engine::general_purpose::STANDARD;
+    for (data_key, value) in secret_data(secret)? {
+        encrypted.insert(data_key.into(), b64.encode(hybrid_encrypt(key, &value, &label)?).into());
+    }
+
+    let mut template_metadata = Mapping::new();
+    template_metadata.insert("name".into(), name.into());
+    template_metadata.insert("namespace".into(), namespace.into());
+    let source_metadata = secret.get("metadata");
+    if let Some(labels) = source_metadata.and_then(|m| m.get("labels")) {
+        template_metadata.insert("labels".into(), labels.clone());
+    }
+    if let Some(Value::Mapping(annotations)) = source_metadata.and_then(|m| m.get("annotations")) {
+        let mut annotations = annotations.clone();
+        for stripped in STRIPPED_ANNOTATIONS {
+            annotations.remove(*stripped);
+        }
+        if !annotations.is_empty() {
+            template_metadata.insert("annotations".into(), Value::Mapping(annotations));
+        }
+    }
+    let mut template = Mapping::new();
+    template.insert("metadata".into(), Value::Mapping(template_metadata));
+    template.insert("type".into(), secret.get("type").cloned().unwrap_or_else(|| "Opaque".into()));
+    if let Some(immutable) = secret.get("immutable") {
+        template.insert("immutable".into(), immutable.clone());
+    }
+
+    let mut metadata = Mapping::new();
+    metadata.insert("name".into(), name.into());
+    metadata.insert("namespace".into(), namespace.into());
+    if let Some(annotation) = scope.annotation() {
+        let mut annotations = Mapping::new();
+        annotations.insert(annotation.into(), "true".into());
+        metadata.insert("annotations".into(), Value::Mapping(annotations));
+    }
+    let mut spec = Mapping::new();
+    spec.insert("encryptedData".into(), Value::Mapping(encrypted));
+    spec.insert("template".into(), Value::Mapping(template));
+
+    let mut sealed = Mapping::new();
+    sealed.insert("apiVersion".into(), "bitnami.com/v1alpha1".into());
+    sealed.insert("kind".into(), "SealedSecret".into());
+    sealed.insert("metadata".into(), Value::Mapping(metadata));
+    sealed.insert("spec".into(), Value::Mapping(spec));
+    Ok(Value::Mapping(sealed))
+}
+
+/// Replace every rendered `Opaque` Secret with its SealedSecret, leaving other documents as they are
+pub fn seal_rendered_secrets(
+    documents: &[Value],
+    namespace: &str,
+    scope: SealingScope,
+    key: &RsaPublicKey,
+) -> Result<Vec<Value>> {
+    documents
+        .iter()
+        .map(|doc| {
+            let opaque = doc.get("type").and_then(|t| t.as_str()).unwrap_or("Opaque") == "Opaque";
+            if resource_kind(doc) == Some("Secret") && opaque {
+                seal_secret(doc, namespace, scope, key)
+            } else {
+                Ok(doc.clone())
+            }
+        })
+        .collect()
+}
+
+/// Unseal a SealedSecret with the controller's private key, as the controller would under the
+/// SealedSecret's current name and namespace
+pub fn unseal_secret(sealed: &Value, key: &RsaPrivateKey) -> Result<Value> {
+    if resource_kind(sealed) != Some("SealedSecret") {
+        bail!("not a SealedSecret");
+    }
+    let (Some(name), Some(namespace)) = (resource_name(sealed), metadata_string(sealed, "namespace")) else {
+        bail!("SealedSecret needs a name and namespace");
+    };
+    let label = SealingScope::of(sealed).label(namespace, name);
+    let spec = sealed.get("spec");
+
+    let mut data = Mapping::new();
+    let b64 = &base64::engine::general_purpose::STANDARD;
+    let encrypted = spec.and_then(|s| s.get("encryptedData")).and_then(|d| d.as_mapping());
+    for (data_key, value) in encrypted.into_iter().flatten() {
+        let (Some(data_key), Some(value)) = (data_key.as_str(), value.as_str()) else {
+            bail!("encryptedData must map strings to base64 strings");
+        };
+        let plaintext = hybrid_decrypt(key, &b64.decode(value)?, &label)
+            .map_err(|e| anyhow::anyhow!("{}/{} key {}: {}", namespace, name, data_key, e))?;
+        data.insert(data_key.into(), b64.encode(plaintext).into());
+    }
+
+    let template = spec.and_then(|s| s.get("template"));
+    let mut metadata = template
+        .and_then(|t| t.get("metadata"))
+        .and_then(|m| m.as_mapping())
+        .cloned()
+        .unwrap_or_default();
+    metadata.insert("name".into(), name.into());
+    metadata.insert("namespace".into(), namespace.into());
+
+    let mut secret = Mapping::new();
+    secret.insert("apiVersion".into(), "v1".into());
+    secret.insert("kind".into(), "Secret".into());
+    secret.insert("metadata".into(), Value::Mapping(metadata));
+    secret.insert(
+        "type".into(),
+        template.and_then(|t| t.get("type")).cloned().unwrap_or_else(|| "Opaque".into()),
+    );
+    if let Some(immutable) = template.and_then(|t| t.get("immutable")) {
+        secret.insert("immutable".into(), immutable.clone());
+    }
+    secret.insert("data".into(), Value::Mapping(data));
+    Ok(Value::Mapping(secret))
+}
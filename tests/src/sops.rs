@@ -0,0 +1,326 @@
+use aes_gcm::aead::consts::U32;
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::aes::Aes256;
+use aes_gcm::AesGcm;
+use age::x25519::{Identity, Recipient};
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use serde_yaml::{Mapping, Value};
+use sha2::{Digest, Sha512};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// SOPS encrypts values with AES-256-GCM under a 32-byte nonce
+type SopsCipher = AesGcm<Aes256, U32>;
+
+const TAG_LENGTH: usize = 16;
+const UNENCRYPTED_SUFFIX: &str = "_unencrypted";
+const SOPS_VERSION: &str = "3.9.0";
+
+/// Parse the `AGE-SECRET-KEY-1…` lines of an age key file, skipping comments and blank lines
+pub fn parse_age_identities(key_file: &str) -> Result<Vec<Identity>> {
+    let identities = key_file
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.parse::<Identity>()
+                .map_err(|e| anyhow::anyhow!("invalid age identity: {}", e))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if identities.is_empty() {
+        bail!("no age identities in key file");
+    }
+    Ok(identities)
+}
+
+/// The age identities sops itself would use: `SOPS_AGE_KEY`, then the file named by `SOPS_AGE_KEY_FILE`
+pub fn age_identities_from_env() -> Result<Vec<Identity>> {
+    if let Ok(keys) = std::env::var("SOPS_AGE_KEY") {
+        return parse_age_identities(&keys);
+    }
+    let Some(path) = std::env::var_os("SOPS_AGE_KEY_FILE") else {
+        bail!("set SOPS_AGE_KEY or SOPS_AGE_KEY_FILE to decrypt SOPS values files");
+    };
+    parse_age_identities(
+        &std::fs::read_to_string(&path).with_context(|| format!("reading {:?}", path))?,
+    )
+}
+
+/// A number as Go's `strconv` prints it: floats in plain decimal notation, so `2.0` is `2`
+fn number_text(number: &serde_yaml::Number) -> String {
+    match number.as_f64().filter(|_| number.is_f64()) {
+        Some(float) => float.to_string(),
+        None => number.to_string(),
+    }
+}
+
+/// The bytes SOPS feeds to the MAC for a value
+fn mac_bytes(value: &Value) -> Result<Vec<u8>> {
+    Ok(match value {
+        Value::String(s) => s.as_bytes().to_vec(),
+        Value::Number(n) => number_text(n).into_bytes(),
+        Value::Bool(true) => b"True".to_vec(),
+        Value::Bool(false) => b"False".to_vec(),
+        _ => bail!("SOPS cannot encrypt {:?}", value),
+    })
+}
+
+/// Call `visit` on every scalar in document order with its `key:key:` additional data; lists
+/// don't add to the path, as in SOPS
+fn walk_values(
+    value: &mut Value,
+    path: &str,
+    visit: &mut dyn FnMut(&mut Value, &str) -> Result<()>,
+) -> Result<()> {
+    match value {
+        Value::Mapping(map) => {
+            for (key, child) in map.iter_mut() {
+                let key = match key {
+                    Value::String(s) => s.clone(),
+                    Value::Number(n) => n.to_string(),
+                    _ => bail!(
+                        "SOPS values files need scalar keys, found {:?} under {}",
+                        key,
+                        path
+                    ),
+                };
+                walk_values(child, &format!("{}{}:", path, key), visit)?;
+            }
+            Ok(())
+        }
+        Value::Sequence(items) => items
+            .iter_mut()
+            .try_for_each(|item| walk_values(item, path, visit)),
+        _ => visit(value, path),
+    }
+}
+
+fn encrypt_value(key: &[u8], value: &Value, additional_data: &str) -> Result<String> {
+    let (plaintext, kind) = match value {
+        Value::String(s) => (s.clone(), "str"),
+        Value::Number(n) if n.is_f64() => (number_text(n), "float"),
+        Value::Number(n) => (number_text(n), "int"),
+        Value::Bool(b) => (b.to_string(), "bool"),
+        _ => bail!("SOPS cannot encrypt {:?} at {}", value, additional_data),
+    };
+    let cipher = SopsCipher::new(GenericArray::from_slice(key));
+    let iv = SopsCipher::generate_nonce(&mut OsRng);
+    let sealed = cipher
+        .encrypt(
+            &iv,
+            Payload {
+                msg: plaintext.as_bytes(),
+                aad: additional_data.as_bytes(),
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("could not encrypt {}", additional_data))?;
+    let (data, tag) = sealed.split_at(sealed.len() - TAG_LENGTH);
+    let b64 = &base64::engine::general_purpose::STANDARD;
+    Ok(format!(
+        "ENC[AES256_GCM,data:{},iv:{},tag:{},type:{}]",
+        b64.encode(data),
+        b64.encode(iv),
+        b64.encode(tag),
+        kind
+    ))
+}
+
+/// Decrypt an `ENC[AES256_GCM,…]` value back to its typed YAML value
+fn decrypt_value(key: &[u8], encrypted: &str, additional_data: &str) -> Result<Value> {
+    let Some(fields) = encrypted
+        .strip_prefix("ENC[AES256_GCM,")
+        .and_then(|f| f.strip_suffix(']'))
+    else {
+        bail!("{} is not an ENC[AES256_GCM,…] value", additional_data);
+    };
+    let field = |name: &str| {
+        fields
+            .split(',')
+            .find_map(|f| f.strip_prefix(name).and_then(|f| f.strip_prefix(':')))
+            .ok_or_else(|| {
+                anyhow::anyhow!("encrypted value at {} has no {}", additional_data, name)
+            })
+    };
+    let b64 = &base64::engine::general_purpose::STANDARD;
+    let mut sealed = b64.decode(field("data")?)?;
+    sealed.extend(b64.decode(field("tag")?)?);
+    let iv = b64.decode(field("iv")?)?;
+    if iv.len() != 32 {
+        bail!(
+            "encrypted value at {} has a {}-byte iv; SOPS uses 32",
+            additional_data,
+            iv.len()
+        );
+    }
+    let cipher = SopsCipher::new(GenericArray::from_slice(key));
+    let plaintext = cipher
+        .decrypt(
+            GenericArray::from_slice(&iv),
+            Payload {
+                msg: &sealed,
+                aad: additional_data.as_bytes(),
+            },
+        )
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "could not decrypt {}: wrong data key or tampered value",
+                additional_data
+            )
+        })?;
+    let text = String::from_utf8(plaintext)?;
+    Ok(match field("type")? {
+        "str" => Value::String(text),
+        "int" => Value::Number(text.parse::<i64>()?.into()),
+        "float" => Value::Number(text.parse::<f64>()?.into()),
+        "bool" => Value::Bool(text.eq_ignore_ascii_case("true")),
+        other => bail!(
+            "encrypted value at {} has unsupported type {}",
+            additional_data,
+            other
+        ),
+    })
+}
+
+fn hex_upper(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// The current time as SOPS writes `lastmodified`, e.g. `2024-05-01T12:00:00Z`
+fn rfc3339_now() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (days, time) = ((seconds / 86400) as i64, seconds % 86400);
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Decrypt a SOPS-encrypted YAML values file with age identities and verify its MAC, returning the
+/// plaintext values without the `sops` metadata
+pub fn decrypt_sops_values(encrypted_yaml: &str, identities: &[Identity]) -> Result<Value> {
+    let mut values: Value = serde_yaml::from_str(encrypted_yaml)?;
+    let Some(metadata) = values.as_mapping_mut().and_then(|m| m.remove("sops")) else {
+        bail!("not a SOPS file: no sops metadata");
+    };
+
+    let stanzas = metadata
+        .get("age")
+        .and_then(|a| a.as_sequence())
+        .cloned()
+        .unwrap_or_default();
+    if stanzas.is_empty() {
+        bail!("SOPS file has no age recipients");
+    }
+    let data_key = stanzas
+        .iter()
+        .filter_map(|stanza| stanza.get("enc").and_then(|e| e.as_str()))
+        .find_map(|armored| {
+            identities
+                .iter()
+                .find_map(|identity| age::decrypt(identity, armored.as_bytes()).ok())
+        })
+        .ok_or_else(|| {
+            let recipients: Vec<&str> = stanzas
+                .iter()
+                .filter_map(|s| s.get("recipient").and_then(|r| r.as_str()))
+                .collect();
+            anyhow::anyhow!(
+                "none of the age identities can decrypt the data key (recipients: {})",
+                recipients.join(", ")
+            )
+        })?;
+    if data_key.len() != 32 {
+        bail!("SOPS data key is {} bytes, not 32", data_key.len());
+    }
+
+    let mut hash = Sha512::new();
+    walk_values(&mut values, "", &mut |value, path| {
+        if let Some(encrypted) = value.as_str().filter(|v| v.starts_with("ENC[")) {
+            *value = decrypt_value(&data_key, encrypted, path)?;
+        }
+        hash.update(mac_bytes(value)?);
+        Ok(())
+    })?;
+
+    let (Some(mac), Some(last_modified)) = (
+        metadata.get("mac").and_then(|m| m.as_str()),
+        metadata.get("lastmodified").and_then(|l| l.as_str()),
+    ) else {
+        bail!("SOPS file has no mac or lastmodified");
+    };
+    let expected =
+        decrypt_value(&data_key, mac, last_modified).context("decrypting the SOPS MAC")?;
+    if expected.as_str() != Some(hex_upper(&hash.finalize()).as_str()) {
+        bail!("SOPS MAC mismatch: the values file was modified without sops");
+    }
+    Ok(values)
+}
+
+/// Encrypt a plaintext YAML values file for age recipients the way `sops --encrypt --age` does, so
+/// fixtures can mirror encrypted production values; values under a key ending in `_unencrypted` stay
+/// plaintext
+pub fn encrypt_sops_values(plain_yaml: &str, recipients: &[Recipient]) -> Result<String> {
+    if recipients.is_empty() {
+        bail!("encrypting a SOPS file needs at least one age recipient");
+    }
+    let mut values: Value = serde_yaml::from_str(plain_yaml)?;
+    if !values.is_mapping() {
+        bail!("SOPS values files must be YAML mappings");
+    }
+    let data_key = SopsCipher::generate_key(&mut OsRng);
+
+    let mut hash = Sha512::new();
+    walk_values(&mut values, "", &mut |value, path| {
+        hash.update(mac_bytes(value)?);
+        if !path.split(':').any(|key| key.ends_with(UNENCRYPTED_SUFFIX)) {
+            *value = Value::String(encrypt_value(&data_key, value, path)?);
+        }
+        Ok(())
+    })?;
+
+    let mut age_stanzas = Vec::new();
+    for recipient in recipients {
+        let armored = age::encrypt_and_armor(recipient, &data_key)
+            .map_err(|e| anyhow::anyhow!("age encryption failed: {}", e))?;
+        let mut stanza = Mapping::new();
+        stanza.insert("recipient".into(), recipient.to_string().into());
+        stanza.insert("enc".into(), armored.into());
+        age_stanzas.push(Value::Mapping(stanza));
+    }
+    let last_modified = rfc3339_now();
+    let mac = encrypt_value(
+        &data_key,
+        &Value::String(hex_upper(&hash.finalize())),
+        &last_modified,
+    )?;
+
+    let mut metadata = Mapping::new();
+    metadata.insert("age".into(), Value::Sequence(age_stanzas));
+    metadata.insert("lastmodified".into(), last_modified.into());
+    metadata.insert("mac".into(), mac.into());
+    metadata.insert("unencrypted_suffix".into(), UNENCRYPTED_SUFFIX.into());
+    metadata.insert("version".into(), SOPS_VERSION.into());
+    if let Some(map) = values.as_mapping_mut() {
+        map.insert("sops".into(), Value::Mapping(metadata));
+    }
+    Ok(serde_yaml::to_string(&values)?)
+}
@@ -0,0 +1,403 @@
+use serde_yaml::Value;
+use sqlparser::ast::{Privileges, Statement};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::keywords::ALL_KEYWORDS;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, Tokenizer, Word};
+use std::fmt;
+
+use crate::script_lint::{looks_like_sql, parse_shell_script, shell_body};
+use crate::{pod_containers, pod_spec, resource_kind, resource_name, Severity};
+
+/// psql meta-commands that send the statement buffer to the server, like `;` does
+const EXECUTING_META_COMMANDS: &[&str] = &["g", "gexec", "gset", "gx"];
+
+/// A statement or meta-command of a psql script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SqlItem {
+    /// A statement with comments removed and whitespace collapsed, including its terminator,
+    /// e.g. `GRANT CONNECT ON DATABASE life TO lifeapp;` or `SELECT 'CREATE DATABASE life' \gexec`;
+    /// copies are compared by [`SqlItem::normalized`], not this text
+    Statement(String),
+    /// A meta-command on its own, e.g. `\c life`
+    MetaCommand(String),
+}
+
+impl SqlItem {
+    /// The form copies are compared in: the syntax tree when `sqlparser` parses the statement with
+    /// the Postgres dialect, the [`normalize_sql`] tokens when it does not
+    pub fn normalized(&self) -> NormalizedSql {
+        let sql = match self {
+            SqlItem::Statement(sql) => sql,
+            SqlItem::MetaCommand(meta) => return NormalizedSql::MetaCommand(meta.clone()),
+        };
+        let (body, terminator) = split_terminator(sql);
+        let parsed = folded_tokens(body).and_then(|tokens| {
+            Parser::new(&PostgreSqlDialect {})
+                .with_tokens(tokens)
+                .parse_statements()
+                .ok()
+        });
+        match parsed {
+            Some(statements) => NormalizedSql::Parsed(
+                statements.into_iter().map(normalize_statement).collect(),
+                terminator.to_string(),
+            ),
+            None => NormalizedSql::Tokens(normalize_sql(sql)),
+        }
+    }
+}
+
+/// A statement or meta-command in the form copies are compared in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NormalizedSql {
+    /// Parsed statements and how they are sent, `;` or an executing meta-command such as `\gexec`
+    Parsed(Vec<Statement>, String),
+    /// A statement `sqlparser` cannot parse, e.g. `ALTER DEFAULT PRIVILEGES`
+    Tokens(String),
+    MetaCommand(String),
+}
+
+impl fmt::Display for SqlItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SqlItem::Statement(sql) | SqlItem::MetaCommand(sql) => f.write_str(sql),
+        }
+    }
+}
+
+/// One copy of SQL found in the rendered output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlSource {
+    /// Where the SQL lives, e.g. `ConfigMap/x data init.sql` or `Job/x container 'db-init' heredoc at line 5`
+    pub location: String,
+    /// ConfigMap data is sent as is; script heredocs go through the shell first
+    pub from_script: bool,
+    pub items: Vec<SqlItem>,
+}
+
+/// A problem found by comparing the copies of SQL in a render
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlIssue {
+    pub severity: Severity,
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for SqlIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.severity, self.location, self.message)
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The dollar-quote tag (`$$` or `$name$`) starting at `chars[start]`, if any
+fn dollar_quote_tag(chars: &[char], start: usize) -> Option<String> {
+    let mut i = start + 1;
+    while chars.get(i).is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_') {
+        i += 1;
+    }
+    let tag: String = chars[start..=i.min(chars.len() - 1)].iter().collect();
+    let valid = chars.get(i) == Some(&'$') && !chars.get(start + 1).is_some_and(|c| c.is_ascii_digit());
+    valid.then_some(tag)
+}
+
+/// Whether a quoted identifier means the same as its unquoted spelling, which Postgres folds to lower case
+fn needs_no_quotes(identifier: &str) -> bool {
+    identifier.chars().next().is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && identifier.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '$')
+        && ALL_KEYWORDS.binary_search(&identifier.to_uppercase().as_str()).is_err()
+}
+
+/// Split the `;` or executing meta-command [`parse_psql_script`] ended a statement with from its SQL
+fn split_terminator(sql: &str) -> (&str, &str) {
+    if let Some(body) = sql.strip_suffix(';') {
+        return (body, ";");
+    }
+    match sql.rfind(" \\") {
+        Some(p) if EXECUTING_META_COMMANDS.contains(&&sql[p + 2..]) => (&sql[..p], &sql[p + 1..]),
+        _ => (sql, ""),
+    }
+}
+
+/// Tokenize SQL with the Postgres dialect, folding unquoted words to lower case and unquoting
+/// identifiers that mean the same unquoted; whitespace and comments are dropped
+fn folded_tokens(sql: &str) -> Option<Vec<Token>> {
+    let tokens = Tokenizer::new(&PostgreSqlDialect {}, sql).tokenize().ok()?;
+    let folded = tokens
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Whitespace(_) => None,
+            Token::Word(word) if word.quote_style.is_none() => Some(Token::Word(Word {
+                value: word.value.to_lowercase(),
+                ..word
+            })),
+            Token::Word(word) if word.quote_style == Some('"') && needs_no_quotes(&word.value) => {
+                Some(Token::make_word(&word.value, None))
+            }
+            token => Some(token),
+        })
+        .collect();
+    Some(folded)
+}
+
+/// Clear the parts of a syntax tree that are optional spellings of the same statement
+fn normalize_statement(mut statement: Statement) -> Statement {
+    // `GRANT ALL` and `GRANT ALL PRIVILEGES` grant the same
+    if let Statement::Grant { privileges, .. } | Statement::Revoke { privileges, .. } = &mut statement {
+        if let Privileges::All { with_privileges_keyword } = privileges {
+            *with_privileges_keyword = false;
+        }
+    }
+    statement
+}
+
+/// The tokens of a statement as `sqlparser` reads them, separated by single spaces, with unquoted
+/// words folded to lower case and needlessly quoted identifiers unquoted as Postgres does
+///
+/// Statements `sqlparser` cannot parse are compared in this form.
+pub fn normalize_sql(sql: &str) -> String {
+    match folded_tokens(sql) {
+        Some(tokens) => tokens.iter().map(|token| token.to_string()).collect::<Vec<_>>().join(" "),
+        None => collapse_whitespace(sql),
+    }
+}
+
+/// Split a psql script into statements and meta-commands
+///
+/// Statements end at `;` or at an executing meta-command such as `\gexec`. Quoted strings,
+/// quoted identifiers and dollar-quoted bodies are kept intact; `--` and `/* */` comments are dropped.
+pub fn parse_psql_script(script: &str) -> Vec<SqlItem> {
+    let chars: Vec<char> = script.chars().collect();
+    let mut items = Vec::new();
+    let mut buffer = String::new();
+    let mut i = 0;
+
+    let flush = |buffer: &mut String, items: &mut Vec<SqlItem>, terminator: &str| {
+        let statement = collapse_whitespace(buffer);
+        if !statement.is_empty() {
+            items.push(SqlItem::Statement(format!("{}{}", statement, terminator)));
+        }
+        buffer.clear();
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\'' | '"' => {
+                let close = chars[i + 1..]
+                    .iter()
+                    .position(|&q| q == c)
+                    .map_or(chars.len(), |p| p + i + 1);
+                buffer.extend(&chars[i..(close + 1).min(chars.len())]);
+                i = close + 1;
+                continue;
+            }
+            '$' => {
+                if let Some(tag) = dollar_quote_tag(&chars, i) {
+                    let rest: String = chars[i + tag.len()..].iter().collect();
+                    let body_length = rest.find(&tag).map_or(rest.len(), |p| p + tag.len());
+                    buffer.push_str(&tag);
+                    buffer.push_str(&rest[..body_length]);
+                    i += tag.len() + rest[..body_length].chars().count();
+                    continue;
+                }
+            }
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                while chars.get(i).is_some_and(|c| *c != '\n') {
+                    i += 1;
+                }
+                buffer.push(' ');
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let rest: String = chars[i + 2..].iter().collect();
+                i += 2 + rest.find("*/").map_or(rest.chars().count(), |p| rest[..p].chars().count() + 2);
+                buffer.push(' ');
+                continue;
+            }
+            ';' => flush(&mut buffer, &mut items, ";"),
+            '\\' => {
+                let line_end = chars[i..].iter().position(|&c| c == '\n').map_or(chars.len(), |p| p + i);
+                let meta: String = chars[i..line_end].iter().collect();
+                let command = meta[1..].split_whitespace().next().unwrap_or_default();
+                if EXECUTING_META_COMMANDS.contains(&command) {
+                    flush(&mut buffer, &mut items, &format!(" \\{}", command));
+                    i += 1 + command.len();
+                } else {
+                    flush(&mut buffer, &mut items, "");
+                    items.push(SqlItem::MetaCommand(collapse_whitespace(&meta)));
+                    i = line_end;
+                }
+                continue;
+            }
+            _ => buffer.push(c),
+        }
+        i += 1;
+    }
+    flush(&mut buffer, &mut items, "");
+    items
+}
+
+/// Collect the SQL of the render: `.sql` or SQL-looking ConfigMap data, and SQL heredocs of
+/// container shell scripts
+pub fn extract_sql_sources(documents: &[Value]) -> Vec<SqlSource> {
+    let mut sources = Vec::new();
+    for doc in documents {
+        let (Some(kind), Some(name)) = (resource_kind(doc), resource_name(doc)) else {
+            continue;
+        };
+        if kind == "ConfigMap" {
+            let data = doc.get("data").and_then(|d| d.as_mapping());
+            for (key, value) in data.into_iter().flatten() {
+                let (Some(key), Some(text)) = (key.as_str(), value.as_str()) else {
+                    continue;
+                };
+                if key.ends_with(".sql") || (text.contains(';') && looks_like_sql(text)) {
+                    sources.push(SqlSource {
+                        location: format!("ConfigMap/{} data {}", name, key),
+                        from_script: false,
+                        items: parse_psql_script(text),
+                    });
+                }
+            }
+        }
+
+        let Some(spec) = pod_spec(doc) else {
+            continue;
+        };
+        for container in pod_containers(spec) {
+            let Some(body) = shell_body(container) else {
+                continue;
+            };
+            let container_name = container.get("name").and_then(|n| n.as_str()).unwrap_or("?");
+            for heredoc in parse_shell_script(body).heredocs {
+                if looks_like_sql(&heredoc.body) {
+                    sources.push(SqlSource {
+                        location: format!(
+                            "{}/{} container '{}' heredoc at line {}",
+                            kind, name, container_name, heredoc.line
+                        ),
+                        from_script: true,
+                        items: parse_psql_script(&heredoc.body),
+                    });
+                }
+            }
+        }
+    }
+    sources
+}
+
+/// A statement-level difference between two copies of SQL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SqlDifference {
+    OnlyInLeft(SqlItem),
+    OnlyInRight(SqlItem),
+    Changed(SqlItem, SqlItem),
+}
+
+/// Diff two statement lists by their longest common subsequence of [`SqlItem::normalized`] forms,
+/// pairing a removal directly followed by an addition into a change
+pub fn diff_sql(left: &[SqlItem], right: &[SqlItem]) -> Vec<SqlDifference> {
+    let left_keys: Vec<NormalizedSql> = left.iter().map(SqlItem::normalized).collect();
+    let right_keys: Vec<NormalizedSql> = right.iter().map(SqlItem::normalized).collect();
+    let mut lcs = vec![vec![0usize; right.len() + 1]; left.len() + 1];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            lcs[i][j] = if left_keys[i] == right_keys[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut differences = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() || j < right.len() {
+        if i < left.len() && j < right.len() && left_keys[i] == right_keys[j] {
+            i += 1;
+            j += 1;
+        } else if j < right.len() && (i == left.len() || lcs[i][j + 1] > lcs[i + 1][j]) {
+            match differences.pop() {
+                Some(SqlDifference::OnlyInLeft(removed)) => {
+                    differences.push(SqlDifference::Changed(removed, right[j].clone()));
+                }
+                previous => {
+                    differences.extend(previous);
+                    differences.push(SqlDifference::OnlyInRight(right[j].clone()));
+                }
+            }
+            j += 1;
+        } else {
+            differences.push(SqlDifference::OnlyInLeft(left[i].clone()));
+            i += 1;
+        }
+    }
+    differences
+}
+
+/// Shell variable syntax in SQL that no shell expands, such as `${APP_PASSWORD}` in a ConfigMap
+fn literal_shell_variables(item: &SqlItem) -> Vec<String> {
+    let text = item.to_string();
+    let mut variables = Vec::new();
+    for (start, _) in text.match_indices('$') {
+        let rest = &text[start + 1..];
+        let name: String = rest
+            .trim_start_matches('{')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect();
+        let braced = rest.starts_with('{');
+        if name.chars().next().is_some_and(|c| c.is_ascii_uppercase() || c == '_') && (braced || name.len() > 1) {
+            variables.push(name);
+        }
+    }
+    variables
+}
+
+/// Report statement-level drift between copies of the same SQL and shell variables that ConfigMap
+/// SQL sends to the server literally; mounts no script reads are left to `check_unused_objects`
+pub fn check_sql_drift(documents: &[Value]) -> Vec<SqlIssue> {
+    let sources = extract_sql_sources(documents);
+    let mut issues = Vec::new();
+
+    for source in sources.iter().filter(|s| !s.from_script) {
+        for item in &source.items {
+            for variable in literal_shell_variables(item) {
+                issues.push(SqlIssue {
+                    severity: Severity::Error,
+                    location: source.location.clone(),
+                    message: format!("`{}` sends ${} to the server literally; nothing expands it", item, variable),
+                });
+            }
+        }
+    }
+
+    for (index, left) in sources.iter().enumerate() {
+        for right in &sources[index + 1..] {
+            let right_keys: Vec<NormalizedSql> = right.items.iter().map(SqlItem::normalized).collect();
+            if !left.items.iter().any(|item| right_keys.contains(&item.normalized())) {
+                continue;
+            }
+            let location = format!("{} vs {}", left.location, right.location);
+            for difference in diff_sql(&left.items, &right.items) {
+                let message = match difference {
+                    SqlDifference::OnlyInLeft(item) => format!("`{}` only in the first copy", item),
+                    SqlDifference::OnlyInRight(item) => format!("`{}` only in the second copy", item),
+                    SqlDifference::Changed(a, b) => format!("`{}` differs from `{}`", a, b),
+                };
+                issues.push(SqlIssue {
+                    severity: Severity::Warning,
+                    location: location.clone(),
+                    message,
+                });
+            }
+        }
+    }
+
+    issues
+}
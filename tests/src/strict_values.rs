@@ -0,0 +1,184 @@
+use anyhow::Result;
+use serde_yaml::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::path::Path;
+
+use crate::{run_helm_template_with_values_files, set_key_path};
+use crate::values_coverage::{
+    analyze_values_coverage, display_path, is_prefix, DeclaredValue, ValueReference, LIST_ELEMENT,
+};
+
+/// A key supplied to a render that neither values.yaml declares nor any template reads
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownValue {
+    pub path: String,
+    /// Where the key came from: `--set` or the path of a values file
+    pub source: String,
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for UnknownValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (from {}) is not declared in values.yaml or read by any template",
+            self.path, self.source
+        )?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "; did you mean '{}'?", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Levenshtein distance between two strings, counted in characters
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Flatten a values overlay into the paths of its leaves, with list elements under `[]`
+pub fn overlay_key_paths(overlay: &Value) -> Vec<Vec<String>> {
+    fn walk(value: &Value, path: &mut Vec<String>, out: &mut BTreeSet<Vec<String>>) {
+        match value {
+            Value::Mapping(map) if !map.is_empty() => {
+                for (key, child) in map {
+                    let key = match key {
+                        Value::String(s) => s.clone(),
+                        other => serde_yaml::to_string(other).unwrap_or_default().trim().to_string(),
+                    };
+                    path.push(key);
+                    walk(child, path, out);
+                    path.pop();
+                }
+            }
+            Value::Sequence(items) if !items.is_empty() => {
+                for item in items {
+                    path.push(LIST_ELEMENT.to_string());
+                    walk(item, path, out);
+                    path.pop();
+                }
+            }
+            _ => {
+                if !path.is_empty() {
+                    out.insert(path.clone());
+                }
+            }
+        }
+    }
+
+    let mut out = BTreeSet::new();
+    walk(overlay, &mut Vec::new(), &mut out);
+    out.into_iter().collect()
+}
+
+/// The keys a chart accepts: everything values.yaml declares plus everything its templates read
+#[derive(Debug, Clone)]
+pub struct StrictValues {
+    declared: Vec<DeclaredValue>,
+    references: Vec<ValueReference>,
+}
+
+impl StrictValues {
+    pub fn for_chart(chart_path: &str) -> Result<Self> {
+        let coverage = analyze_values_coverage(chart_path)?;
+        Ok(StrictValues {
+            declared: coverage.declared,
+            references: coverage.references,
+        })
+    }
+
+    /// Whether a supplied key is declared or read
+    pub fn accepts(&self, path: &[String]) -> bool {
+        let declared = self.declared.iter().any(|d| {
+            d.path.as_slice() == path
+                || (d.open && path.starts_with(&d.path))
+                || d.path.starts_with(path)
+        });
+        declared
+            || self.references.iter().any(|r| {
+                (r.path.len() == path.len() && is_prefix(&r.path, path))
+                    || (r.consumed && is_prefix(&r.path, path))
+                    || (path.len() < r.path.len() && is_prefix(&r.path[..path.len()], path))
+            })
+    }
+
+    /// The known key closest to `path`, if it is close enough to be a plausible typo
+    pub fn suggest(&self, path: &[String]) -> Option<String> {
+        let wanted = display_path(path);
+        let candidates: BTreeSet<String> = self
+            .declared
+            .iter()
+            .map(|d| display_path(&d.path))
+            .chain(self.references.iter().map(|r| r.display_path()))
+            .collect();
+        candidates
+            .into_iter()
+            .map(|candidate| (edit_distance(&wanted, &candidate), candidate))
+            .filter(|(distance, _)| *distance > 0 && *distance <= (wanted.chars().count() / 4).max(2))
+            .min()
+            .map(|(_, candidate)| candidate)
+    }
+
+    fn check_paths(&self, paths: Vec<Vec<String>>, source: &str) -> Vec<UnknownValue> {
+        paths
+            .into_iter()
+            .filter(|path| !self.accepts(path))
+            .map(|path| UnknownValue {
+                path: display_path(&path),
+                source: source.to_string(),
+                suggestion: self.suggest(&path),
+            })
+            .collect()
+    }
+
+    /// Check every key of a values overlay
+    pub fn check_overlay(&self, overlay: &Value, source: &str) -> Vec<UnknownValue> {
+        self.check_paths(overlay_key_paths(overlay), source)
+    }
+
+    /// Check every key of a `--set` map
+    pub fn check_set_values(&self, values: &HashMap<String, String>) -> Vec<UnknownValue> {
+        let mut paths: Vec<Vec<String>> = values.keys().map(|key| set_key_path(key)).collect();
+        paths.sort();
+        self.check_paths(paths, "--set")
+    }
+
+    /// Check values files and `--set` values together
+    pub fn check(&self, values_files: &[&Path], values: Option<&HashMap<String, String>>) -> Result<Vec<UnknownValue>> {
+        let mut unknown = Vec::new();
+        for file in values_files {
+            let overlay: Value = serde_yaml::from_str(&std::fs::read_to_string(file)?)?;
+            unknown.extend(self.check_overlay(&overlay, &file.display().to_string()));
+        }
+        if let Some(values) = values {
+            unknown.extend(self.check_set_values(values));
+        }
+        Ok(unknown)
+    }
+}
+
+/// Render a chart like `run_helm_template_with_values_files`, but fail before rendering if any
+/// supplied key is neither declared in values.yaml nor read by a template
+pub fn run_helm_template_strict(
+    chart_path: &str,
+    values_files: &[&Path],
+    values: Option<&HashMap<String, String>>,
+) -> Result<String> {
+    let unknown = StrictValues::for_chart(chart_path)?.check(values_files, values)?;
+    if !unknown.is_empty() {
+        let lines: Vec<String> = unknown.iter().map(|u| format!("  {}", u)).collect();
+        anyhow::bail!("Strict values check failed:\n{}", lines.join("\n"));
+    }
+    run_helm_template_with_values_files(chart_path, values_files, values)
+}
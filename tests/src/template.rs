@@ -0,0 +1,716 @@
+use anyhow::Result;
+
+/// A piece of a Go template source: literal text, an action, or a comment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Text { text: String, line: usize },
+    Action(RawAction),
+    Comment { line: usize },
+}
+
+/// The inside of a `{{ ... }}` action, with its trim markers removed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawAction {
+    pub text: String,
+    pub line: usize,
+    pub trim_left: bool,
+    pub trim_right: bool,
+    /// Byte offset of the opening `{{` in the source
+    pub start: usize,
+    /// Byte offset just past the closing `}}` in the source
+    pub end: usize,
+}
+
+/// A lexical token inside an action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    /// A field chain such as `.Values.api.image`, `$.Values.x`, `$host.host`, `$` or `.`
+    Field(String),
+    /// A bare identifier: keyword, function name, `true`, `nil`, ...
+    Ident(String),
+    /// A string literal with its quotes removed
+    Str(String),
+    Number(String),
+    Char(String),
+    Pipe,
+    LParen,
+    RParen,
+    Declare,
+    Assign,
+    Comma,
+}
+
+fn line_at(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count() + 1
+}
+
+/// Split a template into text, action and comment segments, tracking the line each one starts on
+pub fn scan_template(source: &str) -> Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut rest = 0;
+
+    while let Some(found) = source[rest..].find("{{") {
+        let start = rest + found;
+        if start > rest {
+            segments.push(Segment::Text {
+                text: source[rest..start].to_string(),
+                line: line_at(source, rest),
+            });
+        }
+
+        let line = line_at(source, start);
+        let mut inner_start = start + 2;
+        let trim_left = source[inner_start..].starts_with("- ")
+            || source[inner_start..].starts_with("-\n")
+            || source[inner_start..].starts_with("-\t");
+        if trim_left {
+            inner_start += 1;
+        }
+
+        let after_space = inner_start + (source[inner_start..].len() - source[inner_start..].trim_start().len());
+        if source[after_space..].starts_with("/*") {
+            let close = source[after_space..]
+                .find("*/")
+                .ok_or_else(|| anyhow::anyhow!("line {}: unclosed comment", line))?;
+            let mut end = after_space + close + 2;
+            end += source[end..].len() - source[end..].trim_start().len();
+            if source[end..].starts_with('-') {
+                end += 1;
+            }
+            if !source[end..].starts_with("}}") {
+                anyhow::bail!("line {}: comment must be followed by }}}}", line);
+            }
+            segments.push(Segment::Comment { line });
+            rest = end + 2;
+            continue;
+        }
+
+        let close = find_action_end(&source[inner_start..])
+            .ok_or_else(|| anyhow::anyhow!("line {}: unclosed action", line))?;
+        let mut inner_end = inner_start + close;
+        let trim_right = inner_end > inner_start
+            && source[..inner_end].ends_with('-')
+            && source[..inner_end - 1].ends_with(char::is_whitespace);
+        if trim_right {
+            inner_end -= 1;
+        }
+
+        segments.push(Segment::Action(RawAction {
+            text: source[inner_start..inner_end].trim().to_string(),
+            line,
+            trim_left,
+            trim_right,
+            start,
+            end: inner_start + close + 2,
+        }));
+        rest = inner_start + close + 2;
+    }
+
+    if rest < source.len() {
+        segments.push(Segment::Text {
+            text: source[rest..].to_string(),
+            line: line_at(source, rest),
+        });
+    }
+
+    Ok(segments)
+}
+
+/// Find the offset of the `}}` closing an action, skipping over string and char literals
+fn find_action_end(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' | b'\'' => {
+                let quote = bytes[i];
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'`' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'`' {
+                    i += 1;
+                }
+            }
+            b'}' if bytes.get(i + 1) == Some(&b'}') => return Some(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Split the text of an action into tokens
+pub fn tokenize_action(text: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '|' => {
+                tokens.push(Token::Pipe);
+                i += 1;
+            }
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            ':' if chars.get(i + 1) == Some(&'=') => {
+                tokens.push(Token::Declare);
+                i += 2;
+            }
+            '=' => {
+                tokens.push(Token::Assign);
+                i += 1;
+            }
+            '"' => {
+                let mut value = String::new();
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    if chars[i] == '\\' && i + 1 < chars.len() {
+                        i += 1;
+                        value.push(match chars[i] {
+                            'n' => '\n',
+                            't' => '\t',
+                            other => other,
+                        });
+                    } else {
+                        value.push(chars[i]);
+                    }
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    anyhow::bail!("unterminated string in action: {}", text);
+                }
+                tokens.push(Token::Str(value));
+                i += 1;
+            }
+            '`' => {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && chars[i] != '`' {
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    anyhow::bail!("unterminated raw string in action: {}", text);
+                }
+                tokens.push(Token::Str(chars[start..i].iter().collect()));
+                i += 1;
+            }
+            '\'' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && chars[i] != '\'' {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                i += 1;
+                tokens.push(Token::Char(chars[start..i.min(chars.len())].iter().collect()));
+            }
+            '$' | '.' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && is_ident(chars[i]) && !(c == '.' && i == start + 1 && chars[i].is_ascii_digit()) {
+                    i += 1;
+                }
+                while i < chars.len() && chars[i] == '.' && chars.get(i + 1).is_some_and(|n| is_ident(*n)) {
+                    i += 1;
+                    while i < chars.len() && is_ident(chars[i]) {
+                        i += 1;
+                    }
+                }
+                if c == '.' && i == start + 1 && chars.get(i).is_some_and(|n| n.is_ascii_digit()) {
+                    while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                        i += 1;
+                    }
+                    tokens.push(Token::Number(chars[start..i].iter().collect()));
+                } else {
+                    tokens.push(Token::Field(chars[start..i].iter().collect()));
+                }
+            }
+            c if c.is_ascii_digit() || ((c == '-' || c == '+') && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit())) => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.' || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Number(chars[start..i].iter().collect()));
+            }
+            c if is_ident(c) => {
+                let start = i;
+                while i < chars.len() && is_ident(chars[i]) {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            other => anyhow::bail!("unexpected character '{}' in action: {}", other, text),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Files under a chart's templates/ directory, relative to the chart root and sorted
+pub fn chart_template_files(chart_dir: &std::path::Path) -> Result<Vec<String>> {
+    fn walk(dir: &std::path::Path, root: &std::path::Path, files: &mut Vec<String>) -> Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                walk(&path, root, files)?;
+            } else if let Ok(rel) = path.strip_prefix(root) {
+                files.push(rel.to_string_lossy().replace('\\', "/"));
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    let templates = chart_dir.join("templates");
+    if templates.is_dir() {
+        walk(&templates, chart_dir, &mut files)?;
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// A parsed Go template node
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Text {
+        text: String,
+        line: usize,
+    },
+    Comment {
+        line: usize,
+    },
+    /// An action that outputs or declares: `{{ .Values.x | quote }}`, `{{ $name := ... }}`
+    Action {
+        pipeline: Pipeline,
+        line: usize,
+    },
+    /// `if`, `with` and `range`; `else if`/`else with` chains nest in `else_body` like Go's parser does
+    Control {
+        kind: ControlKind,
+        pipeline: Pipeline,
+        body: Vec<Node>,
+        else_body: Option<Vec<Node>>,
+        line: usize,
+    },
+    Define {
+        name: String,
+        body: Vec<Node>,
+        line: usize,
+    },
+    /// `{{ block "name" pipeline }}...{{ end }}`: defines a template and executes it in place
+    Block {
+        name: String,
+        pipeline: Pipeline,
+        body: Vec<Node>,
+        line: usize,
+    },
+    Template {
+        name: String,
+        pipeline: Option<Pipeline>,
+        line: usize,
+    },
+    Break {
+        line: usize,
+    },
+    Continue {
+        line: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlKind {
+    If,
+    With,
+    Range,
+}
+
+impl ControlKind {
+    pub(crate) fn keyword(self) -> &'static str {
+        match self {
+            ControlKind::If => "if",
+            ControlKind::With => "with",
+            ControlKind::Range => "range",
+        }
+    }
+}
+
+/// A pipeline: optional variable declaration followed by `|`-separated commands
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Pipeline {
+    pub variables: Vec<String>,
+    /// `=` assignment to existing variables rather than `:=` declaration
+    pub is_assign: bool,
+    pub commands: Vec<Command>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    pub args: Vec<Arg>,
+}
+
+/// An operand of a command
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    Field(String),
+    Ident(String),
+    Str(String),
+    Number(String),
+    Char(String),
+    Bool(bool),
+    Nil,
+    Pipeline(Pipeline),
+}
+
+impl Pipeline {
+    /// Every argument of the pipeline, descending into parenthesized sub-pipelines
+    pub fn walk_args(&self) -> Vec<&Arg> {
+        let mut args = Vec::new();
+        for command in &self.commands {
+            for arg in &command.args {
+                args.push(arg);
+                if let Arg::Pipeline(inner) = arg {
+                    args.extend(inner.walk_args());
+                }
+            }
+        }
+        args
+    }
+
+    /// Names of templates invoked through `include "name"` anywhere in the pipeline
+    pub fn included_templates(&self) -> Vec<&str> {
+        let nested = self.walk_args().into_iter().filter_map(|arg| match arg {
+            Arg::Pipeline(inner) => Some(inner),
+            _ => None,
+        });
+        std::iter::once(self)
+            .chain(nested)
+            .flat_map(|pipeline| &pipeline.commands)
+            .filter_map(|command| match command.args.as_slice() {
+                [Arg::Ident(func), Arg::Str(name), ..] if func == "include" => Some(name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn parse_pipeline(tokens: &[Token]) -> Result<Pipeline> {
+    let mut pipeline = Pipeline::default();
+    let mut rest = tokens;
+
+    if let Some(pos) = tokens.iter().position(|t| matches!(t, Token::Declare | Token::Assign)) {
+        let declared = &tokens[..pos];
+        let names: Vec<String> = declared
+            .iter()
+            .filter(|t| **t != Token::Comma)
+            .map(|t| match t {
+                Token::Field(name) if name.starts_with('$') && !name[1..].contains('.') => Ok(name.clone()),
+                other => Err(anyhow::anyhow!("cannot declare {:?}", other)),
+            })
+            .collect::<Result<_>>()?;
+        if !names.is_empty() && names.len() == declared.iter().filter(|t| **t != Token::Comma).count() {
+            pipeline.variables = names;
+            pipeline.is_assign = tokens[pos] == Token::Assign;
+            rest = &tokens[pos + 1..];
+        }
+    }
+
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, token) in rest.iter().enumerate() {
+        match token {
+            Token::LParen => depth += 1,
+            Token::RParen => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| anyhow::anyhow!("unexpected ')'"))?
+            }
+            Token::Pipe if depth == 0 => {
+                pipeline.commands.push(parse_command(&rest[start..i])?);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        anyhow::bail!("unclosed '('");
+    }
+    if start < rest.len() || !pipeline.commands.is_empty() {
+        pipeline.commands.push(parse_command(&rest[start..])?);
+    }
+
+    Ok(pipeline)
+}
+
+fn parse_command(tokens: &[Token]) -> Result<Command> {
+    if tokens.is_empty() {
+        anyhow::bail!("missing command in pipeline");
+    }
+
+    let mut args = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let arg = match &tokens[i] {
+            Token::LParen => {
+                let mut depth = 0usize;
+                let close = tokens[i..]
+                    .iter()
+                    .position(|t| {
+                        match t {
+                            Token::LParen => depth += 1,
+                            Token::RParen => depth -= 1,
+                            _ => {}
+                        }
+                        depth == 0
+                    })
+                    .ok_or_else(|| anyhow::anyhow!("unclosed '('"))?;
+                let inner = parse_pipeline(&tokens[i + 1..i + close])?;
+                i += close;
+                Arg::Pipeline(inner)
+            }
+            Token::Field(field) => Arg::Field(field.clone()),
+            Token::Ident(ident) => match ident.as_str() {
+                "true" => Arg::Bool(true),
+                "false" => Arg::Bool(false),
+                "nil" => Arg::Nil,
+                _ => Arg::Ident(ident.clone()),
+            },
+            Token::Str(s) => Arg::Str(s.clone()),
+            Token::Number(n) => Arg::Number(n.clone()),
+            Token::Char(c) => Arg::Char(c.clone()),
+            other => anyhow::bail!("unexpected {:?} in command", other),
+        };
+        args.push(arg);
+        i += 1;
+    }
+
+    Ok(Command { args })
+}
+
+/// Apply `{{-` and `-}}` whitespace trimming to the text around actions
+fn apply_trim_markers(segments: &mut [Segment]) {
+    for i in 0..segments.len() {
+        let (trim_left, trim_right) = match &segments[i] {
+            Segment::Action(action) => (action.trim_left, action.trim_right),
+            _ => continue,
+        };
+        if trim_left && i > 0 {
+            if let Segment::Text { text, .. } = &mut segments[i - 1] {
+                *text = text.trim_end().to_string();
+            }
+        }
+        if trim_right {
+            if let Some(Segment::Text { text, line }) = segments.get_mut(i + 1) {
+                let trimmed = text.trim_start();
+                *line += text[..text.len() - trimmed.len()].matches('\n').count();
+                *text = trimmed.to_string();
+            }
+        }
+    }
+}
+
+/// How a list of nodes ended
+enum Stop {
+    Eof,
+    End,
+    Else { tokens: Vec<Token>, line: usize },
+}
+
+struct Parser {
+    segments: Vec<Segment>,
+    pos: usize,
+}
+
+impl Parser {
+    fn parse_nodes(&mut self) -> Result<(Vec<Node>, Stop)> {
+        let mut nodes = Vec::new();
+
+        while self.pos < self.segments.len() {
+            let segment = self.segments[self.pos].clone();
+            self.pos += 1;
+
+            let action = match segment {
+                Segment::Text { text, line } => {
+                    if !text.is_empty() {
+                        nodes.push(Node::Text { text, line });
+                    }
+                    continue;
+                }
+                Segment::Comment { line } => {
+                    nodes.push(Node::Comment { line });
+                    continue;
+                }
+                Segment::Action(action) => action,
+            };
+
+            let line = action.line;
+            let tokens = tokenize_action(&action.text).map_err(|e| anyhow::anyhow!("line {}: {}", line, e))?;
+            let keyword = match tokens.first() {
+                Some(Token::Ident(word)) => word.as_str(),
+                _ => "",
+            };
+            let rest = tokens.get(1..).unwrap_or_default();
+            let in_line = |e: anyhow::Error| anyhow::anyhow!("line {}: {}", line, e);
+
+            let node = match keyword {
+                "end" => return Ok((nodes, Stop::End)),
+                "else" => {
+                    return Ok((
+                        nodes,
+                        Stop::Else {
+                            tokens: rest.to_vec(),
+                            line,
+                        },
+                    ))
+                }
+                "if" => self.parse_control(ControlKind::If, rest, line)?,
+                "with" => self.parse_control(ControlKind::With, rest, line)?,
+                "range" => self.parse_control(ControlKind::Range, rest, line)?,
+                "define" => {
+                    let name = template_name(rest).map_err(in_line)?;
+                    let body = self.parse_until_end("define", line)?;
+                    Node::Define { name, body, line }
+                }
+                "block" => {
+                    let name = template_name(rest).map_err(in_line)?;
+                    let pipeline = parse_pipeline(&rest[1..]).map_err(in_line)?;
+                    let body = self.parse_until_end("block", line)?;
+                    Node::Block {
+                        name,
+                        pipeline,
+                        body,
+                        line,
+                    }
+                }
+                "template" => {
+                    let name = template_name(rest).map_err(in_line)?;
+                    let pipeline = if rest.len() > 1 {
+                        Some(parse_pipeline(&rest[1..]).map_err(in_line)?)
+                    } else {
+                        None
+                    };
+                    Node::Template { name, pipeline, line }
+                }
+                "break" => Node::Break { line },
+                "continue" => Node::Continue { line },
+                _ => Node::Action {
+                    pipeline: parse_pipeline(&tokens).map_err(in_line)?,
+                    line,
+                },
+            };
+            nodes.push(node);
+        }
+
+        Ok((nodes, Stop::Eof))
+    }
+
+    fn parse_until_end(&mut self, keyword: &str, line: usize) -> Result<Vec<Node>> {
+        match self.parse_nodes()? {
+            (body, Stop::End) => Ok(body),
+            (_, Stop::Eof) => anyhow::bail!("line {}: {{{{{}}}}} is never closed with {{{{end}}}}", line, keyword),
+            (_, Stop::Else { line: else_line, .. }) => {
+                anyhow::bail!("line {}: unexpected {{{{else}}}} inside {{{{{}}}}}", else_line, keyword)
+            }
+        }
+    }
+
+    fn parse_control(&mut self, kind: ControlKind, tokens: &[Token], line: usize) -> Result<Node> {
+        if tokens.is_empty() {
+            anyhow::bail!("line {}: missing value for {{{{{}}}}}", line, kind.keyword());
+        }
+        let pipeline = parse_pipeline(tokens).map_err(|e| anyhow::anyhow!("line {}: {}", line, e))?;
+        let (body, stop) = self.parse_nodes()?;
+
+        let else_body = match stop {
+            Stop::End => None,
+            Stop::Eof => anyhow::bail!("line {}: {{{{{}}}}} is never closed with {{{{end}}}}", line, kind.keyword()),
+            Stop::Else {
+                tokens: else_tokens,
+                line: else_line,
+            } => match else_tokens.first() {
+                // `else if` and `else with` share the closing {{end}} of the outer block
+                Some(Token::Ident(word)) if word == "if" || word == "with" => {
+                    let nested_kind = if word == "if" { ControlKind::If } else { ControlKind::With };
+                    Some(vec![self.parse_control(nested_kind, &else_tokens[1..], else_line)?])
+                }
+                None => Some(self.parse_until_end("else", else_line)?),
+                Some(_) => anyhow::bail!("line {}: unexpected tokens after {{{{else}}}}", else_line),
+            },
+        };
+
+        Ok(Node::Control {
+            kind,
+            pipeline,
+            body,
+            else_body,
+            line,
+        })
+    }
+}
+
+fn template_name(tokens: &[Token]) -> Result<String> {
+    match tokens.first() {
+        Some(Token::Str(name)) => Ok(name.clone()),
+        _ => anyhow::bail!("expected a quoted template name"),
+    }
+}
+
+/// Parse a Go template into nodes, failing on malformed actions and unbalanced blocks
+pub fn parse_template(source: &str) -> Result<Vec<Node>> {
+    let mut segments = scan_template(source)?;
+    apply_trim_markers(&mut segments);
+
+    let mut parser = Parser { segments, pos: 0 };
+    match parser.parse_nodes()? {
+        (nodes, Stop::Eof) => Ok(nodes),
+        (_, Stop::End) => {
+            let line = match &parser.segments[parser.pos - 1] {
+                Segment::Action(action) => action.line,
+                _ => 0,
+            };
+            anyhow::bail!("line {}: unexpected {{{{end}}}}", line)
+        }
+        (_, Stop::Else { line, .. }) => anyhow::bail!("line {}: unexpected {{{{else}}}}", line),
+    }
+}
+
+/// Visit every node of a parsed template, depth first
+pub fn walk_nodes<'a>(nodes: &'a [Node], visit: &mut dyn FnMut(&'a Node)) {
+    for node in nodes {
+        visit(node);
+        match node {
+            Node::Control { body, else_body, .. } => {
+                walk_nodes(body, visit);
+                if let Some(else_body) = else_body {
+                    walk_nodes(else_body, visit);
+                }
+            }
+            Node::Define { body, .. } | Node::Block { body, .. } => walk_nodes(body, visit),
+            _ => {}
+        }
+    }
+}
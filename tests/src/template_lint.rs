@@ -0,0 +1,181 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::template::{chart_template_files, parse_template, walk_nodes, Node};
+use crate::Severity;
+
+/// A problem found in a chart template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintFinding {
+    pub severity: Severity,
+    pub rule: &'static str,
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: [{}] {}: {}",
+            self.file, self.line, self.severity, self.rule, self.message
+        )
+    }
+}
+
+fn is_blank(nodes: &[Node]) -> bool {
+    nodes.iter().all(|node| match node {
+        Node::Text { text, .. } => text.trim().is_empty(),
+        Node::Comment { .. } => true,
+        _ => false,
+    })
+}
+
+fn is_manifest(file: &str) -> bool {
+    let name = file.rsplit('/').next().unwrap_or(file);
+    !name.starts_with('_') && (name.ends_with(".yaml") || name.ends_with(".yml"))
+}
+
+/// Lint every template of a chart: undefined and unused named templates, empty and unbalanced blocks,
+/// and manifests that skip the chart's common labels helper
+pub fn lint_chart_templates(chart_path: &str) -> Result<Vec<LintFinding>> {
+    let chart_dir = Path::new(chart_path);
+    let mut findings = Vec::new();
+    let mut parsed = Vec::new();
+
+    for file in chart_template_files(chart_dir)? {
+        let source = std::fs::read_to_string(chart_dir.join(&file))?;
+        match parse_template(&source) {
+            Ok(nodes) => parsed.push((file, nodes)),
+            Err(e) => {
+                let message = e.to_string();
+                let line = message
+                    .strip_prefix("line ")
+                    .and_then(|rest| rest.split(':').next())
+                    .and_then(|n| n.parse().ok())
+                    .unwrap_or(0);
+                findings.push(LintFinding {
+                    severity: Severity::Error,
+                    rule: "parse",
+                    file,
+                    line,
+                    message,
+                });
+            }
+        }
+    }
+
+    let mut defines: BTreeMap<&str, (&str, usize)> = BTreeMap::new();
+    let mut calls: Vec<(&str, &str, usize)> = Vec::new();
+
+    for (file, nodes) in &parsed {
+        walk_nodes(nodes, &mut |node| match node {
+            Node::Define { name, line, .. } | Node::Block { name, line, .. } => {
+                if let Some((first_file, first_line)) = defines.get(name.as_str()) {
+                    findings.push(LintFinding {
+                        severity: Severity::Warning,
+                        rule: "duplicate-define",
+                        file: file.clone(),
+                        line: *line,
+                        message: format!("'{}' is already defined at {}:{}", name, first_file, first_line),
+                    });
+                } else {
+                    defines.insert(name, (file, *line));
+                }
+                if let Node::Block { pipeline, .. } = node {
+                    calls.extend(pipeline.included_templates().into_iter().map(|n| (n, file.as_str(), *line)));
+                }
+            }
+            Node::Template { name, line, .. } => calls.push((name, file, *line)),
+            Node::Action { pipeline, line } | Node::Control { pipeline, line, .. } => {
+                calls.extend(pipeline.included_templates().into_iter().map(|n| (n, file.as_str(), *line)));
+            }
+            _ => {}
+        });
+
+        walk_nodes(nodes, &mut |node| {
+            if let Node::Control {
+                kind,
+                body,
+                else_body,
+                line,
+                ..
+            } = node
+            {
+                if is_blank(body) && else_body.as_deref().map(is_blank).unwrap_or(true) {
+                    findings.push(LintFinding {
+                        severity: Severity::Warning,
+                        rule: "empty-block",
+                        file: file.clone(),
+                        line: *line,
+                        message: format!("{{{{{}}}}} block has an empty body", kind.keyword()),
+                    });
+                }
+            }
+        });
+    }
+
+    for (name, file, line) in &calls {
+        if !defines.contains_key(name) {
+            findings.push(LintFinding {
+                severity: Severity::Error,
+                rule: "undefined-template",
+                file: file.to_string(),
+                line: *line,
+                message: format!("template '{}' is not defined", name),
+            });
+        }
+    }
+
+    for (name, (file, line)) in &defines {
+        if !calls.iter().any(|(called, _, _)| called == name) {
+            findings.push(LintFinding {
+                severity: Severity::Warning,
+                rule: "unused-define",
+                file: file.to_string(),
+                line: *line,
+                message: format!("template '{}' is never used", name),
+            });
+        }
+    }
+
+    let labels_helpers: Vec<&str> = defines
+        .keys()
+        .copied()
+        .filter(|name| name.ends_with(".labels"))
+        .collect();
+    if !labels_helpers.is_empty() {
+        for (file, nodes) in &parsed {
+            if !is_manifest(file) {
+                continue;
+            }
+            let includes_labels = calls
+                .iter()
+                .any(|(name, call_file, _)| call_file == file && labels_helpers.contains(name));
+            let has_resource = {
+                let mut found = false;
+                walk_nodes(nodes, &mut |node| {
+                    if let Node::Text { text, .. } = node {
+                        found |= text.lines().any(|l| l.trim_start().starts_with("kind:"));
+                    }
+                });
+                found
+            };
+            if has_resource && !includes_labels {
+                findings.push(LintFinding {
+                    severity: Severity::Warning,
+                    rule: "missing-labels",
+                    file: file.clone(),
+                    line: 1,
+                    message: format!("manifest does not include {}", labels_helpers.join(" or ")),
+                });
+            }
+        }
+    }
+
+    findings.sort_by(|a, b| a.file.cmp(&b.file).then(a.line.cmp(&b.line)));
+    Ok(findings)
+}
@@ -0,0 +1,337 @@
+use anyhow::Result;
+use base64::Engine;
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::{DecodePrivateKey, EncodePublicKey};
+use rsa::RsaPrivateKey;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+use x509_cert::der::oid::AssociatedOid;
+use x509_cert::der::{Decode, Encode};
+use x509_cert::ext::pkix::name::GeneralName;
+use x509_cert::ext::pkix::SubjectAltName;
+use x509_cert::Certificate;
+
+use crate::{
+    find_resources_by_kind, pod_containers, pod_spec, resource_kind, resource_name, secret_data, Severity,
+};
+
+/// Certificates this close to expiry are reported as warnings
+pub const EXPIRY_WARNING_DAYS: u64 = 30;
+const DOCKER_HUB: &str = "docker.io";
+
+/// A problem with a Secret's contents for its declared type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedSecretIssue {
+    pub severity: Severity,
+    /// The Secret's name
+    pub secret: String,
+    pub message: String,
+}
+
+impl fmt::Display for TypedSecretIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] Secret/{}: {}", self.severity, self.secret, self.message)
+    }
+}
+
+/// Whether a certificate name covers a host; a leading `*.` matches exactly one label
+pub fn name_covers_host(name: &str, host: &str) -> bool {
+    let (name, host) = (name.to_lowercase(), host.to_lowercase());
+    match name.strip_prefix("*.") {
+        Some(suffix) => host
+            .split_once('.')
+            .is_some_and(|(label, rest)| !label.is_empty() && rest == suffix),
+        None => name == host,
+    }
+}
+
+/// The registry host an image is pulled from, `docker.io` for unqualified names
+pub fn image_registry(image: &str) -> &str {
+    match image.split_once('/') {
+        Some((first, _)) if first.contains('.') || first.contains(':') || first == "localhost" => first,
+        _ => DOCKER_HUB,
+    }
+}
+
+/// The registry host a `.dockerconfigjson` auths key names, which may be a URL
+fn auth_registry(key: &str) -> String {
+    let host = key.split_once("://").map_or(key, |(_, rest)| rest);
+    let host = host.split('/').next().unwrap_or_default().to_lowercase();
+    match host.as_str() {
+        "index.docker.io" | "registry-1.docker.io" => DOCKER_HUB.to_string(),
+        _ => host,
+    }
+}
+
+fn dns_names(certificate: &Certificate) -> Vec<String> {
+    let extensions = certificate.tbs_certificate.extensions.as_deref().unwrap_or_default();
+    extensions
+        .iter()
+        .filter(|ext| ext.extn_id == SubjectAltName::OID)
+        .filter_map(|ext| SubjectAltName::from_der(ext.extn_value.as_bytes()).ok())
+        .flat_map(|san| san.0)
+        .filter_map(|name| match name {
+            GeneralName::DnsName(dns) => Some(dns.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The DER SubjectPublicKeyInfo of an RSA, P-256 or P-384 private key in PKCS#8, PKCS#1 or SEC1 PEM
+fn private_key_spki(pem: &str) -> Option<Vec<u8>> {
+    let pem = pem.trim();
+    let document = if let Some(key) = RsaPrivateKey::from_pkcs8_pem(pem)
+        .ok()
+        .or_else(|| RsaPrivateKey::from_pkcs1_pem(pem).ok())
+    {
+        key.to_public_key().to_public_key_der()
+    } else if let Some(key) = p256::SecretKey::from_pkcs8_pem(pem)
+        .ok()
+        .or_else(|| p256::SecretKey::from_sec1_pem(pem).ok())
+    {
+        key.public_key().to_public_key_der()
+    } else {
+        let key = p384::SecretKey::from_pkcs8_pem(pem)
+            .ok()
+            .or_else(|| p384::SecretKey::from_sec1_pem(pem).ok())?;
+        key.public_key().to_public_key_der()
+    };
+    Some(document.ok()?.as_bytes().to_vec())
+}
+
+/// Which Ingress hosts use each TLS Secret
+fn ingress_tls_hosts(documents: &[Value]) -> BTreeMap<String, BTreeSet<String>> {
+    let mut hosts: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for ingress in find_resources_by_kind(documents, "Ingress") {
+        let tls = ingress.get("spec").and_then(|s| s.get("tls")).and_then(|t| t.as_sequence());
+        for entry in tls.into_iter().flatten() {
+            let Some(secret) = entry.get("secretName").and_then(|s| s.as_str()) else {
+                continue;
+            };
+            let entry_hosts = entry.get("hosts").and_then(|h| h.as_sequence()).into_iter().flatten();
+            hosts
+                .entry(secret.to_string())
+                .or_default()
+                .extend(entry_hosts.filter_map(|h| h.as_str()).map(str::to_lowercase));
+        }
+    }
+    hosts
+}
+
+/// The images each pull Secret is used for, through pod specs or their ServiceAccount
+fn pull_secret_images(documents: &[Value]) -> BTreeMap<String, BTreeSet<String>> {
+    let names = |list: Option<&Value>| -> Vec<String> {
+        list.and_then(|l| l.as_sequence())
+            .into_iter()
+            .flatten()
+            .filter_map(|item| item.get("name").and_then(|n| n.as_str()).map(String::from))
+            .collect()
+    };
+    let account_secrets: BTreeMap<&str, Vec<String>> = find_resources_by_kind(documents, "ServiceAccount")
+        .into_iter()
+        .filter_map(|account| Some((resource_name(account)?, names(account.get("imagePullSecrets")))))
+        .collect();
+
+    let mut images: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for spec in documents.iter().filter_map(pod_spec) {
+        let mut secrets = names(spec.get("imagePullSecrets"));
+        let account = spec.get("serviceAccountName").and_then(|a| a.as_str()).unwrap_or("default");
+        secrets.extend(account_secrets.get(account).cloned().unwrap_or_default());
+        let pod_images: Vec<String> = pod_containers(spec)
+            .iter()
+            .filter_map(|c| c.get("image").and_then(|i| i.as_str()).map(String::from))
+            .collect();
+        for secret in secrets {
+            images.entry(secret).or_default().extend(pod_images.iter().cloned());
+        }
+    }
+    images
+}
+
+fn check_tls(
+    data: &BTreeMap<String, Vec<u8>>,
+    hosts: Option<&BTreeSet<String>>,
+    now: u64,
+    report: &mut dyn FnMut(Severity, String),
+) {
+    let (Some(cert), Some(key)) = (data.get("tls.crt"), data.get("tls.key")) else {
+        report(Severity::Error, "kubernetes.io/tls Secrets need tls.crt and tls.key".to_string());
+        return;
+    };
+    let chain = match Certificate::load_pem_chain(cert) {
+        Ok(chain) if !chain.is_empty() => chain,
+        _ => {
+            report(Severity::Error, "tls.crt is not a PEM certificate".to_string());
+            return;
+        }
+    };
+    let leaf = &chain[0];
+
+    let key_pem = String::from_utf8_lossy(key);
+    let leaf_spki = leaf.tbs_certificate.subject_public_key_info.to_der().ok();
+    if !key_pem.contains("-----BEGIN") {
+        report(Severity::Error, "tls.key is not a PEM private key".to_string());
+    } else {
+        match private_key_spki(&key_pem) {
+            Some(spki) if Some(&spki) != leaf_spki.as_ref() => {
+                report(Severity::Error, "tls.key does not match the certificate in tls.crt".to_string())
+            }
+            Some(_) => {}
+            None => report(
+                Severity::Warning,
+                "tls.key is not an RSA, P-256 or P-384 key, so its match with tls.crt was not checked".to_string(),
+            ),
+        }
+    }
+
+    let validity = &leaf.tbs_certificate.validity;
+    let not_before = validity.not_before.to_unix_duration().as_secs();
+    let not_after = validity.not_after.to_unix_duration().as_secs();
+    if now < not_before {
+        report(Severity::Error, format!("the certificate is not valid until {}", validity.not_before));
+    } else if now >= not_after {
+        report(Severity::Error, format!("the certificate expired at {}", validity.not_after));
+    } else if not_after - now < EXPIRY_WARNING_DAYS * 86400 {
+        report(
+            Severity::Warning,
+            format!("the certificate expires at {}, in {} days", validity.not_after, (not_after - now) / 86400),
+        );
+    }
+
+    let names = dns_names(leaf);
+    for host in hosts.into_iter().flatten() {
+        if !names.iter().any(|name| name_covers_host(name, host)) {
+            report(
+                Severity::Error,
+                format!(
+                    "the certificate does not cover Ingress host {} (DNS names: {})",
+                    host,
+                    if names.is_empty() { "none".to_string() } else { names.join(", ") }
+                ),
+            );
+        }
+    }
+}
+
+fn check_docker_config(
+    data: &BTreeMap<String, Vec<u8>>,
+    images: Option<&BTreeSet<String>>,
+    report: &mut dyn FnMut(Severity, String),
+) {
+    let Some(config) = data.get(".dockerconfigjson") else {
+        report(Severity::Error, "kubernetes.io/dockerconfigjson Secrets need .dockerconfigjson".to_string());
+        return;
+    };
+    let config: serde_json::Value = match serde_json::from_slice(config) {
+        Ok(config) => config,
+        Err(e) => {
+            report(Severity::Error, format!(".dockerconfigjson is not JSON: {}", e));
+            return;
+        }
+    };
+    let Some(auths) = config.get("auths").and_then(|a| a.as_object()) else {
+        report(Severity::Error, ".dockerconfigjson has no auths object".to_string());
+        return;
+    };
+
+    let mut registries = BTreeSet::new();
+    for (registry, entry) in auths {
+        registries.insert(auth_registry(registry));
+        let has_credentials = match entry.get("auth").and_then(|a| a.as_str()) {
+            Some(auth) => {
+                let decoded = base64::engine::general_purpose::STANDARD.decode(auth).unwrap_or_default();
+                if !String::from_utf8_lossy(&decoded).contains(':') {
+                    report(
+                        Severity::Error,
+                        format!(".dockerconfigjson auth for {} is not base64 of username:password", registry),
+                    );
+                }
+                true
+            }
+            None => {
+                entry.get("username").is_some() && entry.get("password").is_some()
+                    || entry.get("identitytoken").is_some()
+            }
+        };
+        if !has_credentials {
+            report(Severity::Error, format!(".dockerconfigjson has no credentials for {}", registry));
+        }
+    }
+
+    for image in images.into_iter().flatten() {
+        let registry = image_registry(image);
+        if !registries.contains(registry) {
+            report(
+                Severity::Error,
+                format!(
+                    "pulls image {} from {}, but .dockerconfigjson only has credentials for {}",
+                    image,
+                    registry,
+                    registries.iter().cloned().collect::<Vec<_>>().join(", ")
+                ),
+            );
+        }
+    }
+}
+
+/// Validate each typed Secret's contents at `now` (seconds since the epoch): `kubernetes.io/tls`
+/// certificates, keys, expiry and Ingress hosts, `kubernetes.io/dockerconfigjson` credentials for
+/// the images that pull with it, and the keys `basic-auth` and `ssh-auth` need. `data` and
+/// `stringData` are merged as the API server does.
+pub fn check_typed_secrets_at(documents: &[Value], now: u64) -> Vec<TypedSecretIssue> {
+    let tls_hosts = ingress_tls_hosts(documents);
+    let pull_images = pull_secret_images(documents);
+    let mut issues = Vec::new();
+
+    for secret in documents.iter().filter(|doc| resource_kind(doc) == Some("Secret")) {
+        let name = resource_name(secret).unwrap_or_default().to_string();
+        let mut report = |severity: Severity, message: String| {
+            issues.push(TypedSecretIssue {
+                severity,
+                secret: name.clone(),
+                message,
+            })
+        };
+        let data = match secret_data(secret) {
+            Ok(data) => data,
+            Err(e) => {
+                report(Severity::Error, e.to_string());
+                continue;
+            }
+        };
+
+        match secret.get("type").and_then(|t| t.as_str()).unwrap_or("Opaque") {
+            "kubernetes.io/tls" => check_tls(&data, tls_hosts.get(&name), now, &mut report),
+            "kubernetes.io/dockerconfigjson" => check_docker_config(&data, pull_images.get(&name), &mut report),
+            "kubernetes.io/basic-auth" if !data.contains_key("username") && !data.contains_key("password") => {
+                report(Severity::Error, "kubernetes.io/basic-auth Secrets need username or password".to_string())
+            }
+            "kubernetes.io/ssh-auth" => match data.get("ssh-privatekey") {
+                None => report(Severity::Error, "kubernetes.io/ssh-auth Secrets need ssh-privatekey".to_string()),
+                Some(key) if !String::from_utf8_lossy(key).contains("PRIVATE KEY-----") => {
+                    report(Severity::Error, "ssh-privatekey is not a PEM private key".to_string())
+                }
+                Some(_) => {}
+            },
+            _ => {}
+        }
+    }
+    issues
+}
+
+/// [`check_typed_secrets_at`] for the current time
+pub fn check_typed_secrets(documents: &[Value]) -> Vec<TypedSecretIssue> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    check_typed_secrets_at(documents, now)
+}
+
+/// The DNS names in the subjectAltName of a PEM chain's leaf certificate
+pub fn certificate_dns_names(pem: &str) -> Result<Vec<String>> {
+    let chain = Certificate::load_pem_chain(pem.as_bytes())?;
+    let Some(leaf) = chain.first() else {
+        anyhow::bail!("no certificate in PEM");
+    };
+    Ok(dns_names(leaf))
+}
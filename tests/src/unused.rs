@@ -0,0 +1,126 @@
+use serde_yaml::Value;
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::{pod_containers, pod_object_references, pod_spec, resource_kind, resource_name, unreferenced_mounts, Severity};
+
+/// A volume, mount, ConfigMap or Secret that nothing uses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnusedIssue {
+    pub severity: Severity,
+    /// The resource the issue is about, e.g. `Job/test-release-simbruna-db-init`
+    pub resource: String,
+    pub message: String,
+}
+
+impl fmt::Display for UnusedIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.severity, self.resource, self.message)
+    }
+}
+
+fn names<'a>(list: Option<&'a Value>, field: &str) -> Vec<&'a str> {
+    list.and_then(|l| l.as_sequence())
+        .into_iter()
+        .flatten()
+        .filter_map(|item| item.get(field).and_then(|n| n.as_str()))
+        .collect()
+}
+
+/// Secrets consumed outside pod specs: Ingress TLS certificates and ServiceAccount secrets
+fn non_pod_secret_references(doc: &Value) -> Vec<&str> {
+    match resource_kind(doc) {
+        Some("Ingress") => names(doc.get("spec").and_then(|s| s.get("tls")), "secretName"),
+        Some("ServiceAccount") => {
+            let mut refs = names(doc.get("imagePullSecrets"), "name");
+            refs.extend(names(doc.get("secrets"), "name"));
+            refs
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Report pod volumes nothing mounts, mounts of volumes that don't exist, mounts the container's
+/// command never references, and ConfigMaps and Secrets no workload consumes
+pub fn check_unused_objects(documents: &[Value]) -> Vec<UnusedIssue> {
+    let mut issues = Vec::new();
+    let mut consumed: BTreeSet<(String, String)> = BTreeSet::new();
+
+    for doc in documents {
+        let (Some(kind), Some(name)) = (resource_kind(doc), resource_name(doc)) else {
+            continue;
+        };
+        for secret in non_pod_secret_references(doc) {
+            consumed.insert(("Secret".to_string(), secret.to_string()));
+        }
+        let Some(spec) = pod_spec(doc) else {
+            continue;
+        };
+        let resource = format!("{}/{}", kind, name);
+        let mut report = |severity: Severity, message: String| {
+            issues.push(UnusedIssue {
+                severity,
+                resource: resource.clone(),
+                message,
+            })
+        };
+
+        for reference in pod_object_references(spec) {
+            consumed.insert((reference.kind, reference.name));
+        }
+
+        let volumes = names(spec.get("volumes"), "name");
+        let mut mounted = BTreeSet::new();
+        for container in pod_containers(spec) {
+            let container_name = container.get("name").and_then(|n| n.as_str()).unwrap_or("?");
+            let container_mounts = names(container.get("volumeMounts"), "name");
+            let devices = names(container.get("volumeDevices"), "name");
+            for volume in container_mounts.iter().chain(&devices) {
+                mounted.insert(*volume);
+                if !volumes.contains(volume) {
+                    report(
+                        Severity::Error,
+                        format!("container '{}' mounts volume '{}', which the pod does not define", container_name, volume),
+                    );
+                }
+            }
+            for mount in unreferenced_mounts(container) {
+                report(
+                    Severity::Info,
+                    format!(
+                        "container '{}' mounts volume '{}' at {}, but its command and args never reference it",
+                        container_name,
+                        mount.get("name").and_then(|n| n.as_str()).unwrap_or("?"),
+                        mount.get("mountPath").and_then(|p| p.as_str()).unwrap_or("?")
+                    ),
+                );
+            }
+        }
+        for volume in volumes.iter().filter(|v| !mounted.contains(*v)) {
+            report(Severity::Warning, format!("volume '{}' is not mounted by any container", volume));
+        }
+    }
+
+    for doc in documents {
+        let (Some(kind), Some(name)) = (resource_kind(doc), resource_name(doc)) else {
+            continue;
+        };
+        if !matches!(kind, "ConfigMap" | "Secret") {
+            continue;
+        }
+        if kind == "Secret"
+            && doc.get("type").and_then(|t| t.as_str()) == Some("kubernetes.io/service-account-token")
+        {
+            continue;
+        }
+        if !consumed.contains(&(kind.to_string(), name.to_string())) {
+            issues.push(UnusedIssue {
+                severity: Severity::Warning,
+                resource: format!("{}/{}", kind, name),
+                message: "no workload consumes it through a volume, env, envFrom or projected source".to_string(),
+            });
+        }
+    }
+
+    issues
+}
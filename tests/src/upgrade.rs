@@ -0,0 +1,391 @@
+use anyhow::Result;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+use crate::hooks::parse_hook;
+use crate::{
+    parse_yaml_documents, resource_kind, resource_name, run_git,
+    run_helm_template, Severity,
+};
+
+/// Where to take the previous revision of a chart from
+#[derive(Debug, Clone)]
+pub enum PreviousChart {
+    /// A git ref (commit, tag or branch) in the repository containing the chart
+    GitRef(String),
+    /// A packaged chart archive produced by `helm package`
+    Package(PathBuf),
+}
+
+/// A change between two renders that would break or disrupt `helm upgrade`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpgradeIssue {
+    pub severity: Severity,
+    pub kind: String,
+    pub name: String,
+    pub message: String,
+}
+
+impl fmt::Display for UpgradeIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}/{}: {}", self.severity, self.kind, self.name, self.message)
+    }
+}
+
+/// A chart revision extracted from git history, kept alive for as long as it is rendered
+pub struct ExtractedChart {
+    _dir: TempDir,
+    pub path: PathBuf,
+}
+
+/// Package the chart at `chart_path` as it was at `git_ref`, using only the local git history
+pub fn extract_chart_at_ref(chart_path: &str, git_ref: &str) -> Result<ExtractedChart> {
+    let chart_dir = std::fs::canonicalize(chart_path)?;
+    let chart_dir_str = chart_dir.to_string_lossy();
+    let top_level = run_git(&chart_dir_str, &["rev-parse", "--show-toplevel"])?;
+    let prefix = run_git(&chart_dir_str, &["rev-parse", "--show-prefix"])?;
+    let prefix = prefix.trim().trim_end_matches('/');
+    let chart_name = chart_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| anyhow::anyhow!("Cannot determine chart name from '{}'", chart_path))?;
+
+    let dir = tempfile::tempdir()?;
+    let archive = dir.path().join(format!("{}.tgz", chart_name));
+    run_git(
+        top_level.trim(),
+        &[
+            "archive",
+            "--format=tar.gz",
+            &format!("--prefix={}/", chart_name),
+            "-o",
+            &archive.to_string_lossy(),
+            &format!("{}:{}", git_ref, prefix),
+        ],
+    )?;
+
+    Ok(ExtractedChart {
+        _dir: dir,
+        path: archive,
+    })
+}
+
+/// Render the previous and current chart with the same values and report upgrade breakage
+pub fn simulate_upgrade(
+    chart_path: &str,
+    previous: &PreviousChart,
+    values: Option<&HashMap<String, String>>,
+) -> Result<Vec<UpgradeIssue>> {
+    let extracted;
+    let previous_path: &Path = match previous {
+        PreviousChart::GitRef(git_ref) => {
+            extracted = extract_chart_at_ref(chart_path, git_ref)?;
+            &extracted.path
+        }
+        PreviousChart::Package(path) => path,
+    };
+
+    let old_output = run_helm_template(&previous_path.to_string_lossy(), values)?;
+    let new_output = run_helm_template(chart_path, values)?;
+
+    Ok(diff_immutable_fields(
+        &parse_yaml_documents(&old_output)?,
+        &parse_yaml_documents(&new_output)?,
+    ))
+}
+
+/// Compare two renders of a release and report changes `helm upgrade` cannot apply in place
+pub fn diff_immutable_fields(old_docs: &[Value], new_docs: &[Value]) -> Vec<UpgradeIssue> {
+    let old_resources = index_resources(old_docs);
+    let new_resources = index_resources(new_docs);
+    let mut issues = Vec::new();
+
+    for ((kind, name), old) in &old_resources {
+        if let Some(new) = new_resources.get(&(kind.clone(), name.clone())) {
+            check_resource(kind, name, old, new, &mut issues);
+        }
+    }
+
+    check_removed_resources(&old_resources, &new_resources, &mut issues);
+
+    issues.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| a.kind.cmp(&b.kind))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    issues
+}
+
+fn index_resources(docs: &[Value]) -> BTreeMap<(String, String), &Value> {
+    docs.iter()
+        .filter_map(|doc| {
+            let kind = resource_kind(doc)?;
+            let name = resource_name(doc)?;
+            Some(((kind.to_string(), name.to_string()), doc))
+        })
+        .collect()
+}
+
+fn spec_field<'a>(doc: &'a Value, path: &[&str]) -> Option<&'a Value> {
+    path.iter()
+        .try_fold(doc.get("spec")?, |value, key| value.get(*key))
+}
+
+fn changed(old: &Value, new: &Value, path: &[&str]) -> bool {
+    spec_field(old, path) != spec_field(new, path)
+}
+
+fn check_resource(kind: &str, name: &str, old: &Value, new: &Value, issues: &mut Vec<UpgradeIssue>) {
+    let mut report = |severity: Severity, message: String| {
+        issues.push(UpgradeIssue {
+            severity,
+            kind: kind.to_string(),
+            name: name.to_string(),
+            message,
+        })
+    };
+
+    match kind {
+        "Deployment" | "StatefulSet" | "DaemonSet" | "ReplicaSet" => {
+            if changed(old, new, &["selector"]) {
+                report(
+                    Severity::Error,
+                    "spec.selector is immutable; the upgrade will be rejected".to_string(),
+                );
+            }
+            if kind == "StatefulSet" {
+                for field in ["serviceName", "volumeClaimTemplates", "podManagementPolicy"] {
+                    if changed(old, new, &[field]) {
+                        report(
+                            Severity::Error,
+                            format!("spec.{} is immutable on StatefulSets", field),
+                        );
+                    }
+                }
+            }
+        }
+        "Job" => {
+            let fields: Vec<&str> = ["selector", "template"]
+                .into_iter()
+                .filter(|field| changed(old, new, &[field]))
+                .collect();
+            if fields.is_empty() {
+                return;
+            }
+            let fields = fields
+                .iter()
+                .map(|field| format!("spec.{}", field))
+                .collect::<Vec<_>>()
+                .join(" and ");
+
+            // Helm creates hooks afresh on every run instead of patching them
+            match parse_hook(new) {
+                Some(hook) if hook.delete_policies.iter().any(|p| p == "before-hook-creation") => report(
+                    Severity::Info,
+                    format!(
+                        "{} changed; the hook Job is deleted and re-created by before-hook-creation",
+                        fields
+                    ),
+                ),
+                Some(_) => report(
+                    Severity::Info,
+                    format!("{} changed; hook Jobs are created on each run, not patched", fields),
+                ),
+                None => report(
+                    Severity::Error,
+                    format!("{} is immutable on Jobs; the upgrade will be rejected", fields),
+                ),
+            }
+        }
+        "PersistentVolumeClaim" => {
+            if changed(old, new, &["storageClassName"]) {
+                report(
+                    Severity::Error,
+                    format!(
+                        "spec.storageClassName changed from {} to {}; PVCs cannot change storage class",
+                        describe(spec_field(old, &["storageClassName"])),
+                        describe(spec_field(new, &["storageClassName"]))
+                    ),
+                );
+            }
+            check_pvc_spec(old, new, &mut report);
+        }
+        "Service" => {
+            let old_ip = spec_field(old, &["clusterIP"]);
+            let new_ip = spec_field(new, &["clusterIP"]);
+            if old_ip.is_some() && old_ip != new_ip {
+                report(
+                    Severity::Error,
+                    format!(
+                        "spec.clusterIP changed from {} to {}; clusterIP is immutable",
+                        describe(old_ip),
+                        describe(new_ip)
+                    ),
+                );
+            }
+
+            let old_type = service_type(old);
+            let new_type = service_type(new);
+            if old_type != new_type {
+                let severity = if old_type == "ExternalName" || new_type == "ExternalName" {
+                    Severity::Error
+                } else {
+                    Severity::Warning
+                };
+                report(
+                    severity,
+                    format!(
+                        "spec.type changes from {} to {}; allocated node ports and load balancers are released",
+                        old_type, new_type
+                    ),
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_pvc_spec(old: &Value, new: &Value, report: &mut impl FnMut(Severity, String)) {
+    let (Some(Value::Mapping(old_spec)), Some(Value::Mapping(new_spec))) = (old.get("spec"), new.get("spec"))
+    else {
+        return;
+    };
+
+    let mut keys: Vec<&Value> = old_spec.keys().chain(new_spec.keys()).collect();
+    keys.sort_by_key(|k| k.as_str().unwrap_or_default().to_string());
+    keys.dedup();
+
+    for key in keys {
+        let field = key.as_str().unwrap_or_default();
+        if field == "storageClassName" || field == "resources" {
+            continue;
+        }
+        if old_spec.get(key) != new_spec.get(key) {
+            report(
+                Severity::Error,
+                format!("spec.{} changed; PVC specs are immutable after creation", field),
+            );
+        }
+    }
+
+    let old_size = spec_field(old, &["resources", "requests", "storage"]);
+    let new_size = spec_field(new, &["resources", "requests", "storage"]);
+    if old_size != new_size {
+        match (old_size.and_then(quantity_bytes), new_size.and_then(quantity_bytes)) {
+            (Some(before), Some(after)) if after > before => report(
+                Severity::Info,
+                format!(
+                    "storage request grows from {} to {}; requires a StorageClass with allowVolumeExpansion",
+                    describe(old_size),
+                    describe(new_size)
+                ),
+            ),
+            _ => report(
+                Severity::Error,
+                format!(
+                    "storage request changes from {} to {}; PVCs can only grow",
+                    describe(old_size),
+                    describe(new_size)
+                ),
+            ),
+        }
+    }
+}
+
+fn check_removed_resources(
+    old_resources: &BTreeMap<(String, String), &Value>,
+    new_resources: &BTreeMap<(String, String), &Value>,
+    issues: &mut Vec<UpgradeIssue>,
+) {
+    for ((kind, name), old) in old_resources {
+        if new_resources.contains_key(&(kind.clone(), name.clone())) {
+            continue;
+        }
+
+        let replacement = new_resources.iter().find(|((new_kind, new_name), new)| {
+            new_kind == kind
+                && !old_resources.contains_key(&(new_kind.clone(), new_name.clone()))
+                && component_label(new).is_some()
+                && component_label(new) == component_label(old)
+        });
+
+        let severity = if kind == "PersistentVolumeClaim" || kind == "StatefulSet" {
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+
+        let message = match replacement {
+            Some(((_, new_name), _)) => format!(
+                "renamed to '{}'; Helm deletes the old resource and creates a new one",
+                new_name
+            ),
+            None => "removed; Helm deletes it during the upgrade".to_string(),
+        };
+
+        issues.push(UpgradeIssue {
+            severity,
+            kind: kind.clone(),
+            name: name.clone(),
+            message,
+        });
+    }
+}
+
+fn component_label(doc: &Value) -> Option<&str> {
+    doc.get("metadata")
+        .and_then(|m| m.get("labels"))
+        .and_then(|l| l.get("app.kubernetes.io/component"))
+        .and_then(|c| c.as_str())
+}
+
+fn service_type(doc: &Value) -> &str {
+    spec_field(doc, &["type"])
+        .and_then(|t| t.as_str())
+        .unwrap_or("ClusterIP")
+}
+
+fn describe(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => "<unset>".to_string(),
+        Some(Value::String(s)) => format!("'{}'", s),
+        Some(other) => serde_yaml::to_string(other)
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// Convert a Kubernetes storage quantity such as `64Gi` or `500M` into bytes
+fn quantity_bytes(value: &Value) -> Option<f64> {
+    let text = match value {
+        Value::String(s) => s.trim().to_string(),
+        Value::Number(n) => n.to_string(),
+        _ => return None,
+    };
+
+    let suffixes: [(&str, f64); 12] = [
+        ("Ki", 1024f64),
+        ("Mi", 1024f64.powi(2)),
+        ("Gi", 1024f64.powi(3)),
+        ("Ti", 1024f64.powi(4)),
+        ("Pi", 1024f64.powi(5)),
+        ("Ei", 1024f64.powi(6)),
+        ("k", 1e3),
+        ("M", 1e6),
+        ("G", 1e9),
+        ("T", 1e12),
+        ("P", 1e15),
+        ("E", 1e18),
+    ];
+
+    for (suffix, multiplier) in suffixes {
+        if let Some(number) = text.strip_suffix(suffix) {
+            return number.parse::<f64>().ok().map(|n| n * multiplier);
+        }
+    }
+    text.parse::<f64>().ok()
+}
@@ -0,0 +1,176 @@
+use anyhow::Result;
+use regex::Regex;
+use serde_yaml::Value;
+use std::fmt;
+
+use crate::consistency::{parse_url, SecretValue};
+use crate::secret_scan::redact;
+
+/// Whether `text` matches `pattern` in full; the pattern is `regex` crate syntax, anchored at both ends
+pub fn matches_pattern(pattern: &str, text: &str) -> Result<bool> {
+    let regex = Regex::new(&format!("^(?:{})$", pattern))
+        .map_err(|e| anyhow::anyhow!("invalid pattern `{}`: {}", pattern, e))?;
+    Ok(regex.is_match(text))
+}
+
+/// Why `text` is not an RFC 1123 hostname, if it isn't
+fn hostname_problem(text: &str) -> Option<String> {
+    if text.len() > 253 {
+        return Some(format!("is {} characters long; hostnames allow 253", text.len()));
+    }
+    for label in text.split('.') {
+        if label.is_empty() {
+            return Some("has an empty label".to_string());
+        }
+        if label.len() > 63 {
+            return Some(format!("has the {}-character label '{}'; labels allow 63", label.len(), label));
+        }
+        if let Some(c) = label.chars().find(|c| !c.is_ascii_alphanumeric() && *c != '-') {
+            return Some(format!("contains '{}'", c));
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Some(format!("has the label '{}', which starts or ends with '-'", label));
+        }
+    }
+    None
+}
+
+/// The shape a value must have
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueFormat {
+    /// Matches the `regex` crate pattern in full
+    Pattern(String),
+    /// An absolute URL with this scheme
+    UrlScheme(String),
+    /// ASCII digits only
+    Numeric,
+    /// Unpadded base64url of exactly this many characters
+    Base64Url(usize),
+    /// An RFC 1123 hostname
+    Hostname,
+}
+
+impl fmt::Display for ValueFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueFormat::Pattern(pattern) => write!(f, "matches `{}`", pattern),
+            ValueFormat::UrlScheme(scheme) => write!(f, "is a {} URL", scheme),
+            ValueFormat::Numeric => write!(f, "is numeric"),
+            ValueFormat::Base64Url(length) => write!(f, "is {}-character base64url", length),
+            ValueFormat::Hostname => write!(f, "is a hostname"),
+        }
+    }
+}
+
+impl ValueFormat {
+    /// Why `value` does not have this format, or `None` when it does
+    pub fn problem(&self, value: &str) -> Result<Option<String>> {
+        let problem = match self {
+            ValueFormat::Pattern(pattern) => {
+                (!matches_pattern(pattern, value)?).then(|| format!("does not match `{}`", pattern))
+            }
+            ValueFormat::UrlScheme(scheme) => match parse_url(value) {
+                Err(e) => Some(e.to_string()),
+                Ok(url) if url.scheme != scheme.to_lowercase() => {
+                    Some(format!("uses the {} scheme, not {}", url.scheme, scheme))
+                }
+                Ok(_) => None,
+            },
+            ValueFormat::Numeric => value
+                .chars()
+                .find(|c| !c.is_ascii_digit())
+                .map(|c| format!("contains the non-digit '{}'", c)),
+            ValueFormat::Base64Url(length) => {
+                match value.chars().find(|c| !c.is_ascii_alphanumeric() && *c != '-' && *c != '_') {
+                    Some(c) => Some(format!("contains '{}', which is not a base64url character", c)),
+                    None if value.len() != *length => {
+                        Some(format!("has {} characters, not {}", value.len(), length))
+                    }
+                    None => None,
+                }
+            }
+            ValueFormat::Hostname => hostname_problem(value),
+        };
+        Ok(problem)
+    }
+}
+
+/// A format a rendered Secret value must have
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatRule {
+    pub value: SecretValue,
+    pub format: ValueFormat,
+}
+
+impl FormatRule {
+    pub fn new(secret_suffix: &str, key: &str, format: ValueFormat) -> Self {
+        FormatRule {
+            value: SecretValue::new(secret_suffix, key),
+            format,
+        }
+    }
+}
+
+impl fmt::Display for FormatRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.value, self.format)
+    }
+}
+
+/// A Secret value that does not have its declared format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatViolation {
+    pub rule: String,
+    pub message: String,
+}
+
+impl fmt::Display for FormatViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.rule, self.message)
+    }
+}
+
+fn check_rule(documents: &[Value], rule: &FormatRule) -> Result<Option<String>> {
+    let (secret, value) = rule.value.resolve(documents)?;
+    if value.is_empty() {
+        return Ok(None);
+    }
+    Ok(rule.format.problem(&value)?.map(|problem| {
+        format!("Secret {} key {} value '{}' {}", secret, rule.value.key, redact(&value), problem)
+    }))
+}
+
+/// Check the decoded Secret values in a render against their formats; empty values are skipped,
+/// and values appear redacted in messages
+pub fn check_value_formats(documents: &[Value], rules: &[FormatRule]) -> Vec<FormatViolation> {
+    rules
+        .iter()
+        .filter_map(|rule| {
+            let message = check_rule(documents, rule).unwrap_or_else(|e| Some(e.to_string()))?;
+            Some(FormatViolation {
+                rule: rule.to_string(),
+                message,
+            })
+        })
+        .collect()
+}
+
+/// The formats life's Firebase and OAuth values must have
+pub fn life_format_rules() -> Vec<FormatRule> {
+    vec![
+        FormatRule::new(
+            "-firebase-secrets",
+            "project-id",
+            ValueFormat::Pattern("[a-z][a-z0-9-]{4,28}[a-z0-9]".to_string()),
+        ),
+        FormatRule::new("-firebase-secrets", "auth-domain", ValueFormat::Hostname),
+        FormatRule::new("-firebase-secrets", "messaging-sender-id", ValueFormat::Numeric),
+        FormatRule::new(
+            "-firebase-secrets",
+            "app-id",
+            ValueFormat::Pattern(r"1:\d+:web:[0-9a-f]+".to_string()),
+        ),
+        FormatRule::new("-firebase-secrets", "vapid-key", ValueFormat::Base64Url(87)),
+        FormatRule::new("-oauth-secrets", "redirect-url", ValueFormat::UrlScheme("https".to_string())),
+    ]
+}
@@ -0,0 +1,407 @@
+use anyhow::Result;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+use crate::template::{chart_template_files, scan_template, tokenize_action, Segment, Token};
+
+/// Segment used for the elements of a list in a values path
+pub const LIST_ELEMENT: &str = "[]";
+
+/// Segment used for the entries of a map ranged over in a template
+pub const ANY_KEY: &str = "*";
+
+/// A `.Values` path read by a template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueReference {
+    pub path: Vec<String>,
+    pub file: String,
+    pub line: usize,
+    /// Whether the whole subtree is consumed (printed, passed to a function) rather than only tested
+    pub consumed: bool,
+}
+
+impl ValueReference {
+    /// The path in `a.b[].c` notation
+    pub fn display_path(&self) -> String {
+        display_path(&self.path)
+    }
+}
+
+impl fmt::Display for ValueReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}:{})", self.display_path(), self.file, self.line)
+    }
+}
+
+/// A key declared in values.yaml
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeclaredValue {
+    pub path: Vec<String>,
+    /// Scalars, empty maps, empty lists and nulls
+    pub leaf: bool,
+    /// Empty maps, empty lists and nulls, which users are expected to fill in
+    pub open: bool,
+}
+
+/// Result of comparing template references against values.yaml for one chart
+#[derive(Debug, Clone)]
+pub struct ValuesCoverage {
+    pub references: Vec<ValueReference>,
+    pub declared: Vec<DeclaredValue>,
+    /// Top-most declared keys that no template reads
+    pub dead: Vec<String>,
+    /// Top-most declared keys that only NOTES.txt reads, so they never affect manifests
+    pub notes_only: Vec<String>,
+    /// Top-most referenced paths that values.yaml does not declare, with their first reference
+    pub undeclared: Vec<ValueReference>,
+}
+
+/// Render a values path in `a.b[].c` notation
+pub fn display_path(path: &[String]) -> String {
+    path.join(".").replace(&format!(".{}", LIST_ELEMENT), LIST_ELEMENT)
+}
+
+/// Flatten values.yaml into every declared key, with list elements merged under `[]`
+pub fn declared_values(values: &Value) -> Vec<DeclaredValue> {
+    fn walk(value: &Value, path: &mut Vec<String>, out: &mut BTreeMap<Vec<String>, DeclaredValue>) {
+        let (leaf, open) = match value {
+            Value::Mapping(map) => (map.is_empty(), map.is_empty()),
+            Value::Sequence(seq) => (seq.is_empty(), seq.is_empty()),
+            Value::Null => (true, true),
+            Value::Tagged(tagged) => return walk(&tagged.value, path, out),
+            _ => (true, false),
+        };
+        if !path.is_empty() {
+            let entry = out.entry(path.clone()).or_insert(DeclaredValue {
+                path: path.clone(),
+                leaf,
+                open,
+            });
+            entry.leaf &= leaf;
+            entry.open &= open;
+        }
+
+        match value {
+            Value::Mapping(map) => {
+                for (key, child) in map {
+                    let key = match key {
+                        Value::String(s) => s.clone(),
+                        other => serde_yaml::to_string(other).unwrap_or_default().trim().to_string(),
+                    };
+                    path.push(key);
+                    walk(child, path, out);
+                    path.pop();
+                }
+            }
+            Value::Sequence(seq) => {
+                for item in seq {
+                    path.push(LIST_ELEMENT.to_string());
+                    walk(item, path, out);
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut out = BTreeMap::new();
+    walk(values, &mut Vec::new(), &mut out);
+    out.into_values().collect()
+}
+
+/// What `.` or a variable points at while walking a template
+#[derive(Debug, Clone, PartialEq)]
+enum Scope {
+    Root,
+    Values(Vec<String>),
+    Unknown,
+}
+
+struct Frame {
+    dot: Scope,
+    vars: HashMap<String, Scope>,
+}
+
+struct Walker<'a> {
+    file: &'a str,
+    declared: &'a [DeclaredValue],
+    dot: Scope,
+    vars: HashMap<String, Scope>,
+    stack: Vec<Frame>,
+    references: Vec<ValueReference>,
+}
+
+impl<'a> Walker<'a> {
+    fn resolve(&self, field: &str) -> Scope {
+        let (base, rest) = if let Some(stripped) = field.strip_prefix('$') {
+            let name_end = stripped.find('.').unwrap_or(stripped.len());
+            let name = &stripped[..name_end];
+            let base = if name.is_empty() {
+                Scope::Root
+            } else {
+                self.vars.get(name).cloned().unwrap_or(Scope::Unknown)
+            };
+            (base, &stripped[name_end..])
+        } else {
+            (self.dot.clone(), field)
+        };
+
+        let segments: Vec<&str> = rest.split('.').filter(|s| !s.is_empty()).collect();
+        match base {
+            Scope::Root => match segments.split_first() {
+                None => Scope::Root,
+                Some((&"Values", tail)) => Scope::Values(tail.iter().map(|s| s.to_string()).collect()),
+                Some(_) => Scope::Unknown,
+            },
+            Scope::Values(mut path) => {
+                path.extend(segments.iter().map(|s| s.to_string()));
+                Scope::Values(path)
+            }
+            Scope::Unknown => Scope::Unknown,
+        }
+    }
+
+    fn element_of(&self, scope: Scope) -> Scope {
+        match scope {
+            Scope::Values(mut path) => {
+                let is_map = self
+                    .declared
+                    .iter()
+                    .any(|d| d.path.len() == path.len() + 1 && d.path.starts_with(&path) && d.path[path.len()] != LIST_ELEMENT);
+                path.push(if is_map { ANY_KEY } else { LIST_ELEMENT }.to_string());
+                Scope::Values(path)
+            }
+            _ => Scope::Unknown,
+        }
+    }
+
+    fn record(&mut self, tokens: &[Token], line: usize, consumed: bool) {
+        for token in tokens {
+            if let Token::Field(field) = token {
+                if let Scope::Values(path) = self.resolve(field) {
+                    if path.is_empty() && !consumed {
+                        continue;
+                    }
+                    self.references.push(ValueReference {
+                        path,
+                        file: self.file.to_string(),
+                        line,
+                        consumed,
+                    });
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, dot: Scope) {
+        self.stack.push(Frame {
+            dot: self.dot.clone(),
+            vars: self.vars.clone(),
+        });
+        self.dot = dot;
+    }
+
+    fn single_field_scope(&self, tokens: &[Token]) -> Scope {
+        match tokens {
+            [Token::Field(field)] => self.resolve(field),
+            _ => Scope::Unknown,
+        }
+    }
+
+    fn action(&mut self, tokens: &[Token], line: usize) {
+        let keyword = match tokens.first() {
+            Some(Token::Ident(word)) => word.as_str(),
+            _ => "",
+        };
+
+        match keyword {
+            "if" => {
+                self.record(&tokens[1..], line, false);
+                let dot = self.dot.clone();
+                self.push(dot);
+            }
+            "with" => {
+                self.record(&tokens[1..], line, false);
+                let dot = self.single_field_scope(&tokens[1..]);
+                self.push(dot);
+            }
+            "range" => {
+                let (vars, head) = match tokens.iter().position(|t| *t == Token::Declare) {
+                    Some(pos) => (&tokens[1..pos], &tokens[pos + 1..]),
+                    None => (&tokens[..0], &tokens[1..]),
+                };
+                self.record(head, line, false);
+                let element = self.element_of(self.single_field_scope(head));
+                self.push(element.clone());
+                let names: Vec<&str> = vars
+                    .iter()
+                    .filter_map(|t| match t {
+                        Token::Field(f) => f.strip_prefix('$'),
+                        _ => None,
+                    })
+                    .collect();
+                if let Some(value_var) = names.last() {
+                    self.vars.insert(value_var.to_string(), element);
+                }
+            }
+            "else" => {
+                // `else` branches see the scope from before the block head
+                if let Some(frame) = self.stack.last() {
+                    self.dot = frame.dot.clone();
+                    self.vars = frame.vars.clone();
+                }
+                match tokens.get(1) {
+                    Some(Token::Ident(word)) if word == "if" => self.record(&tokens[2..], line, false),
+                    Some(Token::Ident(word)) if word == "with" => {
+                        self.record(&tokens[2..], line, false);
+                        self.dot = self.single_field_scope(&tokens[2..]);
+                    }
+                    _ => {}
+                }
+            }
+            "end" => {
+                if let Some(frame) = self.stack.pop() {
+                    self.dot = frame.dot;
+                    self.vars = frame.vars;
+                }
+            }
+            "define" | "block" => {
+                if keyword == "block" {
+                    self.record(&tokens[1..], line, true);
+                }
+                self.push(Scope::Root);
+                self.vars.clear();
+            }
+            _ => match tokens {
+                [Token::Field(var), Token::Declare | Token::Assign, rhs @ ..] if var.starts_with('$') => {
+                    self.record(rhs, line, true);
+                    let scope = self.single_field_scope(rhs);
+                    self.vars.insert(var[1..].to_string(), scope);
+                }
+                _ => self.record(tokens, line, true),
+            },
+        }
+    }
+}
+
+/// Extract every `.Values` path a template reads, following `with`/`range` scoping and variables
+pub fn extract_value_references(source: &str, file: &str, declared: &[DeclaredValue]) -> Result<Vec<ValueReference>> {
+    let mut walker = Walker {
+        file,
+        declared,
+        dot: Scope::Root,
+        vars: HashMap::new(),
+        stack: Vec::new(),
+        references: Vec::new(),
+    };
+
+    for segment in scan_template(source)? {
+        if let Segment::Action(action) = segment {
+            let tokens = tokenize_action(&action.text)
+                .map_err(|e| anyhow::anyhow!("{}:{}: {}", file, action.line, e))?;
+            walker.action(&tokens, action.line);
+        }
+    }
+
+    Ok(walker.references)
+}
+
+fn segment_matches(reference: &str, declared: &str) -> bool {
+    reference == declared || (reference == ANY_KEY && declared != LIST_ELEMENT)
+}
+
+pub(crate) fn is_prefix(prefix: &[String], path: &[String]) -> bool {
+    prefix.len() <= path.len() && prefix.iter().zip(path).all(|(r, d)| segment_matches(r, d))
+}
+
+fn is_covered(value: &DeclaredValue, references: &[&ValueReference]) -> bool {
+    references.iter().any(|r| {
+        (r.path.len() == value.path.len() && is_prefix(&r.path, &value.path))
+            || (r.consumed && is_prefix(&r.path, &value.path))
+            || (value.leaf && value.path.len() < r.path.len() && is_prefix(&value.path, &r.path))
+    })
+}
+
+fn dead_values(declared: &[DeclaredValue], references: &[&ValueReference]) -> Vec<String> {
+    let mut dead: Vec<&Vec<String>> = Vec::new();
+
+    for value in declared {
+        if dead.iter().any(|d| value.path.starts_with(d)) {
+            continue;
+        }
+        let any_leaf_covered = declared
+            .iter()
+            .filter(|d| d.leaf && d.path.starts_with(&value.path))
+            .any(|d| is_covered(d, references));
+        if !any_leaf_covered && !is_covered(value, references) {
+            dead.push(&value.path);
+        }
+    }
+
+    dead.into_iter().map(|p| display_path(p)).collect()
+}
+
+fn undeclared_references(declared: &[DeclaredValue], references: &[ValueReference]) -> Vec<ValueReference> {
+    let mut undeclared: Vec<ValueReference> = Vec::new();
+
+    for reference in references {
+        for depth in 1..=reference.path.len() {
+            let prefix = &reference.path[..depth];
+            let found = declared
+                .iter()
+                .find(|d| d.path.len() == depth && is_prefix(prefix, &d.path));
+            match found {
+                Some(d) if d.open => break,
+                Some(_) => continue,
+                None => {
+                    let root = prefix.to_vec();
+                    if !undeclared.iter().any(|u| u.path == root) {
+                        undeclared.push(ValueReference {
+                            path: root,
+                            ..reference.clone()
+                        });
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    undeclared
+}
+
+/// Diff the `.Values` paths read by a chart's templates against the keys declared in its values.yaml
+pub fn analyze_values_coverage(chart_path: &str) -> Result<ValuesCoverage> {
+    let chart_dir = Path::new(chart_path);
+    let values: Value = serde_yaml::from_str(&std::fs::read_to_string(chart_dir.join("values.yaml"))?)?;
+    let declared = declared_values(&values);
+
+    let mut references = Vec::new();
+    for file in chart_template_files(chart_dir)? {
+        let source = std::fs::read_to_string(chart_dir.join(&file))?;
+        references.extend(extract_value_references(&source, &file, &declared)?);
+    }
+
+    let all: Vec<&ValueReference> = references.iter().collect();
+    let manifests: Vec<&ValueReference> = references
+        .iter()
+        .filter(|r| !r.file.ends_with("NOTES.txt"))
+        .collect();
+
+    let dead = dead_values(&declared, &all);
+    let notes_only = dead_values(&declared, &manifests)
+        .into_iter()
+        .filter(|path| !dead.contains(path))
+        .collect();
+    let undeclared = undeclared_references(&declared, &references);
+
+    Ok(ValuesCoverage {
+        references,
+        declared,
+        dead,
+        notes_only,
+        undeclared,
+    })
+}
@@ -0,0 +1,307 @@
+use anyhow::Result;
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use k8s_openapi::api::autoscaling::v2::HorizontalPodAutoscaler;
+use k8s_openapi::api::batch::v1::{CronJob, Job};
+use k8s_openapi::api::core::v1::{ConfigMap, PersistentVolumeClaim, Pod, Secret, Service, ServiceAccount};
+use k8s_openapi::api::networking::v1::Ingress;
+use serde_yaml::{Mapping, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use crate::names::check_rendered_names;
+use crate::{parse_k8s_resource, parse_yaml_documents, pod_object_references, pod_spec, resource_kind, resource_name};
+
+/// Strings that tend to break templates which assume tidy input
+const ODD_STRINGS: &[&str] = &[
+    "",
+    " ",
+    "null",
+    "true",
+    "0123",
+    "a: b",
+    "- item",
+    "{{ .Values }}",
+    "\"quoted\"",
+    "multi\nline",
+    "ünïcödé",
+    "UPPER_case",
+];
+
+/// Numbers at the edges of what templates and Kubernetes accept
+const ODD_NUMBERS: &[f64] = &[0.0, -1.0, 1.5, 65536.0, 9007199254740993.0];
+
+/// A single type-preserving change to a chart's default values
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueMutation {
+    ToggleBool(Vec<String>),
+    Remove(Vec<String>),
+    EmptyList(Vec<String>),
+    /// Repeat the list's items so it holds `times` as many
+    ExtendList(Vec<String>, usize),
+    ReplaceString(Vec<String>, String),
+    ReplaceNumber(Vec<String>, f64),
+}
+
+impl ValueMutation {
+    pub fn path(&self) -> &[String] {
+        match self {
+            ValueMutation::ToggleBool(path)
+            | ValueMutation::Remove(path)
+            | ValueMutation::EmptyList(path)
+            | ValueMutation::ExtendList(path, _)
+            | ValueMutation::ReplaceString(path, _)
+            | ValueMutation::ReplaceNumber(path, _) => path,
+        }
+    }
+}
+
+impl fmt::Display for ValueMutation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path().join(".");
+        match self {
+            ValueMutation::ToggleBool(_) => write!(f, "toggle {}", path),
+            ValueMutation::Remove(_) => write!(f, "remove {}", path),
+            ValueMutation::EmptyList(_) => write!(f, "empty {}", path),
+            ValueMutation::ExtendList(_, times) => write!(f, "repeat {} x{}", path, times),
+            ValueMutation::ReplaceString(_, s) => write!(f, "set {} to {:?}", path, s),
+            ValueMutation::ReplaceNumber(_, n) => write!(f, "set {} to {}", path, n),
+        }
+    }
+}
+
+fn number_value(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        Value::Number((n as i64).into())
+    } else {
+        Value::Number(n.into())
+    }
+}
+
+/// Every candidate mutation of a values tree, in a stable order. Lists are mutated as a whole.
+pub fn candidate_mutations(values: &Value) -> Vec<ValueMutation> {
+    fn walk(value: &Value, path: &mut Vec<String>, out: &mut Vec<ValueMutation>) {
+        if !path.is_empty() {
+            out.push(ValueMutation::Remove(path.clone()));
+        }
+        match value {
+            Value::Bool(_) => out.push(ValueMutation::ToggleBool(path.clone())),
+            Value::Number(_) => out.extend(
+                ODD_NUMBERS
+                    .iter()
+                    .map(|n| ValueMutation::ReplaceNumber(path.clone(), *n)),
+            ),
+            Value::String(_) => out.extend(
+                ODD_STRINGS
+                    .iter()
+                    .map(|s| ValueMutation::ReplaceString(path.clone(), s.to_string()))
+                    .chain([ValueMutation::ReplaceString(path.clone(), "x".repeat(300))]),
+            ),
+            Value::Sequence(items) => {
+                out.push(ValueMutation::EmptyList(path.clone()));
+                if !items.is_empty() {
+                    out.push(ValueMutation::ExtendList(path.clone(), 3));
+                }
+            }
+            Value::Mapping(map) => {
+                for (key, child) in map {
+                    if let Some(key) = key.as_str() {
+                        path.push(key.to_string());
+                        walk(child, path, out);
+                        path.pop();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut out = Vec::new();
+    walk(values, &mut Vec::new(), &mut out);
+    out
+}
+
+fn lookup<'a>(values: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(values, |value, key| value.get(key.as_str()))
+}
+
+/// Build the values overlay that applies `mutations` on top of `base`. Removed keys are set to null,
+/// which makes Helm drop them from the chart defaults.
+pub fn mutations_overlay(base: &Value, mutations: &[ValueMutation]) -> Value {
+    let mut overlay = Value::Mapping(Mapping::new());
+    for mutation in mutations {
+        let original = lookup(base, mutation.path());
+        let value = match mutation {
+            ValueMutation::ToggleBool(_) => Value::Bool(!original.and_then(|v| v.as_bool()).unwrap_or(false)),
+            ValueMutation::Remove(_) => Value::Null,
+            ValueMutation::EmptyList(_) => Value::Sequence(Vec::new()),
+            ValueMutation::ExtendList(_, times) => {
+                let items = original.and_then(|v| v.as_sequence()).cloned().unwrap_or_default();
+                Value::Sequence(items.iter().cycle().take(items.len() * times).cloned().collect())
+            }
+            ValueMutation::ReplaceString(_, s) => Value::String(s.clone()),
+            ValueMutation::ReplaceNumber(_, n) => number_value(*n),
+        };
+
+        set_value_at(&mut overlay, mutation.path(), value);
+    }
+    overlay
+}
+
+/// Set a nested key of a values overlay, creating intermediate mappings as needed
+pub fn set_value_at(overlay: &mut Value, path: &[String], value: Value) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let mut node = overlay;
+    for key in parents {
+        if !node.get(key.as_str()).is_some_and(|v| v.is_mapping()) {
+            node[key.as_str()] = Value::Mapping(Mapping::new());
+        }
+        node = node.get_mut(key.as_str()).expect("parent was just inserted");
+    }
+    node[last.as_str()] = value;
+}
+
+/// How a render of a mutated overlay ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FuzzOutcome {
+    /// The chart rendered and every invariant holds
+    Valid,
+    /// The chart refused the values with an explicit `fail` or `required` message
+    Rejected(String),
+    /// The render crashed, produced invalid YAML, or broke an invariant
+    Broken(Vec<String>),
+}
+
+/// Whether a helm error comes from the chart deliberately rejecting its input
+pub fn is_clean_failure(error: &str) -> bool {
+    error.contains("execution error at")
+}
+
+fn check_schema(doc: &Value, kind: &str) -> Result<()> {
+    match kind {
+        "ConfigMap" => parse_k8s_resource::<ConfigMap>(doc).map(drop),
+        "CronJob" => parse_k8s_resource::<CronJob>(doc).map(drop),
+        "DaemonSet" => parse_k8s_resource::<DaemonSet>(doc).map(drop),
+        "Deployment" => parse_k8s_resource::<Deployment>(doc).map(drop),
+        "HorizontalPodAutoscaler" => parse_k8s_resource::<HorizontalPodAutoscaler>(doc).map(drop),
+        "Ingress" => parse_k8s_resource::<Ingress>(doc).map(drop),
+        "Job" => parse_k8s_resource::<Job>(doc).map(drop),
+        "PersistentVolumeClaim" => parse_k8s_resource::<PersistentVolumeClaim>(doc).map(drop),
+        "Pod" => parse_k8s_resource::<Pod>(doc).map(drop),
+        "Secret" => parse_k8s_resource::<Secret>(doc).map(drop),
+        "Service" => parse_k8s_resource::<Service>(doc).map(drop),
+        "ServiceAccount" => parse_k8s_resource::<ServiceAccount>(doc).map(drop),
+        "StatefulSet" => parse_k8s_resource::<StatefulSet>(doc).map(drop),
+        _ => Ok(()),
+    }
+}
+
+/// Check the invariants every render must satisfy: documents deserialize into their Kubernetes types,
+/// names are valid, and references to Secrets, ConfigMaps, Services and scale targets resolve, either
+/// to the render or to one of the `external` object names provisioned outside the chart
+pub fn check_render_invariants(documents: &[Value], external: &[&str]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let rendered: BTreeSet<(&str, &str)> = documents
+        .iter()
+        .filter_map(|doc| Some((resource_kind(doc)?, resource_name(doc)?)))
+        .collect();
+    let resolves = |kind: &str, name: &str| rendered.contains(&(kind, name)) || external.contains(&name);
+
+    for doc in documents {
+        let (Some(kind), Some(name)) = (resource_kind(doc), resource_name(doc)) else {
+            problems.push(format!("document without kind or metadata.name: {:?}", doc));
+            continue;
+        };
+        if doc.get("apiVersion").and_then(|v| v.as_str()).is_none() {
+            problems.push(format!("{}/{}: missing apiVersion", kind, name));
+        }
+        if let Err(e) = check_schema(doc, kind) {
+            problems.push(format!("{}/{}: does not match the {} schema: {}", kind, name, kind, e));
+        }
+
+        if let Some(spec) = pod_spec(doc) {
+            for reference in pod_object_references(spec) {
+                // Pull secrets are provisioned outside the chart
+                if reference.via == "imagePullSecrets" {
+                    continue;
+                }
+                if !resolves(&reference.kind, &reference.name) {
+                    problems.push(format!(
+                        "{}/{}: {} references missing {} '{}'",
+                        kind, name, reference.via, reference.kind, reference.name
+                    ));
+                }
+            }
+        }
+
+        if kind == "Ingress" {
+            let rules = doc.get("spec").and_then(|s| s.get("rules")).and_then(|r| r.as_sequence());
+            let paths = rules
+                .into_iter()
+                .flatten()
+                .filter_map(|rule| rule.get("http")?.get("paths")?.as_sequence())
+                .flatten();
+            for path in paths {
+                let backend = path.get("backend").and_then(|b| b.get("service")).and_then(|s| s.get("name"));
+                if let Some(service) = backend.and_then(|n| n.as_str()) {
+                    if !resolves("Service", service) {
+                        problems.push(format!("Ingress/{}: backend references missing Service '{}'", name, service));
+                    }
+                }
+            }
+        }
+
+        if kind == "HorizontalPodAutoscaler" {
+            let target = doc.get("spec").and_then(|s| s.get("scaleTargetRef"));
+            let target_kind = target.and_then(|t| t.get("kind")).and_then(|k| k.as_str());
+            let target_name = target.and_then(|t| t.get("name")).and_then(|n| n.as_str());
+            if let (Some(target_kind), Some(target_name)) = (target_kind, target_name) {
+                if !resolves(target_kind, target_name) {
+                    problems.push(format!(
+                        "HorizontalPodAutoscaler/{}: scaleTargetRef references missing {} '{}'",
+                        name, target_kind, target_name
+                    ));
+                }
+            }
+        }
+    }
+
+    problems.extend(check_rendered_names(documents).iter().map(|v| v.to_string()));
+    problems
+}
+
+/// Render a chart with the overlay produced by `mutations` and classify the result; `external` names
+/// the objects the chart expects to exist already, as for [`check_render_invariants`]
+pub fn fuzz_render(
+    chart_path: &str,
+    base: &Value,
+    mutations: &[ValueMutation],
+    values: Option<&HashMap<String, String>>,
+    external: &[&str],
+) -> Result<FuzzOutcome> {
+    let overlay = mutations_overlay(base, mutations);
+    let output = match crate::run_helm_template_with_overlay(chart_path, &overlay, values) {
+        Ok(output) => output,
+        Err(e) if crate::is_render_failure(&e) => {
+            let message = e.to_string();
+            return Ok(if is_clean_failure(&message) {
+                FuzzOutcome::Rejected(message)
+            } else {
+                FuzzOutcome::Broken(vec![message])
+            });
+        }
+        Err(e) => return Err(e),
+    };
+
+    let documents = match parse_yaml_documents(&output) {
+        Ok(documents) => documents,
+        Err(e) => return Ok(FuzzOutcome::Broken(vec![format!("render is not valid YAML: {}", e)])),
+    };
+    let problems = check_render_invariants(&documents, external);
+    Ok(if problems.is_empty() {
+        FuzzOutcome::Valid
+    } else {
+        FuzzOutcome::Broken(problems)
+    })
+}
@@ -0,0 +1,466 @@
+use anyhow::Result;
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+use crate::{set_key_segments, SetKeySegment};
+
+/// Comment prefixes that introduce the allowed values of a key, e.g. `# One of: ClusterIP, NodePort`
+const ENUM_MARKERS: &[&str] = &["one of:", "enum:", "allowed values:", "possible values:"];
+
+/// Comment marking a value that may only reach Secret data, e.g. `# @sensitive`
+const SENSITIVE_MARKER: &str = "@sensitive";
+
+/// Path segment standing for the items of a list
+const ITEMS: &str = "[]";
+
+/// A value that does not conform to the values schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    /// Location of the value, e.g. `api.ingress.hosts[0].host`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() { "(root)" } else { &self.path };
+        write!(f, "{}: {}", path, self.message)
+    }
+}
+
+#[derive(Debug, Default)]
+struct KeyComments {
+    description: Vec<String>,
+    enum_values: Option<Vec<String>>,
+    sensitive: bool,
+}
+
+fn parse_enum(comment: &str) -> Option<Vec<String>> {
+    let lower = comment.to_lowercase();
+    let marker = ENUM_MARKERS.iter().find(|m| lower.starts_with(*m))?;
+    let values = comment[marker.len()..]
+        .split([',', '|'])
+        .map(|v| v.trim().trim_matches(['"', '\'', '`', '.']).to_string())
+        .filter(|v| !v.is_empty())
+        .collect::<Vec<_>>();
+    (!values.is_empty()).then_some(values)
+}
+
+fn looks_like_yaml(comment: &str) -> bool {
+    let comment = comment.trim_start_matches("- ");
+    match comment.split_once(':') {
+        Some((key, _)) => !key.is_empty() && !key.contains(' '),
+        None => false,
+    }
+}
+
+/// Collect the comments written above (or after) each key of a values file, keyed by dotted path
+fn collect_key_comments(content: &str) -> BTreeMap<Vec<String>, KeyComments> {
+    let mut comments: BTreeMap<Vec<String>, KeyComments> = BTreeMap::new();
+    let mut stack: Vec<(usize, String)> = Vec::new();
+    let mut pending: Vec<String> = Vec::new();
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        if trimmed.is_empty() {
+            pending.clear();
+            continue;
+        }
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            pending.push(comment.trim().to_string());
+            continue;
+        }
+
+        let (indent, item, rest) = match trimmed.strip_prefix("- ") {
+            Some(rest) => (indent, true, rest),
+            None => (indent, false, trimmed),
+        };
+        while stack.last().is_some_and(|(i, _)| *i >= indent) {
+            stack.pop();
+        }
+        let mut key_indent = indent;
+        if item {
+            stack.push((indent, ITEMS.to_string()));
+            key_indent = indent + 2;
+        }
+
+        let Some((key, value)) = rest.split_once(':') else {
+            pending.clear();
+            continue;
+        };
+        let key = key.trim().trim_matches(['"', '\'']).to_string();
+        stack.push((key_indent, key));
+
+        let path: Vec<String> = stack.iter().map(|(_, k)| k.clone()).collect();
+        let trailing = value.split_once(" #").map(|(_, c)| c.trim().to_string());
+        let entry = comments.entry(path).or_default();
+        for comment in pending.drain(..).chain(trailing) {
+            if comment.eq_ignore_ascii_case(SENSITIVE_MARKER) {
+                entry.sensitive = true;
+            } else if let Some(values) = parse_enum(&comment) {
+                entry.enum_values = Some(values);
+            } else if !looks_like_yaml(&comment) {
+                entry.description.push(comment);
+            }
+        }
+    }
+    comments
+}
+
+/// Paths of the values marked `# @sensitive` in a values file
+pub fn sensitive_value_paths(values_yaml: &str) -> Vec<Vec<String>> {
+    collect_key_comments(values_yaml)
+        .into_iter()
+        .filter(|(_, comments)| comments.sensitive)
+        .map(|(path, _)| path)
+        .collect()
+}
+
+/// The allowed values of each key documented with a comment such as `# One of: a, b, c`
+pub fn enum_value_paths(values_yaml: &str) -> BTreeMap<Vec<String>, Vec<String>> {
+    collect_key_comments(values_yaml)
+        .into_iter()
+        .filter_map(|(path, comments)| Some((path, comments.enum_values?)))
+        .collect()
+}
+
+fn infer_schema(value: &serde_yaml::Value, path: &mut Vec<String>, comments: &BTreeMap<Vec<String>, KeyComments>) -> JsonValue {
+    let mut schema = match value {
+        serde_yaml::Value::Bool(_) => json!({ "type": "boolean" }),
+        serde_yaml::Value::Number(n) if n.is_i64() || n.is_u64() => json!({ "type": "integer" }),
+        serde_yaml::Value::Number(_) => json!({ "type": "number" }),
+        serde_yaml::Value::String(_) => json!({ "type": "string" }),
+        serde_yaml::Value::Sequence(items) => {
+            let mut schema = json!({ "type": "array" });
+            if !items.is_empty() {
+                path.push(ITEMS.to_string());
+                // Merge all items so that keys present on only some of them are still declared
+                let mut merged = serde_yaml::Value::Null;
+                for item in items {
+                    merged = merge_yaml(merged, item.clone());
+                }
+                schema["items"] = infer_schema(&merged, path, comments);
+                path.pop();
+            }
+            schema
+        }
+        serde_yaml::Value::Mapping(map) if map.is_empty() => json!({ "type": "object" }),
+        serde_yaml::Value::Mapping(map) => {
+            let mut properties = Map::new();
+            for (key, child) in map {
+                let Some(key) = key.as_str() else { continue };
+                path.push(key.to_string());
+                properties.insert(key.to_string(), infer_schema(child, path, comments));
+                path.pop();
+            }
+            json!({ "type": "object", "properties": properties, "additionalProperties": false })
+        }
+        _ => json!({}),
+    };
+
+    if let Some(key_comments) = comments.get(path.as_slice()) {
+        if !key_comments.description.is_empty() {
+            schema["description"] = JsonValue::String(key_comments.description.join(" "));
+        }
+        if let Some(values) = &key_comments.enum_values {
+            schema["enum"] = values.iter().map(|v| JsonValue::String(v.clone())).collect();
+        }
+    }
+    schema
+}
+
+fn merge_yaml(base: serde_yaml::Value, overlay: serde_yaml::Value) -> serde_yaml::Value {
+    match (base, overlay) {
+        (serde_yaml::Value::Mapping(mut base), serde_yaml::Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                let merged = match base.remove(&key) {
+                    Some(existing) => merge_yaml(existing, value),
+                    None => value,
+                };
+                base.insert(key, merged);
+            }
+            serde_yaml::Value::Mapping(base)
+        }
+        (base, serde_yaml::Value::Null) => base,
+        (_, overlay) => overlay,
+    }
+}
+
+/// Infer a JSON Schema (draft 7, as used by Helm) from the contents of a values.yaml file
+///
+/// Non-empty objects reject unknown keys, empty ones are free-form. Comments above a key become its
+/// description, and a comment such as `# One of: a, b, c` becomes an enum.
+pub fn generate_values_schema(values_yaml: &str) -> Result<JsonValue> {
+    let values: serde_yaml::Value = serde_yaml::from_str(values_yaml)?;
+    let comments = collect_key_comments(values_yaml);
+    let mut schema = match values {
+        serde_yaml::Value::Null => json!({ "type": "object", "properties": {}, "additionalProperties": false }),
+        values => infer_schema(&values, &mut Vec::new(), &comments),
+    };
+    let mut root = Map::new();
+    root.insert("$schema".to_string(), json!("http://json-schema.org/draft-07/schema#"));
+    if let Some(fields) = schema.as_object_mut() {
+        root.append(fields);
+    }
+    Ok(JsonValue::Object(root))
+}
+
+/// Apply user refinements to a generated schema as a JSON merge patch (RFC 7396): objects are merged,
+/// `null` removes a keyword and anything else replaces it
+pub fn refine_schema(schema: &mut JsonValue, refinement: &JsonValue) {
+    match (schema.as_object_mut(), refinement.as_object()) {
+        (Some(target), Some(patch)) => {
+            for (key, value) in patch {
+                if value.is_null() {
+                    target.remove(key);
+                } else {
+                    refine_schema(target.entry(key.clone()).or_insert(JsonValue::Null), value);
+                }
+            }
+        }
+        _ => *schema = refinement.clone(),
+    }
+}
+
+/// Generate the schema of a chart's values.yaml, refined by an optional merge patch file
+pub fn generate_chart_values_schema(chart_path: &str, refinement: Option<&Path>) -> Result<JsonValue> {
+    let values_yaml = std::fs::read_to_string(Path::new(chart_path).join("values.yaml"))?;
+    let mut schema = generate_values_schema(&values_yaml)?;
+    if let Some(refinement) = refinement {
+        let patch: JsonValue = serde_json::from_str(&std::fs::read_to_string(refinement)?)?;
+        refine_schema(&mut schema, &patch);
+    }
+    Ok(schema)
+}
+
+fn json_type(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+fn type_matches(expected: &str, actual: &str) -> bool {
+    expected == actual || (expected == "number" && actual == "integer")
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn validate_value(schema: &JsonValue, value: &JsonValue, path: &str, errors: &mut Vec<SchemaError>) {
+    let mut error = |message: String| {
+        errors.push(SchemaError {
+            path: path.to_string(),
+            message,
+        })
+    };
+    let actual = json_type(value);
+
+    if let Some(expected) = schema.get("type") {
+        let expected: Vec<&str> = match expected {
+            JsonValue::String(t) => vec![t.as_str()],
+            JsonValue::Array(types) => types.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        if !expected.is_empty() && !expected.iter().any(|t| type_matches(t, actual)) {
+            error(format!("expected {}, got {}", expected.join(" or "), actual));
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !allowed.contains(value) {
+            let allowed: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+            error(format!("{} is not one of {}", value, allowed.join(", ")));
+        }
+    }
+
+    match value {
+        JsonValue::String(s) => {
+            let length = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(|m| m.as_u64()).filter(|m| length < *m) {
+                error(format!("must be at least {} characters long", min));
+            }
+            if let Some(max) = schema.get("maxLength").and_then(|m| m.as_u64()).filter(|m| length > *m) {
+                error(format!("must be at most {} characters long", max));
+            }
+        }
+        JsonValue::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(|m| m.as_f64()).filter(|m| n < *m) {
+                error(format!("must be >= {}", min));
+            }
+            if let Some(max) = schema.get("maximum").and_then(|m| m.as_f64()).filter(|m| n > *m) {
+                error(format!("must be <= {}", max));
+            }
+        }
+        JsonValue::Array(items) => {
+            if let Some(min) = schema.get("minItems").and_then(|m| m.as_u64()).filter(|m| (items.len() as u64) < *m) {
+                error(format!("must have at least {} items", min));
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    validate_value(item_schema, item, &format!("{}[{}]", path, index), errors);
+                }
+            }
+        }
+        JsonValue::Object(fields) => {
+            let properties = schema.get("properties").and_then(|p| p.as_object());
+            for required in schema.get("required").and_then(|r| r.as_array()).into_iter().flatten() {
+                if let Some(key) = required.as_str().filter(|k| !fields.contains_key(*k)) {
+                    errors.push(SchemaError {
+                        path: child_path(path, key),
+                        message: "is required".to_string(),
+                    });
+                }
+            }
+            for (key, child) in fields {
+                let child_at = child_path(path, key);
+                match properties.and_then(|p| p.get(key)) {
+                    Some(property) => validate_value(property, child, &child_at, errors),
+                    None => match schema.get("additionalProperties") {
+                        Some(JsonValue::Bool(false)) => errors.push(SchemaError {
+                            path: child_at,
+                            message: "is not declared in the values schema".to_string(),
+                        }),
+                        Some(additional) if additional.is_object() => {
+                            validate_value(additional, child, &child_at, errors)
+                        }
+                        _ => {}
+                    },
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Validate values against a values schema, reporting every violation with its path
+pub fn validate_values(schema: &JsonValue, values: &JsonValue) -> Vec<SchemaError> {
+    let mut errors = Vec::new();
+    validate_value(schema, values, "", &mut errors);
+    errors
+}
+
+/// Set `value` at a `--set` path, creating maps and null-padded lists on the way as Helm does
+fn set_at_path(node: &mut JsonValue, path: &[SetKeySegment], value: JsonValue) {
+    let Some((segment, rest)) = path.split_first() else {
+        *node = value;
+        return;
+    };
+    match segment {
+        SetKeySegment::Key(key) => {
+            if !node.is_object() {
+                *node = JsonValue::Object(Map::new());
+            }
+            if let JsonValue::Object(map) = node {
+                set_at_path(map.entry(key.clone()).or_insert(JsonValue::Null), rest, value);
+            }
+        }
+        SetKeySegment::Index(index) => {
+            if !node.is_array() {
+                *node = JsonValue::Array(Vec::new());
+            }
+            if let JsonValue::Array(items) = node {
+                if items.len() <= *index {
+                    items.resize(index + 1, JsonValue::Null);
+                }
+                set_at_path(&mut items[*index], rest, value);
+            }
+        }
+    }
+}
+
+/// Parse `--set` style key/value pairs into a values tree, typing scalars the way Helm does; `null`
+/// stays in the tree so [`coalesce_values`] deletes the key from the defaults
+pub fn set_values_to_json(values: &HashMap<String, String>) -> Result<JsonValue> {
+    let mut root = JsonValue::Object(Map::new());
+    for (key, raw) in values {
+        let value = match raw.as_str() {
+            "true" => JsonValue::Bool(true),
+            "false" => JsonValue::Bool(false),
+            "null" => JsonValue::Null,
+            _ => match raw.parse::<i64>() {
+                // Helm keeps integers with a leading zero as strings
+                Ok(n) if !(raw.starts_with('0') && raw.len() > 1) => json!(n),
+                _ => JsonValue::String(raw.clone()),
+            },
+        };
+        set_at_path(&mut root, &set_key_segments(key)?, value);
+    }
+    Ok(root)
+}
+
+/// Drop the `null` keys of an overlay value that has nothing underneath it to delete
+fn without_nulls(value: &JsonValue) -> JsonValue {
+    match value {
+        JsonValue::Object(map) => JsonValue::Object(
+            map.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), without_nulls(v)))
+                .collect(),
+        ),
+        JsonValue::Array(items) => JsonValue::Array(items.iter().map(without_nulls).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Coalesce an overlay onto chart defaults the way Helm does: maps merge, `null` deletes a key and
+/// is never itself part of the result
+pub fn coalesce_values(base: &JsonValue, overlay: &JsonValue) -> JsonValue {
+    match (base, overlay) {
+        (JsonValue::Object(base), JsonValue::Object(overlay)) => {
+            let mut merged = base.clone();
+            for (key, value) in overlay {
+                if value.is_null() {
+                    merged.remove(key);
+                } else {
+                    let value = match base.get(key) {
+                        Some(existing) => coalesce_values(existing, value),
+                        None => without_nulls(value),
+                    };
+                    merged.insert(key.clone(), value);
+                }
+            }
+            JsonValue::Object(merged)
+        }
+        (_, overlay) => without_nulls(overlay),
+    }
+}
+
+/// Validate an overlay file and `--set` values against a chart's schema after merging them onto its
+/// defaults, as `helm template -f <overlay> --set ...` would
+pub fn validate_chart_overlay(
+    chart_path: &str,
+    schema: &JsonValue,
+    overlay_file: Option<&Path>,
+    set_values: Option<&HashMap<String, String>>,
+) -> Result<Vec<SchemaError>> {
+    let defaults: serde_yaml::Value =
+        serde_yaml::from_str(&std::fs::read_to_string(Path::new(chart_path).join("values.yaml"))?)?;
+    let mut values = serde_json::to_value(defaults)?;
+    if !values.is_object() {
+        values = JsonValue::Object(Map::new());
+    }
+    if let Some(overlay_file) = overlay_file {
+        let overlay: serde_yaml::Value = serde_yaml::from_str(&std::fs::read_to_string(overlay_file)?)?;
+        values = coalesce_values(&values, &serde_json::to_value(overlay)?);
+    }
+    if let Some(set_values) = set_values {
+        values = coalesce_values(&values, &set_values_to_json(set_values)?);
+    }
+    Ok(validate_values(schema, &values))
+}
@@ -0,0 +1,216 @@
+use anyhow::Result;
+use semver::Version;
+use serde_yaml::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use crate::chart::{list_charts, ChartMetadata};
+use crate::helmignore::HelmIgnore;
+use crate::upgrade::extract_chart_at_ref;
+use crate::{parse_yaml_documents, resource_kind, resource_name, run_git, run_helm_template};
+
+/// The semver component a change requires bumping
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BumpLevel {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl fmt::Display for BumpLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            BumpLevel::Patch => "patch",
+            BumpLevel::Minor => "minor",
+            BumpLevel::Major => "major",
+        };
+        f.write_str(label)
+    }
+}
+
+/// Result of comparing a chart against a base git ref
+#[derive(Debug, Clone)]
+pub struct VersionCheck {
+    pub chart: String,
+    pub base_version: Option<Version>,
+    pub current_version: Version,
+    /// Chart-relative paths of changed files that go into the packaged chart
+    pub changed_files: Vec<String>,
+    /// The bump the render diff against the base ref calls for, when one was computed
+    pub suggested_bump: Option<BumpLevel>,
+}
+
+impl VersionCheck {
+    /// Whether the chart is new or its version increased since the base ref
+    pub fn is_bumped(&self) -> bool {
+        match &self.base_version {
+            Some(base) => self.current_version > *base,
+            None => true,
+        }
+    }
+
+    /// Whether the packaged content changed without a version bump, or with a smaller one than suggested
+    pub fn is_violation(&self) -> bool {
+        !self.changed_files.is_empty()
+            && (!self.is_bumped() || self.suggested_bump.is_some_and(|level| !self.satisfies(level)))
+    }
+
+    /// The bump level actually applied between the base and current versions
+    pub fn applied_bump(&self) -> Option<BumpLevel> {
+        let base = self.base_version.as_ref()?;
+        let current = &self.current_version;
+        if current <= base {
+            None
+        } else if current.major > base.major {
+            Some(BumpLevel::Major)
+        } else if current.minor > base.minor {
+            Some(BumpLevel::Minor)
+        } else {
+            Some(BumpLevel::Patch)
+        }
+    }
+
+    /// Whether the applied bump is at least `level`; pre-1.0 charts may use minor for breaking changes
+    pub fn satisfies(&self, level: BumpLevel) -> bool {
+        let Some(applied) = self.applied_bump() else {
+            return self.base_version.is_none();
+        };
+        if self.current_version.major == 0 && level == BumpLevel::Major {
+            return applied >= BumpLevel::Minor;
+        }
+        applied >= level
+    }
+}
+
+impl fmt::Display for VersionCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let base = self
+            .base_version
+            .as_ref()
+            .map(|v| v.to_string())
+            .unwrap_or_else(|| "<new>".to_string());
+        write!(
+            f,
+            "{}: {} -> {} ({} packaged file(s) changed)",
+            self.chart,
+            base,
+            self.current_version,
+            self.changed_files.len()
+        )?;
+        if let Some(level) = self.suggested_bump {
+            write!(f, ", the render diff suggests a {} bump", level)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare a chart directory with `base_ref` and list packaged files that changed
+pub fn check_chart_version_bump(chart_path: &str, base_ref: &str) -> Result<VersionCheck> {
+    let chart_dir = std::fs::canonicalize(chart_path)?;
+    let chart_dir_str = chart_dir.to_string_lossy().to_string();
+    let top_level = run_git(&chart_dir_str, &["rev-parse", "--show-toplevel"])?;
+    let top_level = top_level.trim();
+    let prefix = run_git(&chart_dir_str, &["rev-parse", "--show-prefix"])?;
+    let prefix = prefix.trim().trim_end_matches('/').to_string();
+
+    let current_version = ChartMetadata::load(&chart_dir)?.semver()?;
+    let base_version = run_git(top_level, &["show", &format!("{}:{}/Chart.yaml", base_ref, prefix)])
+        .ok()
+        .map(|content| ChartMetadata::parse(&content)?.semver())
+        .transpose()?;
+
+    let diffed = run_git(top_level, &["diff", "--name-only", base_ref, "--", &prefix])?;
+    let untracked = run_git(
+        top_level,
+        &["ls-files", "--others", "--exclude-standard", "--", &prefix],
+    )?;
+
+    let ignore = HelmIgnore::load(&chart_dir)?;
+    let changed_files: BTreeSet<String> = diffed
+        .lines()
+        .chain(untracked.lines())
+        .filter_map(|path| path.strip_prefix(&format!("{}/", prefix)))
+        .filter(|rel| ignore.is_packaged(rel))
+        .map(|rel| rel.to_string())
+        .collect();
+
+    let chart = chart_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or(prefix);
+
+    Ok(VersionCheck {
+        chart,
+        base_version,
+        current_version,
+        changed_files: changed_files.into_iter().collect(),
+        suggested_bump: None,
+    })
+}
+
+/// Check every chart directory under `charts_dir` against `base_ref`, suggesting a bump for each
+/// chart whose packaged files changed since a base version
+pub fn check_all_chart_versions(charts_dir: &str, base_ref: &str) -> Result<Vec<VersionCheck>> {
+    list_charts(charts_dir)?
+        .iter()
+        .map(|chart| {
+            let chart_path = chart.path.to_string_lossy();
+            let mut check = check_chart_version_bump(&chart_path, base_ref)?;
+            if !check.changed_files.is_empty() && check.base_version.is_some() {
+                check.suggested_bump = Some(suggest_version_bump(&chart_path, base_ref, None)?);
+            }
+            Ok(check)
+        })
+        .collect()
+}
+
+/// Suggest a bump level by rendering the chart at `base_ref` and in the working tree with the same values
+pub fn suggest_version_bump(
+    chart_path: &str,
+    base_ref: &str,
+    values: Option<&HashMap<String, String>>,
+) -> Result<BumpLevel> {
+    let previous = extract_chart_at_ref(chart_path, base_ref)?;
+    let old_output = run_helm_template(&previous.path.to_string_lossy(), values)?;
+    let new_output = run_helm_template(chart_path, values)?;
+
+    Ok(suggest_bump_from_render(
+        &parse_yaml_documents(&old_output)?,
+        &parse_yaml_documents(&new_output)?,
+    ))
+}
+
+/// Classify a render diff: removed resources or changed selectors are major, added resources minor
+pub fn suggest_bump_from_render(old_docs: &[Value], new_docs: &[Value]) -> BumpLevel {
+    let key = |doc: &Value| Some((resource_kind(doc)?.to_string(), resource_name(doc)?.to_string()));
+    let old_keys: BTreeSet<_> = old_docs.iter().filter_map(key).collect();
+    let new_keys: BTreeSet<_> = new_docs.iter().filter_map(key).collect();
+
+    if old_keys.difference(&new_keys).next().is_some() {
+        return BumpLevel::Major;
+    }
+
+    let selector_changed = old_docs.iter().any(|old| {
+        let Some(old_key) = key(old) else {
+            return false;
+        };
+        new_docs
+            .iter()
+            .find(|new| key(new).as_ref() == Some(&old_key))
+            .map(|new| selector(old) != selector(new))
+            .unwrap_or(false)
+    });
+    if selector_changed {
+        return BumpLevel::Major;
+    }
+
+    if new_keys.difference(&old_keys).next().is_some() {
+        return BumpLevel::Minor;
+    }
+
+    BumpLevel::Patch
+}
+
+fn selector(doc: &Value) -> Option<&Value> {
+    doc.get("spec").and_then(|spec| spec.get("selector"))
+}
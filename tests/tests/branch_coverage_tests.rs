@@ -0,0 +1,100 @@
+mod common;
+
+use anyhow::Result;
+use common::life_values;
+use helm_tests::branch_coverage::*;
+use helm_tests::template::parse_template;
+use helm_tests::*;
+use std::path::Path;
+
+const CHART_PATH: &str = "../charts/life/";
+
+#[test]
+fn test_life_branch_table() -> Result<()> {
+    let chart = instrument_chart(CHART_PATH)?;
+
+    let hpa: Vec<&Branch> = chart.branches.iter().filter(|b| b.file == "templates/hpa.yaml").collect();
+    assert_eq!(hpa[0].line, 1);
+    assert_eq!(hpa[0].label, "if .Values.autoscaling.enabled");
+    assert!(hpa.iter().any(|b| b.block == hpa[0].block && b.label.starts_with("else (implicit)")));
+
+    // Named templates are rendered with arbitrary contexts and are not instrumented
+    assert!(!chart.branches.iter().any(|b| b.file == "templates/_helpers.tpl"));
+
+    for file in ["templates/hpa.yaml", "templates/api-ingress.yaml", "templates/00-branch-coverage.yaml"] {
+        let source = std::fs::read_to_string(chart.path.join(file))?;
+        parse_template(&source).map_err(|e| anyhow::anyhow!("{}: {}", file, e))?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_instrumentation_keeps_whitespace_control() -> Result<()> {
+    let mut branches = Vec::new();
+    let source = "a:\n  {{- if .Values.x }}\n  b: 1\n  {{- else if .Values.y -}}\n  c: 2\n{{- end }}\n";
+    let instrumented = instrument_template(source, "templates/x.yaml", &mut branches)?;
+
+    let labels: Vec<&str> = branches.iter().map(|b| b.label.as_str()).collect();
+    assert_eq!(labels, vec!["if .Values.x", "else if .Values.y", "else (implicit) of if .Values.x"]);
+    assert!(branches.iter().all(|b| b.block == 0));
+    assert_eq!(
+        instrumented,
+        "a:\n  {{- if .Values.x }}{{ if kindIs \"map\" $ }}{{ $_ := set $ \"__cov_0\" true }}{{ end }}\n  b: 1\n  \
+         {{- else if .Values.y -}}{{ if kindIs \"map\" $ }}{{ $_ := set $ \"__cov_1\" true }}{{ end -}}\n  c: 2\n\
+         {{- else }}{{ if kindIs \"map\" $ }}{{ $_ := set $ \"__cov_2\" true }}{{ end }}{{- end }}\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_text_and_lcov_reports() -> Result<()> {
+    let chart = instrument_chart(CHART_PATH)?;
+    let hpa: Vec<Branch> = chart
+        .branches
+        .iter()
+        .filter(|b| b.file == "templates/hpa.yaml")
+        .cloned()
+        .collect();
+    let mut coverage = BranchCoverage::new(Path::new(CHART_PATH), hpa.clone());
+    let implicit_else = hpa.iter().find(|b| b.block == hpa[0].block && b.index == 1).unwrap();
+    coverage.record(&[implicit_else.id]);
+    coverage.record(&[implicit_else.id]);
+
+    let text = coverage.to_text();
+    assert!(text.starts_with("templates/hpa.yaml: 1/"), "{}", text);
+    assert!(text.contains("  -    1      0  if .Values.autoscaling.enabled\n"), "{}", text);
+    assert!(coverage.missed().iter().any(|b| b.id == hpa[0].id));
+
+    let lcov = coverage.to_lcov();
+    assert!(lcov.starts_with("TN:\nSF:/"), "{}", lcov);
+    assert!(lcov.contains("/charts/life/templates/hpa.yaml\n"));
+    assert!(lcov.contains(&format!("BRDA:1,{},0,0\n", hpa[0].block)));
+    assert!(lcov.contains(&format!("BRDA:{},{},1,2\n", implicit_else.line, hpa[0].block)));
+    assert!(lcov.contains(&format!("BRF:{}\nBRH:1\nend_of_record\n", hpa.len())));
+
+    Ok(())
+}
+
+#[test]
+fn test_instrumented_render_matches_plain_render() -> Result<()> {
+    let values = life_values();
+    let chart = instrument_chart(CHART_PATH)?;
+    let (output, hits) = chart.render(Some(&values))?;
+
+    let plain = run_helm_template(CHART_PATH, Some(&values))?;
+    let plain_docs = parse_yaml_documents(&plain)?;
+    let instrumented_docs = parse_yaml_documents(&output)?;
+    assert_eq!(plain_docs, instrumented_docs);
+
+    let hpa_if = chart
+        .branches
+        .iter()
+        .find(|b| b.file == "templates/hpa.yaml" && b.index == 0)
+        .unwrap();
+    assert!(!hits.contains(&hpa_if.id), "autoscaling is disabled by default");
+    assert!(!hits.is_empty());
+
+    Ok(())
+}
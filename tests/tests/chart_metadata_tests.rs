@@ -0,0 +1,101 @@
+use anyhow::Result;
+use helm_tests::chart::*;
+use helm_tests::*;
+use std::path::Path;
+
+const CHARTS_DIR: &str = "../charts/";
+
+#[test]
+fn test_list_charts() -> Result<()> {
+    let charts = list_charts(CHARTS_DIR)?;
+    let names: Vec<&str> = charts.iter().map(|c| c.metadata.name.as_str()).collect();
+    assert_eq!(names, vec!["foundry", "life"]);
+
+    let life = &charts[1].metadata;
+    assert_eq!(life.api_version, "v2");
+    assert_eq!(life.semver()?.to_string(), "0.1.5");
+    assert!(life.is_application());
+
+    Ok(())
+}
+
+#[test]
+fn test_scaffold_leftovers_are_flagged() -> Result<()> {
+    let foundry = validate_chart_metadata("../charts/foundry")?;
+    assert!(foundry
+        .iter()
+        .any(|i| i.field == "description" && i.severity == Severity::Warning));
+    assert!(foundry.iter().any(|i| i.field == "appVersion" && i.message.contains("1.16.0")));
+
+    let life = validate_chart_metadata("../charts/life")?;
+    assert!(!life.iter().any(|i| i.field == "description"));
+    assert!(life.iter().any(|i| i.field == "appVersion" && i.message.contains("1.16.0")));
+
+    for chart in list_charts(CHARTS_DIR)? {
+        let errors: Vec<_> = chart
+            .metadata
+            .validate(&chart.path)
+            .into_iter()
+            .filter(|i| i.severity == Severity::Error)
+            .collect();
+        assert!(errors.is_empty(), "{}: {:?}", chart.metadata.name, errors);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_invalid_metadata_is_reported() -> Result<()> {
+    let metadata = ChartMetadata::parse(
+        r#"
+apiVersion: v1
+name: other
+version: 1.2
+appVersion: 2.0
+type: plugin
+kubeVersion: ">= banana"
+dependencies:
+  - name: postgresql
+    version: "~12.x"
+    repository: https://charts.bitnami.com/bitnami
+maintainers:
+  - email: someone@example.com
+"#,
+    )?;
+    assert_eq!(metadata.app_version.as_deref(), Some("2.0"));
+
+    let issues = metadata.validate(Path::new("../charts/life"));
+    let fields: Vec<&str> = issues
+        .iter()
+        .filter(|i| i.severity == Severity::Error)
+        .map(|i| i.field.as_str())
+        .collect();
+    assert_eq!(
+        fields,
+        vec!["apiVersion", "name", "version", "type", "kubeVersion", "maintainers[0]"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_kube_version_constraints() -> Result<()> {
+    let version = semver::Version::parse("1.30.2")?;
+    assert!(constraint_matches(">=1.19.0-0", &version)?);
+    assert!(constraint_matches(">= 1.19 < 1.31", &version)?);
+    assert!(constraint_matches("~1.20 || ^1.25", &version)?);
+    assert!(constraint_matches("1.28 - 1.30", &version)?);
+    assert!(!constraint_matches("<1.30", &version)?);
+    assert!(parse_version_constraint("").is_err());
+
+    // A bare version pins its given components, as in Masterminds, rather than acting as caret
+    assert!(!constraint_matches("1.28", &version)?);
+    assert!(!constraint_matches("v1.28", &version)?);
+    assert!(constraint_matches("1.30", &version)?);
+    assert!(constraint_matches("v1.30.2", &version)?);
+    assert!(constraint_matches(">= v1.29", &version)?);
+    assert!(constraint_matches("v1.28 - v1.30", &version)?);
+    assert!(constraint_matches("1.x", &version)?);
+
+    Ok(())
+}
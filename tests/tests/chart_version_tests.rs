@@ -0,0 +1,166 @@
+use anyhow::Result;
+use helm_tests::helmignore::HelmIgnore;
+use helm_tests::version_bump::*;
+use helm_tests::*;
+use std::fs;
+use std::path::Path;
+
+const CHARTS_DIR: &str = "../charts/";
+
+fn git(repo: &Path, args: &[&str]) -> Result<String> {
+    let mut full_args = vec!["-c", "user.name=test", "-c", "user.email=test@example.com"];
+    full_args.extend_from_slice(args);
+    run_git(&repo.to_string_lossy(), &full_args)
+}
+
+fn write_chart(repo: &Path, version: &str) -> Result<()> {
+    let chart_dir = repo.join("charts/demo");
+    fs::create_dir_all(chart_dir.join("templates"))?;
+    fs::write(
+        chart_dir.join("Chart.yaml"),
+        format!("apiVersion: v2\nname: demo\nversion: {}\n", version),
+    )?;
+    fs::write(chart_dir.join(".helmignore"), "*.bak\nci/\n")?;
+    Ok(())
+}
+
+#[test]
+fn test_helmignore_matches_like_helm() -> Result<()> {
+    let ignore = HelmIgnore::parse("# comment\n.git/\n*.swp\n/values-dev.yaml\nci/\n")?;
+
+    assert!(ignore.ignores(".git", true));
+    assert!(!ignore.ignores(".git", false));
+    assert!(ignore.ignores("templates/deployment.yaml.swp", false));
+    assert!(ignore.ignores("values-dev.yaml", false));
+    assert!(!ignore.is_packaged("ci/values.yaml"));
+    assert!(ignore.is_packaged("templates/deployment.yaml"));
+
+    Ok(())
+}
+
+#[test]
+fn test_changed_content_requires_version_bump() -> Result<()> {
+    let repo = tempfile::tempdir()?;
+    git(repo.path(), &["init", "-q"])?;
+    write_chart(repo.path(), "0.1.4")?;
+    fs::write(repo.path().join("charts/demo/templates/cm.yaml"), "kind: ConfigMap\n")?;
+    git(repo.path(), &["add", "-A"])?;
+    git(repo.path(), &["commit", "-q", "-m", "base"])?;
+    let chart = repo.path().join("charts/demo");
+    let chart = chart.to_string_lossy();
+
+    let check = check_chart_version_bump(&chart, "HEAD")?;
+    assert!(check.changed_files.is_empty());
+    assert!(!check.is_violation());
+
+    fs::write(repo.path().join("charts/demo/notes.bak"), "scratch\n")?;
+    fs::create_dir_all(repo.path().join("charts/demo/ci"))?;
+    fs::write(repo.path().join("charts/demo/ci/values.yaml"), "a: 1\n")?;
+    let check = check_chart_version_bump(&chart, "HEAD")?;
+    assert!(!check.is_violation(), "ignored files changed: {:?}", check.changed_files);
+
+    fs::write(repo.path().join("charts/demo/templates/cm.yaml"), "kind: Secret\n")?;
+    let check = check_chart_version_bump(&chart, "HEAD")?;
+    assert_eq!(check.changed_files, vec!["templates/cm.yaml"]);
+    assert!(check.is_violation());
+
+    write_chart(repo.path(), "0.1.5")?;
+    let check = check_chart_version_bump(&chart, "HEAD")?;
+    assert!(!check.is_violation());
+    assert_eq!(check.applied_bump(), Some(BumpLevel::Patch));
+    assert!(!check.satisfies(BumpLevel::Minor));
+
+    let mut check = check;
+    check.suggested_bump = Some(BumpLevel::Major);
+    assert!(check.is_violation());
+    assert_eq!(
+        check.to_string(),
+        "demo: 0.1.4 -> 0.1.5 (2 packaged file(s) changed), the render diff suggests a major bump"
+    );
+    write_chart(repo.path(), "0.2.0")?;
+    let mut check = check_chart_version_bump(&chart, "HEAD")?;
+    check.suggested_bump = Some(BumpLevel::Major);
+    assert!(!check.is_violation());
+
+    Ok(())
+}
+
+#[test]
+fn test_bump_suggestion_from_render_diff() -> Result<()> {
+    let old = parse_yaml_documents(
+        r#"
+kind: Deployment
+metadata:
+  name: rel-life-api
+spec:
+  selector:
+    matchLabels:
+      app: api
+---
+kind: Service
+metadata:
+  name: rel-life-api
+"#,
+    )?;
+
+    let added = parse_yaml_documents(
+        r#"
+kind: Deployment
+metadata:
+  name: rel-life-api
+spec:
+  selector:
+    matchLabels:
+      app: api
+---
+kind: Service
+metadata:
+  name: rel-life-api
+---
+kind: ConfigMap
+metadata:
+  name: rel-life-extra
+"#,
+    )?;
+    assert_eq!(suggest_bump_from_render(&old, &old), BumpLevel::Patch);
+    assert_eq!(suggest_bump_from_render(&old, &added), BumpLevel::Minor);
+    assert_eq!(suggest_bump_from_render(&added, &old), BumpLevel::Major);
+
+    let reselected = parse_yaml_documents(
+        r#"
+kind: Deployment
+metadata:
+  name: rel-life-api
+spec:
+  selector:
+    matchLabels:
+      app: backend
+---
+kind: Service
+metadata:
+  name: rel-life-api
+"#,
+    )?;
+    assert_eq!(suggest_bump_from_render(&old, &reselected), BumpLevel::Major);
+
+    Ok(())
+}
+
+#[test]
+fn test_repository_chart_versions_are_bumped() -> Result<()> {
+    let base_ref = std::env::var("CHART_BASE_REF").unwrap_or_else(|_| "HEAD".to_string());
+    let checks = check_all_chart_versions(CHARTS_DIR, &base_ref)?;
+
+    assert!(checks.iter().any(|c| c.chart == "life"));
+    for check in checks.iter().filter(|c| !c.changed_files.is_empty()) {
+        println!("{}", check);
+    }
+    let violations: Vec<String> = checks
+        .iter()
+        .filter(|c| c.is_violation())
+        .map(|c| c.to_string())
+        .collect();
+    assert!(violations.is_empty(), "charts changed without a large enough version bump: {:?}", violations);
+
+    Ok(())
+}
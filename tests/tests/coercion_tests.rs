@@ -0,0 +1,159 @@
+mod common;
+
+use anyhow::Result;
+use common::life_values;
+use helm_tests::coercion::*;
+use helm_tests::lineage::{Lineage, OutputField};
+use helm_tests::*;
+use std::collections::HashMap;
+
+const LIFE_CHART: &str = "../charts/life/";
+const FOUNDRY_CHART: &str = "../charts/foundry/";
+
+fn field(kind: &str, name: &str, field: &str) -> OutputField {
+    OutputField {
+        kind: kind.to_string(),
+        name: name.to_string(),
+        field: field.to_string(),
+    }
+}
+
+#[test]
+fn test_unquoted_values_file() -> Result<()> {
+    let mut intended = HashMap::new();
+    intended.insert("image.tag".to_string(), "1.10".to_string());
+    intended.insert("image.repository".to_string(), "example/app".to_string());
+    intended.insert("persistence.size".to_string(), "64Gi".to_string());
+    intended.insert("sender_id".to_string(), "123456789012".to_string());
+    assert_eq!(
+        unquoted_values_file(&intended)?,
+        "image:\n  repository: example/app\n  tag: 1.10\npersistence:\n  size: 64Gi\nsender_id: 123456789012\n"
+    );
+
+    intended.insert("tolerations[0].key".to_string(), "gpu".to_string());
+    assert!(unquoted_values_file(&intended).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_find_coercions() -> Result<()> {
+    let documents = parse_yaml_documents(
+        r#"
+apiVersion: v1
+kind: Secret
+metadata:
+  name: app
+  annotations:
+    example.com/build: 12
+data:
+  sender-id: MS4yMzQ1Njc4OTAxMmUrMTE=
+  region: ZXUtd2VzdA==
+  debug: VHJ1ZQ==
+  replicas: MTI=
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: app
+spec:
+  template:
+    spec:
+      containers:
+        - name: app
+          image: example/app:1.1
+"#,
+    )?;
+    let mut lineage = Lineage::default();
+    lineage.fields.insert("sender_id".to_string(), vec![field("Secret", "app", "data.sender-id")]);
+    lineage.fields.insert("region".to_string(), vec![field("Secret", "app", "data.region")]);
+    lineage.fields.insert(
+        "build".to_string(),
+        vec![field("Secret", "app", "metadata.annotations.example.com/build"), field("Secret", "app", "data.build")],
+    );
+    lineage.fields.insert("debug".to_string(), vec![field("Secret", "app", "data.debug")]);
+    lineage.fields.insert("replicas".to_string(), vec![field("Secret", "app", "data.replicas")]);
+    for path in ["sender_id", "region", "debug", "replicas"] {
+        let reached = lineage.fields_of(path)[0].clone();
+        lineage.whole.insert((path.to_string(), reached));
+    }
+    lineage.fields.insert(
+        "image.tag".to_string(),
+        vec![field("Deployment", "app", "spec.template.spec.containers[0].image")],
+    );
+
+    let mut intended = HashMap::new();
+    intended.insert("sender_id".to_string(), "123456789012".to_string());
+    intended.insert("region".to_string(), "eu-west".to_string());
+    intended.insert("build".to_string(), "0012".to_string());
+    intended.insert("image.tag".to_string(), "1.10".to_string());
+    intended.insert("debug".to_string(), "true".to_string());
+    intended.insert("replicas".to_string(), "2".to_string());
+
+    let issues: Vec<String> = find_coercions(&documents, &lineage, &intended).iter().map(|i| i.to_string()).collect();
+    assert_eq!(
+        issues,
+        vec![
+            "build=0012 renders as '12' in Secret/app.metadata.annotations.example.com/build",
+            "build=0012 removes Secret/app.data.build",
+            "debug=true renders as 'True' in Secret/app.data.debug",
+            "image.tag=1.10 renders as 'example/app:1.1' in Deployment/app.spec.template.spec.containers[0].image",
+            "replicas=2 renders as '12' in Secret/app.data.replicas",
+            "sender_id=123456789012 renders as '1.23456789012e+11' in Secret/app.data.sender-id",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_life_fixture_survives_set() -> Result<()> {
+    let mut values = life_values();
+    values.insert("firebase_messaging_sender_id".to_string(), "123456789012".to_string());
+    let issues: Vec<String> = check_coercion(LIFE_CHART, &values, ValuesSource::Set)?
+        .iter()
+        .map(|i| i.to_string())
+        .collect();
+    assert!(issues.is_empty(), "{}", issues.join("\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_life_large_sender_id_in_values_file() -> Result<()> {
+    let mut values = life_values();
+    values.insert("firebase_messaging_sender_id".to_string(), "123456789012".to_string());
+    let issues: Vec<String> = check_coercion(LIFE_CHART, &values, ValuesSource::ValuesFile)?
+        .iter()
+        .map(|i| i.to_string())
+        .collect();
+    assert_eq!(
+        issues,
+        vec![
+            "firebase_messaging_sender_id=123456789012 renders as '1.23456789012e+11' in Secret/test-release-simbruna-firebase-secrets.data.messaging-sender-id"
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_foundry_image_tag_in_values_file() -> Result<()> {
+    let mut values = HashMap::new();
+    values.insert("image.tag".to_string(), "1.10".to_string());
+    values.insert("persistence.size".to_string(), "64Gi".to_string());
+
+    let issues = check_coercion(FOUNDRY_CHART, &values, ValuesSource::Set)?;
+    assert!(issues.is_empty(), "{:?}", issues);
+
+    let issues: Vec<String> = check_coercion(FOUNDRY_CHART, &values, ValuesSource::ValuesFile)?
+        .iter()
+        .map(|i| i.to_string())
+        .collect();
+    assert_eq!(
+        issues,
+        vec!["image.tag=1.10 renders as 'luxusburg/docker-foundry:1.1' in Deployment/test-release-foundry.spec.template.spec.containers[0].image"]
+    );
+
+    Ok(())
+}
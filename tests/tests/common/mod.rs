@@ -0,0 +1,15 @@
+use std::collections::HashMap;
+
+/// The `--set` values the life chart needs to render
+pub fn life_values() -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    values.insert("firebase_api_key".to_string(), "test-api-key".to_string());
+    values.insert("firebase_auth_domain".to_string(), "test.firebaseapp.com".to_string());
+    values.insert("firebase_project_id".to_string(), "test-project".to_string());
+    values.insert("firebase_storage_bucket".to_string(), "test.appspot.com".to_string());
+    values.insert("firebase_messaging_sender_id".to_string(), "123456789".to_string());
+    values.insert("firebase_app_id".to_string(), "test-app-id".to_string());
+    values.insert("firebase_vapid_key".to_string(), "test-vapid-key".to_string());
+    values.insert("api_endpoint".to_string(), "https://api.test.com".to_string());
+    values
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use anyhow::Result;
+use common::life_values;
+use helm_tests::hooks::*;
+use helm_tests::*;
+
+const CHART_PATH: &str = "../charts/life/";
+
+const LIFE_LIKE_RENDER: &str = r#"
+apiVersion: v1
+kind: Secret
+metadata:
+  name: rel-life-pg-credentials
+  annotations:
+    "helm.sh/hook": pre-install,pre-upgrade
+    "helm.sh/hook-weight": "-10"
+---
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: rel-life-db-init
+  annotations:
+    "helm.sh/hook": pre-install,pre-upgrade
+    "helm.sh/hook-weight": "-10"
+    "helm.sh/hook-delete-policy": before-hook-creation,hook-succeeded
+---
+apiVersion: batch/v1
+kind: Job
+metadata:
+  name: rel-life-db-init
+  annotations:
+    "helm.sh/hook": pre-install,pre-upgrade
+    "helm.sh/hook-weight": "-5"
+    "helm.sh/hook-delete-policy": before-hook-creation,hook-succeeded
+spec:
+  template:
+    spec:
+      containers:
+      - name: db-init
+        env:
+        - name: APP_PASSWORD
+          valueFrom:
+            secretKeyRef:
+              name: rel-life-pg-credentials
+              key: app-password
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: rel-life-api
+spec:
+  template:
+    spec:
+      containers:
+      - name: api
+        env:
+        - name: POSTGRES_CONNECTION_STRING
+          valueFrom:
+            secretKeyRef:
+              name: rel-life-pg-credentials
+              key: connection-string
+---
+apiVersion: v1
+kind: Pod
+metadata:
+  name: rel-life-test-connection
+  annotations:
+    "helm.sh/hook": test
+"#;
+
+#[test]
+fn test_hooks_are_ordered_by_weight_then_name() -> Result<()> {
+    let documents = parse_yaml_documents(LIFE_LIKE_RENDER)?;
+    let steps = simulate_lifecycle(&documents, &[LifecycleEvent::Install]);
+
+    let created: Vec<String> = steps[0]
+        .actions
+        .iter()
+        .filter(|a| a.phase == "pre-install" && a.action == HookAction::Created)
+        .map(|a| format!("{}/{}", a.kind, a.name))
+        .collect();
+
+    assert_eq!(
+        created,
+        vec![
+            "ConfigMap/rel-life-db-init",
+            "Secret/rel-life-pg-credentials",
+            "Job/rel-life-db-init",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_lifecycle_existing_objects() -> Result<()> {
+    let documents = parse_yaml_documents(LIFE_LIKE_RENDER)?;
+    let steps = simulate_lifecycle(
+        &documents,
+        &[
+            LifecycleEvent::Install,
+            LifecycleEvent::Test,
+            LifecycleEvent::Upgrade,
+            LifecycleEvent::Rollback,
+            LifecycleEvent::Uninstall,
+        ],
+    );
+
+    let install = &steps[0];
+    assert!(install.exists("Secret", "rel-life-pg-credentials"));
+    assert!(install.exists("Deployment", "rel-life-api"));
+    assert!(!install.exists("Job", "rel-life-db-init"));
+    assert!(!install.exists("ConfigMap", "rel-life-db-init"));
+
+    assert!(steps[1].exists("Pod", "rel-life-test-connection"));
+
+    let upgrade = &steps[2];
+    assert!(upgrade
+        .actions
+        .iter()
+        .any(|a| a.name == "rel-life-pg-credentials" && a.action == HookAction::Deleted));
+
+    let uninstall = &steps[4];
+    assert!(!uninstall.exists("Deployment", "rel-life-api"));
+    assert!(uninstall.exists("Secret", "rel-life-pg-credentials"));
+
+    Ok(())
+}
+
+#[test]
+fn test_hook_dependencies_are_flagged() -> Result<()> {
+    let documents = parse_yaml_documents(LIFE_LIKE_RENDER)?;
+    let issues = check_hook_dependencies(&documents);
+
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].severity, Severity::Warning);
+    assert_eq!(issues[0].dependent, "Deployment/rel-life-api");
+    assert_eq!(issues[0].hook, "Secret/rel-life-pg-credentials");
+
+    Ok(())
+}
+
+#[test]
+fn test_life_chart_hook_dependencies() -> Result<()> {
+    let values = life_values();
+
+    let output = run_helm_template(CHART_PATH, Some(&values))?;
+    let documents = parse_yaml_documents(&output)?;
+    let issues = check_hook_dependencies(&documents);
+
+    assert!(issues
+        .iter()
+        .any(|i| i.hook == "Secret/test-release-life-pg-credentials" && i.dependent.contains("-api")));
+    assert!(issues.iter().all(|i| i.severity != Severity::Error), "{:?}", issues);
+
+    Ok(())
+}
@@ -0,0 +1,167 @@
+mod common;
+
+use anyhow::Result;
+use common::life_values;
+use helm_tests::lineage::*;
+use helm_tests::values_schema::sensitive_value_paths;
+use helm_tests::*;
+use std::collections::BTreeMap;
+
+const LIFE_CHART: &str = "../charts/life/";
+
+#[test]
+fn test_trace_canaries_and_leaks() -> Result<()> {
+    let canaries: BTreeMap<String, String> = [
+        ("api_key".to_string(), canary(0)),
+        ("owner".to_string(), canary(1)),
+        ("unused".to_string(), canary(2)),
+    ]
+    .into();
+    let documents = parse_yaml_documents(&format!(
+        r#"
+apiVersion: v1
+kind: Secret
+metadata:
+  name: app
+data:
+  api-key: {}
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: app
+  annotations:
+    owner: team-{}
+spec:
+  template:
+    spec:
+      containers:
+        - name: app
+          args: ["--api-key={}"]
+"#,
+        base64::Engine::encode(&base64::engine::general_purpose::STANDARD, canary(0)),
+        canary(1),
+        canary(0).to_uppercase(),
+    ))?;
+
+    let lineage = Lineage {
+        fields: trace_canaries(&documents, &canaries),
+        canaries,
+        ..Default::default()
+    };
+    let api_key: Vec<String> = lineage.fields_of("api_key").iter().map(|f| f.to_string()).collect();
+    assert_eq!(
+        api_key,
+        vec![
+            "Secret/app.data.api-key",
+            "Deployment/app.spec.template.spec.containers[0].args[0]",
+        ]
+    );
+    assert_eq!(lineage.unreached(), vec!["unused"]);
+
+    let leaks: Vec<String> = check_sensitive(&lineage, &["api_key".to_string()])
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+    assert_eq!(
+        leaks,
+        vec!["sensitive value api_key reaches Deployment/app.spec.template.spec.containers[0].args[0]"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_trace_boolean() -> Result<()> {
+    let render = |enabled: bool, replicas: u32| {
+        parse_yaml_documents(&format!(
+            r#"
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: app
+data:
+  FEATURE_FLAG: "{}"
+  PYTHON_FLAG: "{}"
+  DEBUG: "true"
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: app
+spec:
+  replicas: {}
+  template:
+    spec:
+      automountServiceAccountToken: {}
+"#,
+            enabled,
+            if enabled { "True" } else { "False" },
+            replicas,
+            enabled
+        ))
+    };
+
+    let fields: Vec<String> = trace_boolean(&render(true, 1)?, &render(false, 3)?, true)
+        .iter()
+        .map(|f| f.to_string())
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            "ConfigMap/app.data.FEATURE_FLAG",
+            "ConfigMap/app.data.PYTHON_FLAG",
+            "Deployment/app.spec.template.spec.automountServiceAccountToken",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_sensitive_marker() {
+    let paths = sensitive_value_paths(
+        r#"
+# @sensitive
+password: ""
+database:
+  host: db
+  # Password of the application user
+  # @sensitive
+  password: ""
+"#,
+    );
+    assert_eq!(
+        paths,
+        vec![vec!["database".to_string(), "password".to_string()], vec!["password".to_string()]]
+    );
+}
+
+#[test]
+fn test_life_lineage() -> Result<()> {
+    let lineage = chart_lineage(LIFE_CHART, Some(&life_values()))?;
+    assert!(
+        lineage
+            .fields_of("firebase_api_key")
+            .iter()
+            .any(|f| f.kind == "Secret" && f.name.ends_with("-firebase-secrets") && f.field == "data.api-key"),
+        "{:?}",
+        lineage.fields_of("firebase_api_key")
+    );
+    assert!(lineage.fields_of("api.image.repository").iter().any(|f| f.field.ends_with(".image")));
+
+    let mut sensitive = chart_sensitive_values(LIFE_CHART)?;
+    sensitive.extend(
+        [
+            "firebase_api_key",
+            "firebase_vapid_key",
+            "oauth_client_secret",
+            "postgres_connection_string",
+            "postgres_app_password",
+        ]
+        .map(String::from),
+    );
+    let leaks: Vec<String> = check_sensitive(&lineage, &sensitive).iter().map(|l| l.to_string()).collect();
+    assert!(leaks.is_empty(), "{}", leaks.join("\n"));
+
+    Ok(())
+}
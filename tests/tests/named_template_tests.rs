@@ -0,0 +1,105 @@
+use anyhow::Result;
+use helm_tests::named_template::*;
+
+const LIFE_CHART: &str = "../charts/life/";
+const FOUNDRY_CHART: &str = "../charts/foundry/";
+
+#[test]
+fn test_throwaway_chart_contains_only_helpers() -> Result<()> {
+    let context = TemplateContext {
+        chart_name: Some("renamed".to_string()),
+        app_version: Some("9.9.9".to_string()),
+        ..Default::default()
+    };
+    let chart = named_template_chart(LIFE_CHART, "simbruna.labels", &context)?;
+
+    let metadata = helm_tests::chart::ChartMetadata::load(&chart.path)?;
+    assert_eq!(metadata.name, "renamed");
+    assert_eq!(metadata.version, "0.1.5");
+    assert_eq!(metadata.app_version.as_deref(), Some("9.9.9"));
+
+    let templates = helm_tests::template::chart_template_files(&chart.path)?;
+    assert_eq!(
+        templates,
+        vec!["templates/_helpers.tpl", "templates/named-template-under-test.yaml"]
+    );
+    assert!(chart.path.join("values.yaml").is_file());
+
+    Ok(())
+}
+
+#[test]
+fn test_fullname_default_and_release_containing_chart_name() -> Result<()> {
+    let fullname = render_named_template(LIFE_CHART, "simbruna.fullname", &TemplateContext::default())?;
+    assert_eq!(fullname, "test-release-life");
+
+    let context = TemplateContext {
+        release_name: "life-prod".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(render_named_template(LIFE_CHART, "simbruna.fullname", &context)?, "life-prod");
+
+    let context = TemplateContext::with_values("fullnameOverride: custom-name")?;
+    assert_eq!(render_named_template(LIFE_CHART, "simbruna.fullname", &context)?, "custom-name");
+
+    Ok(())
+}
+
+#[test]
+fn test_fullname_truncation() -> Result<()> {
+    let mut context = TemplateContext::with_values("nameOverride: abcdefghijklmnopqrstuvwxyz")?;
+    context.release_name = "r".repeat(40);
+    let fullname = render_named_template(LIFE_CHART, "simbruna.fullname", &context)?;
+    assert_eq!(fullname, format!("{}-abcdefghijklmnopqrstuv", "r".repeat(40)));
+
+    // Truncating to 63 characters leaves a trailing '-' that must be trimmed
+    context.values = serde_yaml::from_str("nameOverride: abcdefghijklmnopqrstu-tail")?;
+    let fullname = render_named_template(LIFE_CHART, "simbruna.fullname", &context)?;
+    assert_eq!(fullname, format!("{}-abcdefghijklmnopqrstu", "r".repeat(40)));
+
+    let context = TemplateContext::with_values(&format!("fullnameOverride: {}-x", "a".repeat(62)))?;
+    let fullname = render_named_template(LIFE_CHART, "simbruna.fullname", &context)?;
+    assert_eq!(fullname, "a".repeat(62));
+
+    Ok(())
+}
+
+#[test]
+fn test_labels_use_chart_context() -> Result<()> {
+    let context = TemplateContext {
+        chart_version: Some("1.2.3+build".to_string()),
+        app_version: Some("9.9.9".to_string()),
+        ..Default::default()
+    };
+    let labels = render_named_template(LIFE_CHART, "simbruna.labels", &context)?;
+    assert_eq!(
+        labels,
+        "helm.sh/chart: life-1.2.3_build\n\
+         app.kubernetes.io/name: life\n\
+         app.kubernetes.io/instance: test-release\n\
+         app.kubernetes.io/version: \"9.9.9\"\n\
+         app.kubernetes.io/managed-by: Helm"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_foundry_service_account_name() -> Result<()> {
+    let created = render_named_template(FOUNDRY_CHART, "foundry.serviceAccountName", &TemplateContext::default())?;
+    assert_eq!(created, "test-release-foundry");
+
+    let context = TemplateContext::with_values("serviceAccount:\n  create: false")?;
+    assert_eq!(
+        render_named_template(FOUNDRY_CHART, "foundry.serviceAccountName", &context)?,
+        "default"
+    );
+
+    let context = TemplateContext::with_values("serviceAccount:\n  create: false\n  name: existing")?;
+    assert_eq!(
+        render_named_template(FOUNDRY_CHART, "foundry.serviceAccountName", &context)?,
+        "existing"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use anyhow::Result;
+use common::life_values;
+use helm_tests::pairwise::*;
+use helm_tests::*;
+use serde_yaml::Value;
+use std::collections::BTreeSet;
+
+const LIFE_CHART: &str = "../charts/life/";
+const FOUNDRY_CHART: &str = "../charts/foundry/";
+
+fn chart_factors(chart_path: &str) -> Result<Vec<Factor>> {
+    discover_factors(&std::fs::read_to_string(format!("{}values.yaml", chart_path))?)
+}
+
+/// Every Service must select the pods of some rendered workload
+fn services_select_pods(documents: &[Value]) -> Result<()> {
+    let pod_labels: Vec<&Value> = documents
+        .iter()
+        .filter_map(|doc| doc.get("spec")?.get("template")?.get("metadata")?.get("labels"))
+        .collect();
+    for service in find_resources_by_kind(documents, "Service") {
+        let Some(selector) = service
+            .get("spec")
+            .and_then(|s| s.get("selector"))
+            .and_then(|s| s.as_mapping())
+        else {
+            continue;
+        };
+        let matched = pod_labels
+            .iter()
+            .any(|labels| selector.iter().all(|(key, value)| labels.get(key) == Some(value)));
+        if !matched {
+            anyhow::bail!("Service {} selects no pods", resource_name(service).unwrap_or("?"));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_discover_toggles_and_enums() -> Result<()> {
+    let life: Vec<String> = chart_factors(LIFE_CHART)?.iter().map(|f| f.display_path()).collect();
+    for toggle in [
+        "api.ingress.enabled",
+        "frontend.ingress.enabled",
+        "autoscaling.enabled",
+        "serviceAccount.create",
+        "service.type",
+        "api.service.type",
+        "api.image.pullPolicy",
+    ] {
+        assert!(life.contains(&toggle.to_string()), "{} not discovered in {:?}", toggle, life);
+    }
+
+    let foundry = chart_factors(FOUNDRY_CHART)?;
+    let persistence = foundry
+        .iter()
+        .find(|f| f.display_path() == "persistence.enabled")
+        .expect("persistence.enabled should be discovered");
+    assert_eq!(persistence.levels, vec![Value::Bool(true), Value::Bool(false)]);
+
+    let pull_policy = foundry.iter().find(|f| f.display_path() == "image.pullPolicy").unwrap();
+    assert_eq!(pull_policy.levels[0], Value::from("IfNotPresent"));
+    assert_eq!(pull_policy.levels.len(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_enums_come_from_comments() -> Result<()> {
+    let factors = discover_factors(
+        r#"
+image:
+  # One of: Always, IfNotPresent, Never
+  pullPolicy: Never
+service:
+  type: ClusterIP
+  port: 80
+  exposed: false
+"#,
+    )?;
+    assert_eq!(
+        factors,
+        vec![
+            Factor::new("image.pullPolicy", vec!["Never".into(), "Always".into(), "IfNotPresent".into()]),
+            Factor::new("service.exposed", vec![Value::Bool(true), Value::Bool(false)]),
+        ]
+    );
+
+    assert_eq!(pairwise_combinations(&[]), vec![Vec::<usize>::new()]);
+    assert_eq!(combination_overlay(&[], &[]), Value::Mapping(Default::default()));
+
+    Ok(())
+}
+
+#[test]
+fn test_pairwise_rows_cover_every_pair() {
+    let mut factors: Vec<Factor> = (0..6)
+        .map(|i| Factor::new(&format!("toggle{}.enabled", i), vec![Value::Bool(true), Value::Bool(false)]))
+        .collect();
+    factors.push(Factor::new(
+        "service.type",
+        vec!["ClusterIP".into(), "NodePort".into(), "LoadBalancer".into()],
+    ));
+
+    let rows = pairwise_combinations(&factors);
+    assert!(rows.len() <= 12, "{} rows is not much better than exhaustive", rows.len());
+
+    let covered: BTreeSet<(usize, usize, usize, usize)> = rows
+        .iter()
+        .flat_map(|row| (0..row.len()).flat_map(move |i| (i + 1..row.len()).map(move |j| (i, row[i], j, row[j]))))
+        .collect();
+    for i in 0..factors.len() {
+        for j in i + 1..factors.len() {
+            for a in 0..factors[i].levels.len() {
+                for b in 0..factors[j].levels.len() {
+                    assert!(covered.contains(&(i, a, j, b)), "pair {:?} not covered", (i, a, j, b));
+                }
+            }
+        }
+    }
+
+    let overlay = combination_overlay(&factors[5..], &rows[0][5..]);
+    assert!(overlay["toggle5"]["enabled"].is_bool());
+    assert!(overlay["service"]["type"].is_string());
+}
+
+#[test]
+fn test_life_matrix_services_select_pods() -> Result<()> {
+    let factors = chart_factors(LIFE_CHART)?;
+    let failures = run_pairwise_matrix(LIFE_CHART, &factors, Some(&life_values()), services_select_pods)?;
+    let report: Vec<String> = failures.iter().map(|f| f.to_string()).collect();
+    assert!(failures.is_empty(), "{}", report.join("\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_foundry_matrix_services_select_pods() -> Result<()> {
+    let factors = chart_factors(FOUNDRY_CHART)?;
+    let failures = run_pairwise_matrix(FOUNDRY_CHART, &factors, None, services_select_pods)?;
+    let report: Vec<String> = failures.iter().map(|f| f.to_string()).collect();
+    assert!(failures.is_empty(), "{}", report.join("\n"));
+
+    Ok(())
+}
@@ -0,0 +1,169 @@
+mod common;
+
+use anyhow::Result;
+use common::life_values;
+use helm_tests::names::*;
+use helm_tests::*;
+use proptest::prelude::*;
+use std::collections::HashMap;
+
+const LIFE_CHART: &str = "../charts/life/";
+const FOUNDRY_CHART: &str = "../charts/foundry/";
+
+/// Release names helm accepts: at most 53 characters of dot-separated DNS-1123 labels
+fn release_name(chart_name: &'static str) -> impl Strategy<Value = String> {
+    let valid = |name: &String| name.len() <= 53 && check_dns1123_subdomain(name).is_ok();
+    prop_oneof![
+        "[a-z0-9]([-a-z0-9]{0,51}[a-z0-9])?",
+        "[a-z0-9]{40,53}",
+        proptest::string::string_regex(&format!(
+            "([a-z0-9][-a-z0-9]{{0,20}})?{}([-a-z0-9]{{0,20}}[a-z0-9])?",
+            chart_name
+        ))
+        .unwrap(),
+        "[a-z0-9]{1,20}\\.[a-z0-9]{1,20}",
+        "[0-9]{1,10}",
+    ]
+    .prop_filter("helm rejects the release name", valid)
+}
+
+fn name_override() -> impl Strategy<Value = Option<String>> {
+    proptest::option::of("[a-z0-9]([-a-z0-9]{0,70}[a-z0-9])?")
+}
+
+fn check_render(
+    chart_path: &str,
+    release: &str,
+    mut values: HashMap<String, String>,
+    name_override: Option<String>,
+    fullname_override: Option<String>,
+) -> Result<Vec<NameViolation>> {
+    if let Some(name) = name_override {
+        values.insert("nameOverride".to_string(), name);
+    }
+    if let Some(name) = fullname_override {
+        values.insert("fullnameOverride".to_string(), name);
+    }
+    let output = run_helm_template_with_release(chart_path, release, Some(&values))?;
+    Ok(check_rendered_names(&parse_yaml_documents(&output)?))
+}
+
+#[test]
+fn test_name_rules() {
+    assert!(check_dns1123_label(&"a".repeat(63)).is_ok());
+    assert!(check_dns1123_label(&"a".repeat(64)).is_err());
+    assert!(check_dns1123_label("release.life").is_err());
+    assert!(check_dns1035_label("1-release").is_err());
+    assert!(check_dns1123_subdomain("release.life-api").is_ok());
+    assert!(check_dns1123_subdomain("release.-life").is_err());
+    assert!(check_label_value("").is_ok());
+    assert!(check_label_value("v1.2_3").is_ok());
+    assert!(check_label_value("trailing-").is_err());
+    assert!(check_label_key("app.kubernetes.io/name").is_ok());
+    assert!(check_label_key("app.kubernetes.io/").is_err());
+    assert!(check_port_name("http").is_ok());
+    assert!(check_port_name("8080").is_err());
+    assert!(check_port_name("a-very-long-port-name").is_err());
+    assert!(check_resource_name("CronJob", &"a".repeat(53)).is_err());
+}
+
+#[test]
+fn test_long_release_name_violations_are_reported() -> Result<()> {
+    let fullname = format!("{}-life", "r".repeat(53));
+    let docs = parse_yaml_documents(&format!(
+        r#"
+apiVersion: v1
+kind: Service
+metadata:
+  name: {fullname}-frontend
+  labels:
+    app.kubernetes.io/instance: {fullname}-frontend
+spec:
+  ports:
+    - name: http_port
+      port: 80
+  selector:
+    app.kubernetes.io/name: life
+---
+apiVersion: v1
+kind: Secret
+metadata:
+  name: {fullname}-firebase-secrets
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: {fullname}-api
+spec:
+  template:
+    metadata:
+      labels:
+        app.kubernetes.io/name: life
+    spec:
+      containers:
+        - name: api
+          ports:
+            - name: metrics-endpoint
+              containerPort: 9090
+"#
+    ))?;
+
+    let fields: Vec<(String, String)> = check_rendered_names(&docs)
+        .into_iter()
+        .map(|v| (v.kind, v.field))
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            ("Service".to_string(), "metadata.name".to_string()),
+            (
+                "Service".to_string(),
+                "metadata.labels.app.kubernetes.io/instance".to_string()
+            ),
+            ("Service".to_string(), "spec.ports[0].name".to_string()),
+            ("Deployment".to_string(), "container api port name".to_string()),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_default_release_names_are_valid() -> Result<()> {
+    let violations = check_render(LIFE_CHART, "test-release", life_values(), None, None)?;
+    assert!(violations.is_empty(), "{:?}", violations);
+
+    let violations = check_render(FOUNDRY_CHART, "test-release", HashMap::new(), None, None)?;
+    assert!(violations.is_empty(), "{:?}", violations);
+
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    // Opt-in: renders the chart once per case. Run with `make test-names`.
+    #[test]
+    #[ignore]
+    fn prop_life_names_are_valid(
+        release in release_name("life"),
+        name in name_override(),
+        fullname in name_override(),
+    ) {
+        let violations = check_render(LIFE_CHART, &release, life_values(), name, fullname).unwrap();
+        let report: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+        prop_assert!(violations.is_empty(), "release '{}':\n{}", release, report.join("\n"));
+    }
+
+    #[test]
+    #[ignore]
+    fn prop_foundry_names_are_valid(
+        release in release_name("foundry"),
+        name in name_override(),
+        fullname in name_override(),
+    ) {
+        let violations = check_render(FOUNDRY_CHART, &release, HashMap::new(), name, fullname).unwrap();
+        let report: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+        prop_assert!(violations.is_empty(), "release '{}':\n{}", release, report.join("\n"));
+    }
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use anyhow::Result;
+use common::life_values;
+use helm_tests::required_values::*;
+use helm_tests::*;
+use std::collections::HashMap;
+
+const LIFE_CHART: &str = "../charts/life/";
+
+#[test]
+fn test_find_emptied_fields() -> Result<()> {
+    let present = parse_yaml_documents(
+        r#"
+apiVersion: v1
+kind: Secret
+metadata:
+  name: app
+data:
+  api-key: dGVzdA==
+  sender-id: MTIz
+  unchanged: ""
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: app
+spec:
+  template:
+    spec:
+      containers:
+        - name: app
+          image: "example/app:1.0"
+          env:
+            - name: ENDPOINT
+              value: https://api.test.com
+"#,
+    )?;
+    let absent = parse_yaml_documents(
+        r#"
+apiVersion: v1
+kind: Secret
+metadata:
+  name: app
+data:
+  api-key: ""
+  sender-id: PG5pbD4=
+  unchanged: ""
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: app
+spec:
+  template:
+    spec:
+      containers:
+        - name: app
+          image: "example/app:"
+          env:
+            - name: ENDPOINT
+              value:
+"#,
+    )?;
+
+    let fields: Vec<String> = find_emptied_fields(&present, &absent)
+        .iter()
+        .map(|f| format!("{}/{} {}", f.kind, f.name, f.field))
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            "Secret/app data.api-key",
+            "Secret/app data.sender-id",
+            "Deployment/app spec.template.spec.containers[0].env[0].value",
+            "Deployment/app spec.template.spec.containers[0].image",
+        ]
+    );
+    assert!(find_emptied_fields(&present, &present).is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_life_required_values() -> Result<()> {
+    let report = detect_required_values(LIFE_CHART, &life_values())?;
+    let keys = report.keys();
+
+    // `toString` turns a missing value into `<nil>`, and the `""` defaults render empty Secret data
+    for key in [
+        "firebase_messaging_sender_id",
+        "oauth_client_id",
+        "oauth_client_secret",
+        "postgres_connection_string",
+        "postgres_app_password",
+    ] {
+        assert!(keys.contains(&key), "{} not reported as required in {:?}", key, keys);
+    }
+
+    // Undeclared or deleted values reach `b64enc` as nil, so helm fails before anything renders empty
+    for key in [
+        "api_endpoint",
+        "firebase_api_key",
+        "firebase_auth_domain",
+        "firebase_project_id",
+        "firebase_storage_bucket",
+        "firebase_app_id",
+        "firebase_vapid_key",
+        "oauth_redirect_url",
+    ] {
+        assert!(report.enforced.iter().any(|k| k == key), "{} not reported as enforced in {:?}", key, report.enforced);
+        assert!(!keys.contains(&key), "{} reported as both required and enforced", key);
+    }
+
+    let json: serde_json::Value = serde_json::from_str(&report.to_json()?)?;
+    assert!(json["required"].as_array().is_some_and(|r| !r.is_empty()));
+
+    Ok(())
+}
+
+#[test]
+fn test_expect_render_error() -> Result<()> {
+    let chart = tempfile::tempdir()?;
+    std::fs::create_dir(chart.path().join("templates"))?;
+    std::fs::write(
+        chart.path().join("Chart.yaml"),
+        "apiVersion: v2\nname: required\nversion: 0.1.0\n",
+    )?;
+    std::fs::write(
+        chart.path().join("templates/secret.yaml"),
+        "apiVersion: v1\nkind: Secret\nmetadata:\n  name: app\nstringData:\n  token: {{ required \"token is required\" .Values.token | quote }}\n",
+    )?;
+    let chart_path = chart.path().to_str().unwrap();
+
+    assert!(!is_render_failure(&anyhow::Error::from(std::io::Error::from(
+        std::io::ErrorKind::NotFound
+    ))));
+    let error = expect_render_error(chart_path, None)?;
+    assert!(error.starts_with("Helm template failed"), "{}", error);
+    assert!(error.contains("token is required"), "{}", error);
+
+    let mut values = HashMap::new();
+    values.insert("token".to_string(), "secret".to_string());
+    assert!(expect_render_error(chart_path, Some(&values)).is_err());
+
+    Ok(())
+}
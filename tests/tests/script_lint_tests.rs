@@ -0,0 +1,137 @@
+mod common;
+
+use anyhow::Result;
+use common::life_values;
+use helm_tests::script_lint::*;
+use helm_tests::*;
+
+const LIFE_CHART: &str = "../charts/life/";
+
+fn job(script: &str) -> String {
+    let script: String = script.lines().map(|line| format!("              {}\n", line)).collect();
+    format!(
+        r#"
+apiVersion: batch/v1
+kind: Job
+metadata:
+  name: db-init
+spec:
+  template:
+    spec:
+      restartPolicy: OnFailure
+      containers:
+        - name: db-init
+          image: postgres:16
+          command:
+            - /bin/bash
+            - -c
+            - |
+{}          env:
+            - name: POSTGRES_HOST
+              value: db.example.com
+            - name: APP_PASSWORD
+              valueFrom:
+                secretKeyRef:
+                  name: pg-credentials
+                  key: app-password
+"#,
+        script
+    )
+}
+
+#[test]
+fn test_parse_shell_script() {
+    let script = parse_shell_script(
+        r#"export PGPASSWORD="$ADMIN" # "$COMMENTED"
+for table in $TABLES; do echo '$LITERAL'; done
+read -r answer
+cat <<'EOF'
+$QUOTED_HEREDOC
+EOF
+cat <<-SQL
+	SELECT '$NAME', ${COUNT:-0};
+	SQL
+echo "${#answer}" done
+"#,
+    );
+    let expansions: Vec<(&str, usize, ExpansionContext)> = script
+        .expansions
+        .iter()
+        .map(|e| (e.name.as_str(), e.line, e.context))
+        .collect();
+    assert_eq!(
+        expansions,
+        vec![
+            ("ADMIN", 1, ExpansionContext::DoubleQuoted { sql: false, nested_shell: false }),
+            ("TABLES", 2, ExpansionContext::Unquoted { assignment: false }),
+            ("NAME", 8, ExpansionContext::Heredoc { sql: true }),
+            ("COUNT", 8, ExpansionContext::Heredoc { sql: true }),
+            ("answer", 10, ExpansionContext::DoubleQuoted { sql: false, nested_shell: false }),
+        ]
+    );
+    assert!(script.expansions[3].has_default);
+    let definitions: Vec<&str> = script.definitions.iter().map(String::as_str).collect();
+    assert_eq!(definitions, vec!["PGPASSWORD", "answer", "table"]);
+}
+
+#[test]
+fn test_secret_interpolation_is_flagged() -> Result<()> {
+    let documents = parse_yaml_documents(&job(
+        r#"cat > /tmp/init.sql << EOF
+SELECT 'CREATE USER lifeapp';
+ALTER USER lifeapp WITH PASSWORD '$APP_PASSWORD';
+EOF
+psql -h "$POSTGRES_HOST" -c "ALTER USER lifeapp WITH PASSWORD '$APP_PASSWORD'"
+sh -c "echo $APP_PASSWORD > /tmp/password"
+echo $APP_PASSWORD | md5sum
+psql -h "$POSTGRES_HOST" -U "$POSTGRES_ADMIN_USER" -f /tmp/init.sql
+"#,
+    ))?;
+
+    let issues: Vec<String> = lint_container_scripts(&documents).iter().map(|i| i.to_string()).collect();
+    assert_eq!(
+        issues,
+        vec![
+            "[error] Job/db-init container 'db-init' line 3: $APP_PASSWORD (from Secret pg-credentials key app-password) is expanded into SQL in an unquoted heredoc; a value containing ' breaks or injects statements",
+            "[error] Job/db-init container 'db-init' line 5: $APP_PASSWORD (from Secret pg-credentials key app-password) is expanded into a SQL string; a value containing ' breaks or injects statements",
+            "[error] Job/db-init container 'db-init' line 6: $APP_PASSWORD (from Secret pg-credentials key app-password) is expanded into a command string that another shell parses",
+            "[warning] Job/db-init container 'db-init' line 7: $APP_PASSWORD (from Secret pg-credentials key app-password) is expanded without quotes, so word splitting and globbing apply",
+            "[error] Job/db-init container 'db-init' line 8: $POSTGRES_ADMIN_USER is not defined in the container env or by the script",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_safe_quoting_is_accepted() -> Result<()> {
+    let documents = parse_yaml_documents(&job(
+        r#"cat > /tmp/init.sql << 'EOF'
+ALTER USER lifeapp WITH PASSWORD :'app_password';
+EOF
+psql -h "$POSTGRES_HOST" -v app_password="$APP_PASSWORD" -f /tmp/init.sql
+"#,
+    ))?;
+    let issues: Vec<String> = lint_container_scripts(&documents).iter().map(|i| i.to_string()).collect();
+    assert!(issues.is_empty(), "{}", issues.join("\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_life_db_init_password_interpolation() -> Result<()> {
+    let documents = parse_yaml_documents(&run_helm_template(LIFE_CHART, Some(&life_values()))?)?;
+    let errors: Vec<String> = lint_container_scripts(&documents)
+        .iter()
+        .filter(|i| i.severity == Severity::Error)
+        .map(|i| i.to_string())
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            "[error] Job/test-release-simbruna-db-init container 'db-init' line 29: $APP_PASSWORD (from Secret test-release-simbruna-pg-credentials key app-password) is expanded into SQL in an unquoted heredoc; a value containing ' breaks or injects statements"
+        ]
+    );
+
+    Ok(())
+}
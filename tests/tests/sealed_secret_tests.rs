@@ -0,0 +1,152 @@
+mod common;
+
+use anyhow::Result;
+use common::life_values;
+use helm_tests::sealed_secret::*;
+use helm_tests::*;
+
+const LIFE_CHART: &str = "../charts/life/";
+const TEST_CERTIFICATE: &str = include_str!("fixtures/sealed-secrets-test.crt");
+const TEST_PRIVATE_KEY: &str = include_str!("fixtures/sealed-secrets-test.key");
+
+fn rename(sealed: &serde_yaml::Value, namespace: &str, name: &str) -> serde_yaml::Value {
+    let mut moved = sealed.clone();
+    moved["metadata"]["namespace"] = namespace.into();
+    moved["metadata"]["name"] = name.into();
+    moved
+}
+
+#[test]
+fn test_hybrid_encryption_layout() -> Result<()> {
+    let public = load_sealing_key(TEST_CERTIFICATE)?;
+    let private = load_unsealing_key(TEST_PRIVATE_KEY)?;
+
+    let ciphertext = hybrid_encrypt(&public, b"hunter2", "apps/db")?;
+    assert_eq!(u16::from_be_bytes([ciphertext[0], ciphertext[1]]), 256);
+    assert_eq!(ciphertext.len(), 2 + 256 + b"hunter2".len() + 16);
+    assert_eq!(hybrid_decrypt(&private, &ciphertext, "apps/db")?, b"hunter2");
+    assert!(hybrid_decrypt(&private, &ciphertext, "apps/db2").is_err());
+    assert!(hybrid_decrypt(&private, &ciphertext[..100], "apps/db").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_scopes_bind_name_and_namespace() -> Result<()> {
+    let public = load_sealing_key(TEST_CERTIFICATE)?;
+    let private = load_unsealing_key(TEST_PRIVATE_KEY)?;
+    let secret: serde_yaml::Value = serde_yaml::from_str(
+        "apiVersion: v1\nkind: Secret\nmetadata:\n  name: db\ntype: Opaque\nstringData:\n  password: hunter2\n",
+    )?;
+
+    let strict = seal_secret(&secret, "apps", SealingScope::Strict, &public)?;
+    assert_eq!(SealingScope::of(&strict), SealingScope::Strict);
+    assert!(unseal_secret(&strict, &private).is_ok());
+    assert!(unseal_secret(&rename(&strict, "apps", "db-copy"), &private).is_err());
+
+    let namespace_wide = seal_secret(&secret, "apps", SealingScope::NamespaceWide, &public)?;
+    assert_eq!(
+        resource_annotation(&namespace_wide, "sealedsecrets.bitnami.com/namespace-wide"),
+        Some("true")
+    );
+    assert!(unseal_secret(&rename(&namespace_wide, "apps", "db-copy"), &private).is_ok());
+    assert!(unseal_secret(&rename(&namespace_wide, "other", "db"), &private).is_err());
+
+    let cluster_wide = seal_secret(&secret, "apps", SealingScope::ClusterWide, &public)?;
+    assert_eq!(resource_annotation(&cluster_wide, "sealedsecrets.bitnami.com/cluster-wide"), Some("true"));
+    let unsealed = unseal_secret(&rename(&cluster_wide, "other", "db-copy"), &private)?;
+    assert_eq!(extract_secret_value(&unsealed, "password")?, "hunter2");
+
+    Ok(())
+}
+
+#[test]
+fn test_seal_rendered_secrets() -> Result<()> {
+    let public = load_sealing_key(TEST_CERTIFICATE)?;
+    let private = load_unsealing_key(TEST_PRIVATE_KEY)?;
+    let documents = parse_yaml_documents(
+        r#"
+apiVersion: v1
+kind: Secret
+metadata:
+  name: app-credentials
+  labels:
+    app.kubernetes.io/name: app
+  annotations:
+    kubectl.kubernetes.io/last-applied-configuration: '{"stringData":{"password":"hunter2"}}'
+type: Opaque
+data:
+  username: YWRtaW4=
+  password: b2xk
+stringData:
+  password: hunter2
+---
+apiVersion: v1
+kind: Secret
+metadata:
+  name: app-token
+  annotations:
+    kubernetes.io/service-account.name: app
+type: kubernetes.io/service-account-token
+---
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: app-config
+"#,
+    )?;
+
+    let sealed = seal_rendered_secrets(&documents, "apps", SealingScope::Strict, &public)?;
+    let kinds: Vec<&str> = sealed.iter().filter_map(resource_kind).collect();
+    assert_eq!(kinds, vec!["SealedSecret", "Secret", "ConfigMap"]);
+
+    let text = serde_yaml::to_string(&sealed[0])?;
+    assert!(!text.contains("hunter2") && !text.contains("YWRtaW4="), "{}", text);
+    assert_eq!(sealed[0]["metadata"]["namespace"].as_str(), Some("apps"));
+    assert_eq!(
+        sealed[0]["spec"]["template"]["metadata"]["labels"]["app.kubernetes.io/name"].as_str(),
+        Some("app")
+    );
+
+    let unsealed = unseal_secret(&sealed[0], &private)?;
+    assert_eq!(extract_secret_value(&unsealed, "username")?, "admin");
+    assert_eq!(extract_secret_value(&unsealed, "password")?, "hunter2");
+    assert_eq!(unsealed["type"].as_str(), Some("Opaque"));
+
+    Ok(())
+}
+
+#[test]
+fn test_life_secrets_round_trip_through_sealed_secrets() -> Result<()> {
+    let public = load_sealing_key(TEST_CERTIFICATE)?;
+    let private = load_unsealing_key(TEST_PRIVATE_KEY)?;
+    let mut values = life_values();
+    values.insert("oauth_client_secret".to_string(), "GOCSPX-test-client-secret".to_string());
+    values.insert("postgres_app_password".to_string(), "test-password".to_string());
+    let documents = parse_yaml_documents(&run_helm_template(LIFE_CHART, Some(&values))?)?;
+
+    let sealed = seal_rendered_secrets(&documents, "life", SealingScope::Strict, &public)?;
+    assert!(find_resources_by_kind(&sealed, "Secret").is_empty());
+    let sealed_names: Vec<&str> = find_resources_by_kind(&sealed, "SealedSecret")
+        .into_iter()
+        .filter_map(resource_name)
+        .collect();
+    assert_eq!(
+        sealed_names,
+        vec![
+            "test-release-simbruna-firebase-secrets",
+            "test-release-simbruna-oauth-secrets",
+            "test-release-simbruna-pg-credentials",
+        ]
+    );
+
+    for (original, sealed) in find_resources_by_kind(&documents, "Secret")
+        .into_iter()
+        .zip(find_resources_by_kind(&sealed, "SealedSecret"))
+    {
+        let unsealed = unseal_secret(sealed, &private)?;
+        assert_eq!(secret_data(&unsealed)?, secret_data(original)?, "{:?}", resource_name(original));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use anyhow::Result;
+use common::life_values;
+use helm_tests::sql_drift::*;
+use helm_tests::unused::check_unused_objects;
+use helm_tests::*;
+
+const LIFE_CHART: &str = "../charts/life/";
+
+fn statement(sql: &str) -> SqlItem {
+    SqlItem::Statement(sql.to_string())
+}
+
+#[test]
+fn test_parse_psql_script() {
+    let items = parse_psql_script(
+        r#"
+-- Create database if it doesn't exist
+SELECT 'CREATE DATABASE life'
+WHERE NOT EXISTS (SELECT FROM pg_database WHERE datname = 'life')\gexec
+
+\c life
+/* grants; idempotent */
+GRANT CONNECT ON DATABASE life TO lifeapp;
+CREATE FUNCTION touch() RETURNS void AS $body$ BEGIN; END; $body$ LANGUAGE plpgsql;
+ALTER USER lifeapp WITH PASSWORD 'semi;colon'
+"#,
+    );
+    assert_eq!(
+        items,
+        vec![
+            statement("SELECT 'CREATE DATABASE life' WHERE NOT EXISTS (SELECT FROM pg_database WHERE datname = 'life') \\gexec"),
+            SqlItem::MetaCommand("\\c life".to_string()),
+            statement("GRANT CONNECT ON DATABASE life TO lifeapp;"),
+            statement("CREATE FUNCTION touch() RETURNS void AS $body$ BEGIN; END; $body$ LANGUAGE plpgsql;"),
+            statement("ALTER USER lifeapp WITH PASSWORD 'semi;colon'"),
+        ]
+    );
+}
+
+#[test]
+fn test_diff_sql() {
+    let left = vec![statement("A;"), statement("B;"), statement("C;"), statement("D;")];
+    let right = vec![statement("A;"), statement("B2;"), statement("C;"), statement("E;")];
+    assert_eq!(
+        diff_sql(&left, &right),
+        vec![
+            SqlDifference::Changed(statement("B;"), statement("B2;")),
+            SqlDifference::Changed(statement("D;"), statement("E;")),
+        ]
+    );
+    assert_eq!(
+        diff_sql(&left[..2], &left),
+        vec![SqlDifference::OnlyInRight(statement("C;")), SqlDifference::OnlyInRight(statement("D;"))]
+    );
+}
+
+#[test]
+fn test_normalized_spellings_do_not_drift() {
+    let same = |a: &str, b: &str| statement(a).normalized() == statement(b).normalized();
+    assert!(same(
+        "GRANT ALL PRIVILEGES ON ALL TABLES IN SCHEMA public TO lifeapp;",
+        "grant all on all tables in schema \"public\" to LifeApp;"
+    ));
+    assert!(same("GRANT USAGE ON SCHEMA public TO lifeapp;", "GRANT USAGE ON SCHEMA public TO lifeapp ;"));
+    assert!(!same("GRANT USAGE ON SCHEMA public TO lifeapp;", "GRANT CREATE ON SCHEMA public TO lifeapp;"));
+    assert!(!same("CREATE TABLE \"Items\" (id int);", "CREATE TABLE items (id int);"));
+    assert!(!same("SELECT 'Life';", "SELECT 'life';"));
+    assert!(!same("SELECT 'life';", "SELECT 'life' \\gexec"));
+    assert!(matches!(
+        statement("GRANT USAGE ON SCHEMA public TO lifeapp;").normalized(),
+        NormalizedSql::Parsed(..)
+    ));
+
+    // sqlparser cannot parse these, so they are compared by their tokens
+    assert!(matches!(
+        statement("ALTER DEFAULT PRIVILEGES IN SCHEMA public GRANT ALL ON TABLES TO lifeapp;").normalized(),
+        NormalizedSql::Tokens(_)
+    ));
+    assert!(same(
+        "SELECT 'x' WHERE NOT EXISTS(SELECT FROM pg_database) \\gexec",
+        "select 'x'\nwhere not exists ( select from PG_DATABASE ) \\gexec"
+    ));
+    assert_eq!(
+        normalize_sql("grant connect on database \"life\" to LifeApp;"),
+        normalize_sql("GRANT CONNECT ON DATABASE life TO lifeapp ;")
+    );
+
+    let left = vec![statement("GRANT CONNECT ON DATABASE life TO lifeapp;"), statement("A;")];
+    let right = vec![statement("grant connect on database \"life\" to lifeapp;"), statement("B;")];
+    assert_eq!(diff_sql(&left, &right), vec![SqlDifference::Changed(statement("A;"), statement("B;"))]);
+}
+
+#[test]
+fn test_config_map_and_job_drift() -> Result<()> {
+    let documents = parse_yaml_documents(
+        r#"
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: db-init
+data:
+  init.sql: |
+    \c life
+    GRANT CONNECT ON DATABASE life TO lifeapp;
+    ALTER USER lifeapp WITH PASSWORD '\${APP_PASSWORD}';
+---
+apiVersion: batch/v1
+kind: Job
+metadata:
+  name: db-init
+spec:
+  template:
+    spec:
+      containers:
+        - name: db-init
+          image: postgres:16
+          command:
+            - /bin/bash
+            - -c
+            - |
+              cat > /tmp/init.sql << EOF
+              \c life
+              GRANT CONNECT ON DATABASE life TO lifeapp;
+              ALTER USER lifeapp WITH PASSWORD '$APP_PASSWORD';
+              EOF
+              psql -f /tmp/init.sql
+          volumeMounts:
+            - name: init-scripts
+              mountPath: /scripts
+      volumes:
+        - name: init-scripts
+          configMap:
+            name: db-init
+"#,
+    )?;
+
+    let issues: Vec<String> = check_sql_drift(&documents).iter().map(|i| i.to_string()).collect();
+    assert_eq!(
+        issues,
+        vec![
+            "[error] ConfigMap/db-init data init.sql: `ALTER USER lifeapp WITH PASSWORD '\\${APP_PASSWORD}';` sends $APP_PASSWORD to the server literally; nothing expands it",
+            "[warning] ConfigMap/db-init data init.sql vs Job/db-init container 'db-init' heredoc at line 2: `ALTER USER lifeapp WITH PASSWORD '\\${APP_PASSWORD}';` differs from `ALTER USER lifeapp WITH PASSWORD '$APP_PASSWORD';`",
+        ]
+    );
+    let unused: Vec<String> = check_unused_objects(&documents).iter().map(|i| i.to_string()).collect();
+    assert_eq!(
+        unused,
+        vec!["[info] Job/db-init: container 'db-init' mounts volume 'init-scripts' at /scripts, but its command and args never reference it"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_life_db_init_sql_drift() -> Result<()> {
+    let documents = parse_yaml_documents(&run_helm_template(LIFE_CHART, Some(&life_values()))?)?;
+    let issues: Vec<String> = check_sql_drift(&documents).iter().map(|i| i.to_string()).collect();
+    assert_eq!(issues.len(), 2, "{}", issues.join("\n"));
+    assert!(issues[0].contains("sends $APP_PASSWORD to the server literally"));
+    assert!(issues[1].contains("differs from `ALTER USER lifeapp WITH PASSWORD '$APP_PASSWORD';`"));
+
+    Ok(())
+}
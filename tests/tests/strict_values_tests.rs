@@ -0,0 +1,103 @@
+mod common;
+
+use anyhow::Result;
+use common::life_values;
+use helm_tests::set_key_path;
+use helm_tests::strict_values::*;
+use std::io::Write;
+
+const LIFE_CHART: &str = "../charts/life/";
+
+#[test]
+fn test_helpers() {
+    assert_eq!(edit_distance("oauth_clientid", "oauth_client_id"), 1);
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(edit_distance("", "abc"), 3);
+
+    assert_eq!(set_key_path("api.ingress.hosts[0].host"), vec!["api", "ingress", "hosts", "[]", "host"]);
+    assert_eq!(
+        set_key_path("podAnnotations.example\\.com/name"),
+        vec!["podAnnotations", "example.com/name"]
+    );
+
+    let overlay: serde_yaml::Value = serde_yaml::from_str("a:\n  b: 1\n  c: []\nd: [{e: 1}]").unwrap();
+    let paths: Vec<String> = overlay_key_paths(&overlay).iter().map(|p| p.join(".")).collect();
+    assert_eq!(paths, vec!["a.b", "a.c", "d.[].e"]);
+}
+
+#[test]
+fn test_life_accepts_declared_and_read_keys() -> Result<()> {
+    let strict = StrictValues::for_chart(LIFE_CHART)?;
+    assert!(strict.check_set_values(&life_values()).is_empty());
+
+    let overlay: serde_yaml::Value = serde_yaml::from_str(
+        r#"
+oauth_client_id: client
+podAnnotations:
+  prometheus.io/scrape: "true"
+api:
+  ingress:
+    annotations:
+      cert-manager.io/cluster-issuer: letsencrypt
+    hosts:
+      - host: api.example.com
+        paths:
+          - path: /
+            pathType: Prefix
+autoscaling:
+  targetMemoryUtilizationPercentage: 80
+"#,
+    )?;
+    let unknown = strict.check_overlay(&overlay, "overlay.yaml");
+    assert!(unknown.is_empty(), "{:?}", unknown);
+
+    Ok(())
+}
+
+#[test]
+fn test_typos_are_rejected_with_suggestions() -> Result<()> {
+    let strict = StrictValues::for_chart(LIFE_CHART)?;
+
+    let mut secrets = tempfile::Builder::new().suffix(".yaml").tempfile()?;
+    writeln!(secrets, "oauth_clientid: client\noauth_client_secret: secret\napi:\n  env:\n    FOO: bar")?;
+    let mut values = life_values();
+    values.insert("firebase_apikey".to_string(), "key".to_string());
+
+    let unknown = strict.check(&[secrets.path()], Some(&values))?;
+    let source = secrets.path().display().to_string();
+    assert_eq!(
+        unknown,
+        vec![
+            UnknownValue {
+                path: "api.env.FOO".to_string(),
+                source: source.clone(),
+                suggestion: None,
+            },
+            UnknownValue {
+                path: "oauth_clientid".to_string(),
+                source,
+                suggestion: Some("oauth_client_id".to_string()),
+            },
+            UnknownValue {
+                path: "firebase_apikey".to_string(),
+                source: "--set".to_string(),
+                suggestion: Some("firebase_api_key".to_string()),
+            },
+        ]
+    );
+    assert!(unknown[1].to_string().ends_with("did you mean 'oauth_client_id'?"));
+
+    let error = run_helm_template_strict(LIFE_CHART, &[secrets.path()], Some(&values)).unwrap_err();
+    assert!(error.to_string().starts_with("Strict values check failed:"));
+    assert!(error.to_string().contains("firebase_apikey (from --set)"));
+
+    Ok(())
+}
+
+#[test]
+fn test_strict_render_of_valid_values() -> Result<()> {
+    let output = run_helm_template_strict(LIFE_CHART, &[], Some(&life_values()))?;
+    assert!(output.contains("kind: Deployment"));
+
+    Ok(())
+}
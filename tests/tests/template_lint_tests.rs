@@ -0,0 +1,101 @@
+use anyhow::Result;
+use helm_tests::template::*;
+use helm_tests::template_lint::*;
+use helm_tests::*;
+use std::fs;
+
+#[test]
+fn test_parse_control_structures() -> Result<()> {
+    let nodes = parse_template(
+        r#"{{- define "demo.name" -}}
+{{- if .Values.a }}a{{- else if .Values.b }}b{{ else }}c{{ end }}
+{{- range $i, $host := .Values.hosts }}{{ $host | quote }}{{ end }}
+{{- end }}"#,
+    )?;
+
+    let Node::Define { name, body, .. } = &nodes[0] else {
+        panic!("expected define, got {:?}", nodes[0]);
+    };
+    assert_eq!(name, "demo.name");
+
+    let Node::Control { kind, else_body, .. } = &body[0] else {
+        panic!("expected if, got {:?}", body[0]);
+    };
+    assert_eq!(*kind, ControlKind::If);
+    let else_body = else_body.as_ref().unwrap();
+    assert!(matches!(&else_body[0], Node::Control { kind: ControlKind::If, else_body: Some(_), line: 2, .. }));
+
+    let Node::Control { kind, pipeline, line, .. } = &body[1] else {
+        panic!("expected range, got {:?}", body[1]);
+    };
+    assert_eq!(*kind, ControlKind::Range);
+    assert_eq!(*line, 3);
+    assert_eq!(pipeline.variables, vec!["$i", "$host"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_unbalanced_blocks_are_errors() {
+    let err = parse_template("{{ if .Values.a }}\nx\n").unwrap_err();
+    assert!(err.to_string().contains("line 1"), "{}", err);
+
+    let err = parse_template("a\n{{ end }}").unwrap_err();
+    assert!(err.to_string().contains("line 2: unexpected {{end}}"), "{}", err);
+}
+
+#[test]
+fn test_foundry_empty_with_block() -> Result<()> {
+    let findings = lint_chart_templates("../charts/foundry/")?;
+
+    assert_eq!(findings.len(), 1, "{:?}", findings);
+    assert_eq!(findings[0].rule, "empty-block");
+    assert_eq!(findings[0].file, "templates/deployment.yaml");
+    assert_eq!(findings[0].line, 61);
+    assert_eq!(findings[0].message, "{{with}} block has an empty body");
+
+    Ok(())
+}
+
+#[test]
+fn test_life_templates_are_clean() -> Result<()> {
+    let findings = lint_chart_templates("../charts/life/")?;
+    assert!(findings.is_empty(), "{:?}", findings);
+    Ok(())
+}
+
+#[test]
+fn test_lint_rules_on_synthetic_chart() -> Result<()> {
+    let chart = tempfile::tempdir()?;
+    let templates = chart.path().join("templates");
+    fs::create_dir_all(&templates)?;
+    fs::write(
+        templates.join("_helpers.tpl"),
+        "{{- define \"demo.labels\" -}}\napp: demo\n{{- end }}\n{{- define \"demo.unused\" -}}\nx\n{{- end }}\n",
+    )?;
+    fs::write(
+        templates.join("configmap.yaml"),
+        "kind: ConfigMap\nmetadata:\n  name: {{ include \"demo.fullname\" . }}\n",
+    )?;
+    fs::write(templates.join("broken.yaml"), "kind: Secret\n{{- with .Values.x }}\n")?;
+
+    let findings = lint_chart_templates(&chart.path().to_string_lossy())?;
+    let rules: Vec<(&str, &str, usize)> = findings
+        .iter()
+        .map(|f| (f.rule, f.file.as_str(), f.line))
+        .collect();
+
+    assert_eq!(
+        rules,
+        vec![
+            ("unused-define", "templates/_helpers.tpl", 1),
+            ("unused-define", "templates/_helpers.tpl", 4),
+            ("parse", "templates/broken.yaml", 2),
+            ("missing-labels", "templates/configmap.yaml", 1),
+            ("undefined-template", "templates/configmap.yaml", 3),
+        ]
+    );
+    assert!(findings.iter().all(|f| f.severity != Severity::Info));
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use anyhow::Result;
+use common::life_values;
+use helm_tests::unused::*;
+use helm_tests::*;
+
+const LIFE_CHART: &str = "../charts/life/";
+const FOUNDRY_CHART: &str = "../charts/foundry/";
+
+#[test]
+fn test_unused_volumes_mounts_and_objects() -> Result<()> {
+    let documents = parse_yaml_documents(
+        r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: app
+spec:
+  template:
+    spec:
+      containers:
+        - name: app
+          image: example/app:1.0
+          command: ["/app/server", "--config", "/etc/app/config.yaml"]
+          envFrom:
+            - secretRef:
+                name: app-env
+          volumeMounts:
+            - name: config
+              mountPath: /etc/app
+            - name: cache
+              mountPath: /var/cache/app
+            - name: scratch
+              mountPath: /tmp
+      volumes:
+        - name: config
+          configMap:
+            name: app-config
+        - name: cache
+          emptyDir: {}
+        - name: certs
+          projected:
+            sources:
+              - secret:
+                  name: app-certs
+---
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: app-config
+---
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: leftover
+---
+apiVersion: v1
+kind: Secret
+metadata:
+  name: app-env
+---
+apiVersion: v1
+kind: Secret
+metadata:
+  name: app-certs
+---
+apiVersion: v1
+kind: Secret
+metadata:
+  name: app-tls
+type: kubernetes.io/tls
+---
+apiVersion: networking.k8s.io/v1
+kind: Ingress
+metadata:
+  name: app
+spec:
+  tls:
+    - hosts: [app.example.com]
+      secretName: app-tls
+"#,
+    )?;
+
+    let issues: Vec<String> = check_unused_objects(&documents).iter().map(|i| i.to_string()).collect();
+    assert_eq!(
+        issues,
+        vec![
+            "[error] Deployment/app: container 'app' mounts volume 'scratch', which the pod does not define",
+            "[info] Deployment/app: container 'app' mounts volume 'cache' at /var/cache/app, but its command and args never reference it",
+            "[info] Deployment/app: container 'app' mounts volume 'scratch' at /tmp, but its command and args never reference it",
+            "[warning] Deployment/app: volume 'certs' is not mounted by any container",
+            "[warning] ConfigMap/leftover: no workload consumes it through a volume, env, envFrom or projected source",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_life_only_flags_unread_db_init_mount() -> Result<()> {
+    let documents = parse_yaml_documents(&run_helm_template(LIFE_CHART, Some(&life_values()))?)?;
+    let issues: Vec<String> = check_unused_objects(&documents).iter().map(|i| i.to_string()).collect();
+    assert_eq!(
+        issues,
+        vec![
+            "[info] Job/test-release-simbruna-db-init: container 'db-init' mounts volume 'init-scripts' at /scripts, but its command and args never reference it"
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_foundry_has_no_unused_objects() -> Result<()> {
+    let documents = parse_yaml_documents(&run_helm_template(FOUNDRY_CHART, None)?)?;
+    let issues: Vec<String> = check_unused_objects(&documents)
+        .iter()
+        .filter(|i| i.severity > Severity::Info)
+        .map(|i| i.to_string())
+        .collect();
+    assert!(issues.is_empty(), "{}", issues.join("\n"));
+
+    Ok(())
+}
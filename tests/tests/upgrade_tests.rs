@@ -0,0 +1,209 @@
+mod common;
+
+use anyhow::Result;
+use common::life_values;
+use helm_tests::upgrade::*;
+use helm_tests::*;
+
+const CHART_PATH: &str = "../charts/life/";
+
+#[test]
+fn test_upgrade_from_head_has_no_breaking_changes() -> Result<()> {
+    let values = life_values();
+    let issues = simulate_upgrade(
+        CHART_PATH,
+        &PreviousChart::GitRef("HEAD".to_string()),
+        Some(&values),
+    )?;
+
+    let errors: Vec<_> = issues.iter().filter(|i| i.severity == Severity::Error).collect();
+    assert!(errors.is_empty(), "unexpected upgrade errors: {:?}", errors);
+
+    Ok(())
+}
+
+#[test]
+fn test_selector_change_is_breaking() -> Result<()> {
+    let old = parse_yaml_documents(
+        r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: rel-life-api
+spec:
+  selector:
+    matchLabels:
+      app.kubernetes.io/name: life
+"#,
+    )?;
+    let new = parse_yaml_documents(
+        r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: rel-life-api
+spec:
+  selector:
+    matchLabels:
+      app.kubernetes.io/name: life
+      app.kubernetes.io/component: api
+"#,
+    )?;
+
+    let issues = diff_immutable_fields(&old, &new);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].severity, Severity::Error);
+    assert!(issues[0].message.contains("spec.selector"));
+
+    Ok(())
+}
+
+#[test]
+fn test_hook_job_template_change_is_recreated() -> Result<()> {
+    let job = |image: &str, annotations: &str| {
+        format!(
+            r#"
+apiVersion: batch/v1
+kind: Job
+metadata:
+  name: rel-life-db-init
+  annotations:
+    {}
+spec:
+  template:
+    spec:
+      containers:
+      - name: db-init
+        image: {}
+"#,
+            annotations, image
+        )
+    };
+    let messages = |annotations: &str| -> Result<Vec<String>> {
+        let old = parse_yaml_documents(&job("postgres:15", annotations))?;
+        let new = parse_yaml_documents(&job("postgres:16", annotations))?;
+        Ok(diff_immutable_fields(&old, &new).iter().map(|i| i.to_string()).collect())
+    };
+
+    let recreated = "[info] Job/rel-life-db-init: spec.template changed; the hook Job is deleted and \
+                     re-created by before-hook-creation";
+    assert_eq!(
+        messages(
+            r#""helm.sh/hook": pre-install,pre-upgrade
+    "helm.sh/hook-delete-policy": before-hook-creation,hook-succeeded"#
+        )?,
+        vec![recreated]
+    );
+    // Without a delete policy Helm defaults to before-hook-creation
+    assert_eq!(messages(r#""helm.sh/hook": pre-install,pre-upgrade"#)?, vec![recreated]);
+    assert_eq!(
+        messages(
+            r#""helm.sh/hook": pre-install,pre-upgrade
+    "helm.sh/hook-delete-policy": hook-succeeded"#
+        )?,
+        vec!["[info] Job/rel-life-db-init: spec.template changed; hook Jobs are created on each run, not patched"]
+    );
+    assert_eq!(
+        messages(r#"example.com/owner: life"#)?,
+        vec!["[error] Job/rel-life-db-init: spec.template is immutable on Jobs; the upgrade will be rejected"]
+    );
+
+    let old = parse_yaml_documents(
+        "apiVersion: batch/v1\nkind: Job\nmetadata:\n  name: migrate\nspec:\n  selector:\n    matchLabels:\n      a: b\n",
+    )?;
+    let new = parse_yaml_documents(
+        "apiVersion: batch/v1\nkind: Job\nmetadata:\n  name: migrate\nspec:\n  selector:\n    matchLabels:\n      a: c\n",
+    )?;
+    let issues = diff_immutable_fields(&old, &new);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].message, "spec.selector is immutable on Jobs; the upgrade will be rejected");
+
+    Ok(())
+}
+
+#[test]
+fn test_pvc_and_service_changes() -> Result<()> {
+    let old = parse_yaml_documents(
+        r#"
+apiVersion: v1
+kind: PersistentVolumeClaim
+metadata:
+  name: rel-foundry
+spec:
+  accessModes: [ReadWriteOnce]
+  storageClassName: fast
+  resources:
+    requests:
+      storage: 64Gi
+---
+apiVersion: v1
+kind: Service
+metadata:
+  name: rel-foundry
+spec:
+  type: LoadBalancer
+  clusterIP: 10.0.0.10
+"#,
+    )?;
+    let new = parse_yaml_documents(
+        r#"
+apiVersion: v1
+kind: PersistentVolumeClaim
+metadata:
+  name: rel-foundry
+spec:
+  accessModes: [ReadWriteOnce]
+  storageClassName: slow
+  resources:
+    requests:
+      storage: 128Gi
+---
+apiVersion: v1
+kind: Service
+metadata:
+  name: rel-foundry
+spec:
+  type: ClusterIP
+  clusterIP: 10.0.0.11
+"#,
+    )?;
+
+    let issues = diff_immutable_fields(&old, &new);
+    let messages: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+    assert!(messages.iter().any(|m| m.contains("storageClassName")), "{:?}", messages);
+    assert!(messages.iter().any(|m| m.contains("allowVolumeExpansion")), "{:?}", messages);
+    assert!(messages.iter().any(|m| m.contains("clusterIP")), "{:?}", messages);
+    assert!(messages.iter().any(|m| m.contains("LoadBalancer to ClusterIP")), "{:?}", messages);
+
+    Ok(())
+}
+
+#[test]
+fn test_renamed_resource_is_reported() -> Result<()> {
+    let old = parse_yaml_documents(
+        r#"
+apiVersion: v1
+kind: Secret
+metadata:
+  name: rel-life-oauth
+  labels:
+    app.kubernetes.io/component: oauth
+"#,
+    )?;
+    let new = parse_yaml_documents(
+        r#"
+apiVersion: v1
+kind: Secret
+metadata:
+  name: rel-life-oauth-secrets
+  labels:
+    app.kubernetes.io/component: oauth
+"#,
+    )?;
+
+    let issues = diff_immutable_fields(&old, &new);
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].message.contains("renamed to 'rel-life-oauth-secrets'"));
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use anyhow::Result;
+use common::life_values;
+use helm_tests::value_format::*;
+use helm_tests::*;
+
+const LIFE_CHART: &str = "../charts/life/";
+
+const VAPID_KEY: &str =
+    "BOr8c3xGq0a7s1YpT4ZkE9u2Wm6Lh5Nd0Vb3Jc8Fg1Rt7Ky2Xw4Qe6Ui9Op0As3Df5Gh8Jk1Lz4Xc7Vb0Nm2Qw5";
+
+#[test]
+fn test_patterns_match_in_full() -> Result<()> {
+    assert!(matches_pattern(r"1:\d+:web:[0-9a-f]+", "1:123456789:web:0a1b2c3d")?);
+    assert!(!matches_pattern(r"1:\d+:web:[0-9a-f]+", "x1:123456789:web:0a1b2c3d")?);
+    assert!(matches_pattern("web|ios", "ios")?);
+    assert!(!matches_pattern("web|ios", "webapp")?);
+    assert!(matches_pattern("[a-", "a").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_value_formats() -> Result<()> {
+    assert_eq!(ValueFormat::Numeric.problem("1.23456789012e+11")?, Some("contains the non-digit '.'".to_string()));
+    assert_eq!(ValueFormat::Numeric.problem("0012")?, None);
+    assert_eq!(ValueFormat::Base64Url(87).problem(VAPID_KEY)?, None);
+    assert_eq!(
+        ValueFormat::Base64Url(87).problem(&VAPID_KEY[1..])?,
+        Some("has 86 characters, not 87".to_string())
+    );
+    assert_eq!(
+        ValueFormat::Base64Url(4).problem("ab+/")?,
+        Some("contains '+', which is not a base64url character".to_string())
+    );
+    assert_eq!(ValueFormat::Hostname.problem("life.firebaseapp.com")?, None);
+    assert_eq!(
+        ValueFormat::Hostname.problem("life-.firebaseapp.com")?,
+        Some("has the label 'life-', which starts or ends with '-'".to_string())
+    );
+    assert_eq!(ValueFormat::Hostname.problem("https://life.com")?, Some("contains ':'".to_string()));
+    let https = ValueFormat::UrlScheme("https".to_string());
+    assert_eq!(https.problem("https://simbru.ryougi.ca/auth/callback")?, None);
+    assert_eq!(
+        https.problem("http://simbru.ryougi.ca/auth/callback")?,
+        Some("uses the http scheme, not https".to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_format_rules_on_synthetic_render() -> Result<()> {
+    let documents = parse_yaml_documents(
+        r#"
+apiVersion: v1
+kind: Secret
+metadata:
+  name: demo-firebase-secrets
+data:
+  messaging-sender-id: MS4yMzQ1Njc4OTAxMmUrMTE=
+  app-id: ""
+stringData:
+  auth-domain: demo.firebaseapp.com
+"#,
+    )?;
+    let rules = vec![
+        FormatRule::new("-firebase-secrets", "messaging-sender-id", ValueFormat::Numeric),
+        FormatRule::new("-firebase-secrets", "app-id", ValueFormat::Numeric),
+        FormatRule::new("-firebase-secrets", "auth-domain", ValueFormat::Hostname),
+        FormatRule::new("-oauth-secrets", "redirect-url", ValueFormat::UrlScheme("https".to_string())),
+    ];
+    let violations: Vec<String> = check_value_formats(&documents, &rules).iter().map(|v| v.to_string()).collect();
+    assert_eq!(
+        violations,
+        vec![
+            "*-firebase-secrets/messaging-sender-id is numeric: Secret demo-firebase-secrets key messaging-sender-id value '1.23****' contains the non-digit '.'",
+            "*-oauth-secrets/redirect-url is a https URL: no Secret named *-oauth-secrets with key redirect-url in the render",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_life_valid_values_pass() -> Result<()> {
+    let mut values = life_values();
+    values.insert("firebase_app_id".to_string(), "1:123456789:web:0a1b2c3d4e5f".to_string());
+    values.insert("firebase_vapid_key".to_string(), VAPID_KEY.to_string());
+    let documents = parse_yaml_documents(&run_helm_template(LIFE_CHART, Some(&values))?)?;
+    let violations: Vec<String> = check_value_formats(&documents, &life_format_rules())
+        .iter()
+        .map(|v| v.to_string())
+        .collect();
+    assert!(violations.is_empty(), "{}", violations.join("\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_life_fixture_placeholders_are_flagged() -> Result<()> {
+    let mut values = life_values();
+    values.insert("oauth_redirect_url".to_string(), "http://simbru.ryougi.ca/auth/callback".to_string());
+    let documents = parse_yaml_documents(&run_helm_template(LIFE_CHART, Some(&values))?)?;
+    let violations: Vec<String> = check_value_formats(&documents, &life_format_rules())
+        .iter()
+        .map(|v| v.to_string())
+        .collect();
+    assert_eq!(
+        violations,
+        vec![
+            "*-firebase-secrets/app-id matches `1:\\d+:web:[0-9a-f]+`: Secret test-release-simbruna-firebase-secrets key app-id value 'test****' does not match `1:\\d+:web:[0-9a-f]+`",
+            "*-firebase-secrets/vapid-key is 87-character base64url: Secret test-release-simbruna-firebase-secrets key vapid-key value 'test****' has 14 characters, not 87",
+            "*-oauth-secrets/redirect-url is a https URL: Secret test-release-simbruna-oauth-secrets key redirect-url value 'http****' uses the http scheme, not https",
+        ]
+    );
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use anyhow::Result;
+use helm_tests::values_coverage::*;
+
+#[test]
+fn test_life_dead_and_undeclared_values() -> Result<()> {
+    let coverage = analyze_values_coverage("../charts/life/")?;
+
+    for dead in ["api.env", "livenessProbe", "readinessProbe"] {
+        assert!(coverage.dead.contains(&dead.to_string()), "{} not dead: {:?}", dead, coverage.dead);
+    }
+    assert!(coverage.notes_only.contains(&"ingress".to_string()));
+    assert!(coverage.notes_only.contains(&"service.type".to_string()));
+    assert!(!coverage.dead.iter().any(|d| d.starts_with("frontend")));
+
+    let undeclared: Vec<String> = coverage.undeclared.iter().map(|r| r.display_path()).collect();
+    assert!(undeclared.contains(&"firebase_api_key".to_string()));
+    assert!(undeclared.contains(&"frontend.ingress.annotations".to_string()));
+    assert!(!undeclared.contains(&"oauth_client_id".to_string()));
+
+    Ok(())
+}
+
+#[test]
+fn test_foundry_probes_are_undeclared() -> Result<()> {
+    let coverage = analyze_values_coverage("../charts/foundry/")?;
+
+    let probe = coverage
+        .undeclared
+        .iter()
+        .find(|r| r.display_path() == "livenessProbe")
+        .expect("livenessProbe should be undeclared");
+    assert_eq!(probe.file, "templates/deployment.yaml");
+    assert!(coverage.undeclared.iter().any(|r| r.display_path() == "readinessProbe"));
+
+    Ok(())
+}
+
+#[test]
+fn test_with_and_range_scoping() -> Result<()> {
+    let values: serde_yaml::Value = serde_yaml::from_str(
+        r#"
+ingress:
+  annotations: {}
+  hosts:
+    - host: example.local
+      paths:
+        - path: /
+labels:
+  team: web
+"#,
+    )?;
+    let declared = declared_values(&values);
+
+    let template = r#"
+{{- with .Values.ingress.annotations }}
+annotations: {{- toYaml . | nindent 2 }}
+{{- end }}
+{{- range $host := .Values.ingress.hosts }}
+- host: {{ $host.host }}
+  {{- range .paths }}
+  path: {{ .path }} {{ $.Values.ingress.className }}
+  {{- end }}
+{{- end }}
+{{- range $key, $value := .Values.labels }}
+{{ $key }}: {{ $value }}
+{{- end }}
+"#;
+    let references = extract_value_references(template, "t.yaml", &declared)?;
+    let paths: Vec<String> = references.iter().map(|r| r.display_path()).collect();
+
+    assert_eq!(
+        paths,
+        vec![
+            "ingress.annotations",
+            "ingress.annotations",
+            "ingress.hosts",
+            "ingress.hosts[].host",
+            "ingress.hosts[].paths",
+            "ingress.hosts[].paths[].path",
+            "ingress.className",
+            "labels",
+            "labels.*",
+        ]
+    );
+    assert_eq!(references[3].line, 6);
+
+    Ok(())
+}
@@ -0,0 +1,199 @@
+mod common;
+
+use anyhow::Result;
+use common::life_values;
+use helm_tests::values_fuzz::*;
+use helm_tests::*;
+use proptest::prelude::*;
+use serde_yaml::Value;
+use std::collections::HashMap;
+
+const LIFE_CHART: &str = "../charts/life/";
+const FOUNDRY_CHART: &str = "../charts/foundry/";
+/// The Postgres Secret life's db-init Job reads is created by the database release, not the chart
+const LIFE_EXTERNAL: &[&str] = &["app-postgres-postgresql"];
+
+fn chart_values(chart_path: &str) -> Value {
+    let content = std::fs::read_to_string(format!("{}values.yaml", chart_path)).unwrap();
+    serde_yaml::from_str(&content).unwrap()
+}
+
+/// One to three mutations of a chart's values.yaml; proptest shrinks failures to the fewest and earliest
+fn mutation_sets(base: &Value) -> impl Strategy<Value = Vec<ValueMutation>> {
+    prop::collection::vec(prop::sample::select(candidate_mutations(base)), 1..=3)
+}
+
+fn assert_not_broken(
+    chart_path: &str,
+    base: &Value,
+    mutations: &[ValueMutation],
+    values: Option<&HashMap<String, String>>,
+    external: &[&str],
+) -> Result<(), TestCaseError> {
+    let outcome = fuzz_render(chart_path, base, mutations, values, external)
+        .map_err(|e| TestCaseError::fail(e.to_string()))?;
+    if let FuzzOutcome::Broken(problems) = outcome {
+        let overlay = serde_yaml::to_string(&mutations_overlay(base, mutations)).unwrap();
+        return Err(TestCaseError::fail(format!(
+            "overlay reproducing the failure:\n{}\n{}",
+            overlay,
+            problems.join("\n")
+        )));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_candidate_mutations_preserve_types() -> Result<()> {
+    let base: Value = serde_yaml::from_str(
+        r#"
+enabled: true
+replicas: 2
+image:
+  tag: "1.0"
+hosts: [a.example.com]
+"#,
+    )?;
+    let mutations = candidate_mutations(&base);
+
+    assert_eq!(mutations[0], ValueMutation::Remove(vec!["enabled".to_string()]));
+    assert_eq!(mutations[1], ValueMutation::ToggleBool(vec!["enabled".to_string()]));
+    assert!(mutations.contains(&ValueMutation::ReplaceNumber(vec!["replicas".to_string()], -1.0)));
+    assert!(mutations.contains(&ValueMutation::ReplaceString(
+        vec!["image".to_string(), "tag".to_string()],
+        "0123".to_string()
+    )));
+    assert!(mutations.contains(&ValueMutation::ExtendList(vec!["hosts".to_string()], 3)));
+    assert!(!mutations
+        .iter()
+        .any(|m| matches!(m, ValueMutation::ToggleBool(path) if path[0] != "enabled")));
+
+    Ok(())
+}
+
+#[test]
+fn test_overlay_is_minimal() -> Result<()> {
+    let base = chart_values(LIFE_CHART);
+    let overlay = mutations_overlay(
+        &base,
+        &[
+            ValueMutation::ToggleBool(vec!["frontend".to_string(), "ingress".to_string(), "enabled".to_string()]),
+            ValueMutation::Remove(vec!["api".to_string(), "service".to_string()]),
+            ValueMutation::ExtendList(vec!["frontend".to_string(), "ingress".to_string(), "hosts".to_string()], 2),
+        ],
+    );
+    let expected: Value = serde_yaml::from_str(
+        r#"
+frontend:
+  ingress:
+    enabled: false
+    hosts:
+      - host: simbru.home.ryougi.ca
+        paths: [{path: /, pathType: ImplementationSpecific}]
+      - host: simbru.ryougi.ca
+        paths: [{path: /, pathType: ImplementationSpecific}]
+      - host: simbru.home.ryougi.ca
+        paths: [{path: /, pathType: ImplementationSpecific}]
+      - host: simbru.ryougi.ca
+        paths: [{path: /, pathType: ImplementationSpecific}]
+api:
+  service: null
+"#,
+    )?;
+    assert_eq!(overlay, expected);
+
+    Ok(())
+}
+
+#[test]
+fn test_invariants_catch_dangling_references() -> Result<()> {
+    let docs = parse_yaml_documents(
+        r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: app
+spec:
+  replicas: "two"
+  selector:
+    matchLabels:
+      app: app
+  template:
+    metadata:
+      labels:
+        app: app
+    spec:
+      imagePullSecrets:
+        - name: registry
+      containers:
+        - name: app
+          image: app:1.0
+          envFrom:
+            - secretRef:
+                name: app-secrets
+---
+apiVersion: networking.k8s.io/v1
+kind: Ingress
+metadata:
+  name: app
+spec:
+  rules:
+    - host: app.example.com
+      http:
+        paths:
+          - path: /
+            pathType: Prefix
+            backend:
+              service:
+                name: app
+                port:
+                  number: 80
+"#,
+    )?;
+    let problems = check_render_invariants(&docs, &[]);
+
+    assert_eq!(problems.len(), 3, "{:?}", problems);
+    assert!(problems[0].contains("does not match the Deployment schema"));
+    assert!(problems[1].contains("missing Secret 'app-secrets'"));
+    assert!(problems[2].contains("missing Service 'app'"));
+
+    let problems = check_render_invariants(&docs, &["app-secrets"]);
+    assert_eq!(problems.len(), 2, "{:?}", problems);
+    assert!(!problems.iter().any(|p| p.contains("app-secrets")));
+
+    assert!(is_clean_failure(
+        "Helm template failed: Error: execution error at (life/templates/x.yaml:1:4): api_endpoint is required"
+    ));
+    assert!(!is_clean_failure(
+        "Helm template failed: Error: template: life/templates/x.yaml:5:20: executing \"life/templates/x.yaml\" at <.Values.frontend.ingress.annotations>: nil pointer evaluating interface {}.annotations"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_chart_defaults_are_valid() -> Result<()> {
+    let life = chart_values(LIFE_CHART);
+    assert_eq!(fuzz_render(LIFE_CHART, &life, &[], Some(&life_values()), LIFE_EXTERNAL)?, FuzzOutcome::Valid);
+    let foundry = chart_values(FOUNDRY_CHART);
+    assert_eq!(fuzz_render(FOUNDRY_CHART, &foundry, &[], None, &[])?, FuzzOutcome::Valid);
+
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    // Opt-in: renders the chart once per case. Run with `make fuzz-values`.
+    #[test]
+    #[ignore]
+    fn fuzz_life_values(mutations in mutation_sets(&chart_values(LIFE_CHART))) {
+        assert_not_broken(LIFE_CHART, &chart_values(LIFE_CHART), &mutations, Some(&life_values()), LIFE_EXTERNAL)?;
+    }
+
+    #[test]
+    #[ignore]
+    fn fuzz_foundry_values(mutations in mutation_sets(&chart_values(FOUNDRY_CHART))) {
+        assert_not_broken(FOUNDRY_CHART, &chart_values(FOUNDRY_CHART), &mutations, None, &[])?;
+    }
+}
@@ -0,0 +1,198 @@
+mod common;
+
+use anyhow::Result;
+use common::life_values;
+use helm_tests::values_schema::*;
+use serde_json::json;
+use std::io::Write;
+
+const LIFE_CHART: &str = "../charts/life/";
+
+/// Declares the values life reads without defaulting them in values.yaml
+fn life_refinement() -> serde_json::Value {
+    let string = json!({ "type": "string" });
+    json!({
+        "properties": {
+            "firebase_api_key": string,
+            "firebase_auth_domain": string,
+            "firebase_project_id": string,
+            "firebase_storage_bucket": string,
+            "firebase_messaging_sender_id": { "type": ["string", "integer"] },
+            "firebase_app_id": string,
+            "firebase_vapid_key": string,
+            "api_endpoint": string,
+        }
+    })
+}
+
+#[test]
+fn test_schema_inference() -> Result<()> {
+    let schema = generate_values_schema(
+        r#"
+# Number of pods
+replicaCount: 1
+ratio: 0.5
+service:
+  # One of: ClusterIP, NodePort, LoadBalancer
+  type: ClusterIP
+  port: 80 # Port the Service listens on
+podAnnotations: {}
+hosts:
+  - host: example.com
+    paths:
+      - path: /
+  - host: other.example.com
+    tls: true
+"#,
+    )?;
+
+    assert_eq!(schema["$schema"], "http://json-schema.org/draft-07/schema#");
+    assert_eq!(schema["additionalProperties"], false);
+    let properties = &schema["properties"];
+    assert_eq!(properties["replicaCount"], json!({ "type": "integer", "description": "Number of pods" }));
+    assert_eq!(properties["ratio"]["type"], "number");
+    assert_eq!(
+        properties["service"]["properties"]["type"]["enum"],
+        json!(["ClusterIP", "NodePort", "LoadBalancer"])
+    );
+    assert_eq!(
+        properties["service"]["properties"]["port"]["description"],
+        "Port the Service listens on"
+    );
+    assert_eq!(properties["podAnnotations"], json!({ "type": "object" }));
+
+    let host = &properties["hosts"]["items"];
+    assert_eq!(host["properties"]["tls"]["type"], "boolean");
+    assert_eq!(host["properties"]["paths"]["items"]["properties"]["path"]["type"], "string");
+
+    Ok(())
+}
+
+#[test]
+fn test_life_defaults_match_generated_schema() -> Result<()> {
+    let schema = generate_chart_values_schema(LIFE_CHART, None)?;
+    let errors = validate_chart_overlay(LIFE_CHART, &schema, None, None)?;
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    Ok(())
+}
+
+#[test]
+fn test_set_typos_are_reported() -> Result<()> {
+    let mut refinement = tempfile::NamedTempFile::new()?;
+    write!(refinement, "{}", life_refinement())?;
+    let schema = generate_chart_values_schema(LIFE_CHART, Some(refinement.path()))?;
+
+    let errors = validate_chart_overlay(LIFE_CHART, &schema, None, Some(&life_values()))?;
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let mut values = life_values();
+    let api_key = values.remove("firebase_api_key").unwrap();
+    values.insert("firebase_apikey".to_string(), api_key);
+    let errors = validate_chart_overlay(LIFE_CHART, &schema, None, Some(&values))?;
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].to_string(), "firebase_apikey: is not declared in the values schema");
+
+    let mut values = life_values();
+    values.insert("api.ingress.hosts[0].host".to_string(), "simbru-api.example.com".to_string());
+    values.insert("podAnnotations.example\\.com/owner".to_string(), "simbru".to_string());
+    values.insert("autoscaling".to_string(), "null".to_string());
+    values.insert("api.service.type".to_string(), "null".to_string());
+    let errors = validate_chart_overlay(LIFE_CHART, &schema, None, Some(&values))?;
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    Ok(())
+}
+
+#[test]
+fn test_set_values_follow_helm_paths() -> Result<()> {
+    let mut values = std::collections::HashMap::new();
+    values.insert("hosts[1].host".to_string(), "b.example.com".to_string());
+    values.insert("annotations.example\\.com/owner".to_string(), "simbru".to_string());
+    values.insert("replicas".to_string(), "0123".to_string());
+    values.insert("legacy".to_string(), "null".to_string());
+    assert_eq!(
+        set_values_to_json(&values)?,
+        json!({
+            "hosts": [null, { "host": "b.example.com" }],
+            "annotations": { "example.com/owner": "simbru" },
+            "replicas": "0123",
+            "legacy": null,
+        })
+    );
+
+    let defaults = json!({ "legacy": { "enabled": true }, "replicas": 1 });
+    assert_eq!(
+        coalesce_values(&defaults, &json!({ "legacy": null, "extra": { "gone": null, "kept": 1 } })),
+        json!({ "replicas": 1, "extra": { "kept": 1 } })
+    );
+
+    values.insert("hosts[x]".to_string(), "a".to_string());
+    assert!(set_values_to_json(&values).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_overlay_errors_have_precise_paths() -> Result<()> {
+    let schema = generate_chart_values_schema(LIFE_CHART, None)?;
+    let mut overlay = tempfile::NamedTempFile::new()?;
+    write!(
+        overlay,
+        r#"
+api:
+  replicaCount: "2"
+  ingress:
+    hosts:
+      - host: simbru-api.example.com
+        paths:
+          - path: /
+            pathTyp: Prefix
+autoscaling: null
+"#
+    )?;
+
+    let errors: Vec<String> = validate_chart_overlay(LIFE_CHART, &schema, Some(overlay.path()), None)?
+        .iter()
+        .map(|e| e.to_string())
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            "api.ingress.hosts[0].paths[0].pathTyp: is not declared in the values schema",
+            "api.replicaCount: expected integer, got string",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_refinement_is_a_merge_patch() {
+    let mut schema = json!({
+        "type": "object",
+        "properties": { "replicaCount": { "type": "integer" }, "legacy": { "type": "string" } },
+        "additionalProperties": false
+    });
+    refine_schema(
+        &mut schema,
+        &json!({
+            "properties": { "replicaCount": { "minimum": 1 }, "legacy": null },
+            "required": ["replicaCount"]
+        }),
+    );
+    assert_eq!(
+        schema,
+        json!({
+            "type": "object",
+            "properties": { "replicaCount": { "type": "integer", "minimum": 1 } },
+            "additionalProperties": false,
+            "required": ["replicaCount"]
+        })
+    );
+
+    let errors = validate_values(&schema, &json!({ "replicaCount": 0 }));
+    assert_eq!(errors[0].to_string(), "replicaCount: must be >= 1");
+    let errors = validate_values(&schema, &json!({}));
+    assert_eq!(errors[0].to_string(), "replicaCount: is required");
+}